@@ -0,0 +1,64 @@
+//! Counts the allocations made while recording events with primitive fields,
+//! and spans with and without fields.
+//!
+//! Trees are sent to a [`Sink`], which never reads their fields, so deferred
+//! values are never formatted. Run with `cargo bench --bench allocations`.
+use std::alloc::{GlobalAlloc, Layout, System};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use tracing::{info, info_span};
+use tracing_forest::{processor::Sink, tag::NoTag, ForestLayer};
+use tracing_subscriber::{layer::SubscriberExt, Registry};
+
+struct CountingAllocator;
+
+static ALLOCATIONS: AtomicUsize = AtomicUsize::new(0);
+
+unsafe impl GlobalAlloc for CountingAllocator {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        ALLOCATIONS.fetch_add(1, Ordering::Relaxed);
+        System.alloc(layout)
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        System.dealloc(ptr, layout)
+    }
+}
+
+#[global_allocator]
+static GLOBAL: CountingAllocator = CountingAllocator;
+
+const EVENTS: usize = 10_000;
+
+/// Returns the average number of allocations made per call to `f`.
+fn allocations_per_call(layer: ForestLayer<Sink, NoTag>, f: impl Fn(usize)) -> f64 {
+    let subscriber = Registry::default().with(layer);
+    tracing::subscriber::with_default(subscriber, || {
+        let before = ALLOCATIONS.load(Ordering::Relaxed);
+        for i in 0..EVENTS {
+            f(i);
+        }
+        let after = ALLOCATIONS.load(Ordering::Relaxed);
+        (after - before) as f64 / EVENTS as f64
+    })
+}
+
+fn main() {
+    let event = |i| info!(id = i, ratio = 0.5, ok = true, delta = -1i64, "event");
+    let eager = allocations_per_call(ForestLayer::from(Sink), event);
+    let lazy = allocations_per_call(ForestLayer::from(Sink).lazy_fields(true), event);
+
+    println!("allocations per event (eager fields): {:.2}", eager);
+    println!("allocations per event (lazy fields):  {:.2}", lazy);
+    assert!(lazy < eager, "lazy fields should allocate less");
+
+    // Span fields are always formatted, whether or not a processor shows them.
+    let bare = allocations_per_call(ForestLayer::from(Sink), |_| {
+        info_span!("request").in_scope(|| {})
+    });
+    let with_fields = allocations_per_call(ForestLayer::from(Sink), |i| {
+        info_span!("request", id = i, path = "/login", ok = true).in_scope(|| {})
+    });
+
+    println!("allocations per span (no fields):     {:.2}", bare);
+    println!("allocations per span (3 fields):      {:.2}", with_fields);
+}
@@ -0,0 +1,70 @@
+//! Measures the cost of spans and events that are disabled or discarded.
+//!
+//! Disabled callsites should cost about the same with a `ForestLayer` as with
+//! no subscriber at all.
+use criterion::{black_box, criterion_group, criterion_main, Criterion};
+use tracing::{info, info_span, trace, trace_span, Level};
+use tracing_forest::{processor::Sink, ForestLayer};
+use tracing_subscriber::{filter::LevelFilter, layer::SubscriberExt, Registry};
+
+fn disabled(c: &mut Criterion) {
+    let mut group = c.benchmark_group("disabled");
+
+    group.bench_function("no_subscriber", |b| {
+        b.iter(|| trace!(value = black_box(42), "disabled"))
+    });
+
+    let subscriber = Registry::default()
+        .with(ForestLayer::from(Sink))
+        .with(LevelFilter::INFO);
+    tracing::subscriber::with_default(subscriber, || {
+        group.bench_function("event", |b| {
+            b.iter(|| trace!(value = black_box(42), "disabled"))
+        });
+
+        group.bench_function("span", |b| {
+            b.iter(|| trace_span!("disabled", value = black_box(42)).in_scope(|| {}))
+        });
+    });
+
+    group.finish();
+}
+
+fn discarded(c: &mut Criterion) {
+    let mut group = c.benchmark_group("discarded");
+
+    let subscriber = Registry::default().with(ForestLayer::from(Sink).sample_rate(0.0));
+    tracing::subscriber::with_default(subscriber, || {
+        let span = info_span!("unsampled");
+        let _guard = span.enter();
+
+        group.bench_function("unsampled_event", |b| {
+            b.iter(|| info!(value = black_box(42), "discarded"))
+        });
+    });
+
+    let subscriber = Registry::default().with(ForestLayer::from(Sink).max_children(0));
+    tracing::subscriber::with_default(subscriber, || {
+        let span = info_span!("full");
+        let _guard = span.enter();
+
+        group.bench_function("omitted_event", |b| {
+            b.iter(|| info!(value = black_box(42), "discarded"))
+        });
+    });
+
+    let subscriber = Registry::default().with(ForestLayer::from(Sink).min_event_level(Level::INFO));
+    tracing::subscriber::with_default(subscriber, || {
+        let span = info_span!("filtered");
+        let _guard = span.enter();
+
+        group.bench_function("below_min_level_event", |b| {
+            b.iter(|| trace!(value = black_box(42), "discarded"))
+        });
+    });
+
+    group.finish();
+}
+
+criterion_group!(benches, disabled, discarded);
+criterion_main!(benches);
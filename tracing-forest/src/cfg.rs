@@ -45,3 +45,27 @@ macro_rules! cfg_chrono {
         )*
     }
 }
+
+#[doc(hidden)]
+#[macro_export]
+macro_rules! cfg_metrics {
+    ($($item:item)*) => {
+        $(
+            #[cfg(feature = "metrics")]
+            #[cfg_attr(docsrs, doc(cfg(feature = "metrics")))]
+            $item
+        )*
+    }
+}
+
+#[doc(hidden)]
+#[macro_export]
+macro_rules! cfg_rusqlite {
+    ($($item:item)*) => {
+        $(
+            #[cfg(feature = "rusqlite")]
+            #[cfg_attr(docsrs, doc(cfg(feature = "rusqlite")))]
+            $item
+        )*
+    }
+}
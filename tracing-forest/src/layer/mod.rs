@@ -1,16 +1,21 @@
+//! The [`ForestLayer`] and its configuration options.
 use crate::fail;
 use crate::printer::PrettyPrinter;
-use crate::processor::{Processor, Sink};
+use crate::processor::{self, Processor, Sink};
 use crate::tag::{NoTag, Tag, TagParser};
-use crate::tree::{self, FieldSet, Tree};
+use crate::tree::{self, FieldSet, Primitive, SpanColor, SpanStatus, Tree};
 #[cfg(feature = "chrono")]
-use chrono::Utc;
+use chrono::{DateTime, Utc};
+use std::borrow::Cow;
 use std::fmt;
 use std::io::{self, Write};
-use std::time::Instant;
+use std::sync::Arc;
+#[cfg(feature = "tokio")]
+use std::sync::Once;
+use std::time::{Duration, Instant};
 use tracing::field::{Field, Visit};
-use tracing::span::{Attributes, Id};
-use tracing::{Event, Subscriber};
+use tracing::span::{Attributes, Id, Record};
+use tracing::{Event, Level, Subscriber};
 use tracing_subscriber::layer::{Context, Layer, SubscriberExt};
 use tracing_subscriber::registry::{LookupSpan, Registry, SpanRef};
 use tracing_subscriber::util::SubscriberInitExt;
@@ -18,50 +23,73 @@ use tracing_subscriber::util::SubscriberInitExt;
 use uuid::Uuid;
 #[cfg(feature = "uuid")]
 pub(crate) mod id;
+mod open_spans;
+pub use open_spans::{OpenSpanTracker, SpanSnapshot};
 
 pub(crate) struct OpenedSpan {
     span: tree::Span,
     start: Instant,
+    opened: Instant,
+    last_exit: Option<Instant>,
+    node_starts: Vec<Instant>,
+    omitted: usize,
+    last_flush: Option<Instant>,
 }
 
 impl OpenedSpan {
-    fn new<S>(attrs: &Attributes, _ctx: &Context<S>) -> Self
+    fn new<S>(attrs: &Attributes, config: &Config, _ctx: &Context<S>) -> Self
     where
         S: Subscriber + for<'a> LookupSpan<'a>,
     {
         #[cfg(feature = "uuid")]
-        let uuid = {
-            let mut maybe_uuid = None;
-
-            attrs.record(&mut |field: &Field, value: &dyn fmt::Debug| {
-                if field.name() == "uuid" && maybe_uuid.is_none() {
-                    const LENGTH: usize = 45;
-                    let mut buf = [0u8; LENGTH];
-                    let mut remaining = &mut buf[..];
-
-                    if let Ok(()) = write!(remaining, "{:?}", value) {
-                        let len = LENGTH - remaining.len();
-                        if let Some(parsed) = id::try_parse(&buf[..len]) {
-                            maybe_uuid = Some(parsed);
+        let mut maybe_uuid = None;
+        let mut status = SpanStatus::Unset;
+        let mut instrumented = false;
+        let mut color = None;
+        let mut fields = FieldSet::default();
+
+        attrs.record(
+            &mut |field: &Field, value: &dyn fmt::Debug| match field.name() {
+                #[cfg(feature = "uuid")]
+                "uuid" => {
+                    if maybe_uuid.is_none() {
+                        const LENGTH: usize = 45;
+                        let mut buf = [0u8; LENGTH];
+                        let mut remaining = &mut buf[..];
+
+                        if let Ok(()) = write!(remaining, "{:?}", value) {
+                            let len = LENGTH - remaining.len();
+                            if let Some(parsed) = id::try_parse(&buf[..len]) {
+                                maybe_uuid = Some(parsed);
+                            }
                         }
                     }
                 }
+                "forest.instrumented" => instrumented = format!("{:?}", value) == "true",
+                "forest.color" => {
+                    color = SpanColor::parse(format!("{:?}", value).trim_matches('"'))
+                }
+                key if key == config.status_field => {
+                    if let Some(parsed) = parse_status(value) {
+                        status = parsed;
+                    }
+                }
+                key => fields.push(span_field(key, value, config)),
+            },
+        );
 
-                // record other field-values pairs here...
-            });
-
-            match maybe_uuid {
-                Some(uuid) => uuid,
-                None => match _ctx.lookup_current() {
-                    Some(parent) => parent
-                        .extensions()
-                        .get::<OpenedSpan>()
-                        .expect(fail::OPENED_SPAN_NOT_IN_EXTENSIONS)
-                        .span
-                        .uuid(),
-                    None => Uuid::new_v4(),
-                },
-            }
+        #[cfg(feature = "uuid")]
+        let uuid = match maybe_uuid {
+            Some(uuid) => uuid,
+            None => match _ctx.lookup_current() {
+                Some(parent) => parent
+                    .extensions()
+                    .get::<OpenedSpan>()
+                    .expect(fail::OPENED_SPAN_NOT_IN_EXTENSIONS)
+                    .span
+                    .uuid(),
+                None => Uuid::new_v4(),
+            },
         };
 
         let shared = tree::Shared {
@@ -70,42 +98,210 @@ impl OpenedSpan {
             #[cfg(feature = "uuid")]
             uuid,
             level: *attrs.metadata().level(),
+            recorded_at: tree::since_epoch(),
+            #[cfg(feature = "tokio")]
+            task_id: tokio::task::try_id(),
+        };
+
+        let mut span = tree::Span::new(shared, attrs.metadata().name());
+        span.status = status;
+        span.instrumented = instrumented;
+        span.color = color;
+        span.fields = fields;
+
+        let capacity = match config.max_children {
+            // The omitted marker takes one more slot.
+            Some(max) => config.span_children_capacity.min(max + 1),
+            None => config.span_children_capacity,
         };
+        span.nodes.reserve_exact(capacity);
 
-        let span = tree::Span::new(shared, attrs.metadata().name());
+        let now = Instant::now();
 
         OpenedSpan {
             span,
-            start: Instant::now(),
+            start: now,
+            opened: now,
+            last_exit: None,
+            node_starts: Vec::new(),
+            omitted: 0,
+            last_flush: None,
         }
     }
 
+    fn record(&mut self, values: &Record, config: &Config) {
+        let span = &mut self.span;
+        values.record(
+            &mut |field: &Field, value: &dyn fmt::Debug| match field.name() {
+                #[cfg(feature = "uuid")]
+                "uuid" => {}
+                "forest.instrumented" | "forest.color" => {}
+                key if key == config.status_field => {
+                    if let Some(parsed) = parse_status(value) {
+                        span.status = parsed;
+                    }
+                }
+                key => {
+                    let field = span_field(key, value, config);
+                    match span
+                        .fields
+                        .iter_mut()
+                        .find(|recorded| recorded.key() == key)
+                    {
+                        Some(recorded) => *recorded = field,
+                        None => span.fields.push(field),
+                    }
+                }
+            },
+        );
+    }
+
     fn enter(&mut self) {
         self.start = Instant::now();
+        self.span.activity.enters += 1;
+
+        if let Some(last_exit) = self.last_exit {
+            self.span.idle_duration += self.start - last_exit;
+        }
     }
 
     fn exit(&mut self) {
-        self.span.total_duration += self.start.elapsed();
+        let now = Instant::now();
+        self.span.total_duration += now - self.start;
+        self.span.activity.exits += 1;
+        self.last_exit = Some(now);
+    }
+
+    fn close(self, config: &Config) -> tree::Span {
+        let mut span = self.span;
+
+        #[cfg(feature = "chrono")]
+        {
+            span.end_timestamp = Utc::now();
+        }
+
+        if let Some(span_name_fn) = &config.span_name_fn {
+            let display_name = (span_name_fn.0)(&span, span.fields());
+            if display_name != span.name {
+                span.display_name = Some(display_name.into_owned());
+            }
+        }
+
+        if config.child_order == ChildOrder::StartTime {
+            let mut nodes: Vec<_> = self
+                .node_starts
+                .into_iter()
+                .zip(std::mem::take(&mut span.nodes))
+                .collect();
+            nodes.sort_by_key(|(start, _)| *start);
+            span.nodes = nodes.into_iter().map(|(_, node)| node).collect();
+        }
+
+        if self.omitted > 0 {
+            let shared = tree::Shared {
+                #[cfg(feature = "chrono")]
+                timestamp: span.end_timestamp,
+                recorded_at: tree::since_epoch(),
+                ..span.shared.clone()
+            };
+            span.nodes.push(Tree::Event(tree::Event {
+                shared,
+                #[cfg(feature = "uuid")]
+                span_uuid: Some(span.uuid()),
+                message: Some(format!("…(+{} children omitted)", self.omitted)),
+                tag: None,
+                fields: FieldSet::default(),
+                hidden: false,
+            }));
+        }
+
+        span
     }
 
-    fn close(self) -> tree::Span {
-        self.span
+    /// Returns `true` if the span already holds the maximum number of children,
+    /// in which case the new child is counted as omitted instead.
+    fn omit_child(&mut self, config: &Config) -> bool {
+        match config.max_children {
+            Some(max) if self.span.nodes.len() >= max => {
+                self.omitted += 1;
+                true
+            }
+            _ => false,
+        }
     }
 
-    fn record_event(&mut self, event: tree::Event) {
-        #[cfg(feature = "uuid")]
+    /// Returns `true` if an event in this span would be discarded, in which
+    /// case it's counted as if it were recorded.
+    ///
+    /// This lets the layer skip visiting the fields of events that won't be
+    /// kept.
+    fn discard_event(&mut self, config: &Config) -> bool {
+        let discard = !self.span.sampled
+            || config
+                .max_children
+                .is_some_and(|max| self.span.nodes.len() >= max);
+
+        if discard {
+            self.span.activity.events += 1;
+            if self.span.sampled {
+                self.omitted += 1;
+            }
+        }
+
+        discard
+    }
+
+    fn record_event(&mut self, event: tree::Event, config: &Config) {
+        self.span.activity.events += 1;
+
+        if !self.span.sampled || self.omit_child(config) {
+            return;
+        }
+
+        if config.child_order == ChildOrder::StartTime {
+            self.node_starts.push(Instant::now());
+        }
+
+        let event = Tree::Event(event);
+
+        #[cfg(feature = "chrono")]
         let event = {
             let mut event = event;
-            event.shared.uuid = self.span.uuid();
+            apply_clock_policy(self.span.timestamp(), &mut event, config.clock_policy);
             event
         };
 
-        self.span.nodes.push(Tree::Event(event));
+        self.span.nodes.push(event);
     }
 
-    fn record_span(&mut self, span: tree::Span) {
+    fn record_span(&mut self, span: tree::Span, opened: Instant, config: &Config) {
         self.span.inner_duration += span.total_duration();
-        self.span.nodes.push(Tree::Span(span));
+
+        if !self.span.sampled || self.omit_child(config) {
+            return;
+        }
+
+        if config.child_order == ChildOrder::StartTime {
+            self.node_starts.push(opened);
+        }
+
+        #[cfg(feature = "uuid")]
+        let span = {
+            let mut span = span;
+            span.parent_uuid = Some(self.span.uuid());
+            span
+        };
+
+        let span = Tree::Span(span);
+
+        #[cfg(feature = "chrono")]
+        let span = {
+            let mut span = span;
+            apply_clock_policy(self.span.timestamp(), &mut span, config.clock_policy);
+            span
+        };
+
+        self.span.nodes.push(span);
     }
 
     #[cfg(feature = "uuid")]
@@ -114,23 +310,965 @@ impl OpenedSpan {
     }
 }
 
+fn parse_status(value: &dyn fmt::Debug) -> Option<SpanStatus> {
+    const LENGTH: usize = 8;
+    let mut buf = [0u8; LENGTH];
+    let mut remaining = &mut buf[..];
+
+    write!(remaining, "{:?}", value).ok()?;
+    let len = LENGTH - remaining.len();
+    let value = std::str::from_utf8(&buf[..len]).ok()?;
+
+    SpanStatus::parse(value.trim_matches('"'))
+}
+
+/// The order that a span's children are stored in, set by
+/// [`ForestLayer::child_order`].
+#[derive(Clone, Copy, Debug, Default, Hash, PartialEq, Eq)]
+pub enum ChildOrder {
+    /// Children are ordered by when they were opened. Events are ordered by
+    /// when they occurred.
+    StartTime,
+
+    /// Children are ordered by when they were closed. Events are ordered by
+    /// when they occurred.
+    ///
+    /// This is the default.
+    #[default]
+    CloseTime,
+}
+
+/// How wall-clock timestamps that go backwards are handled, set by
+/// [`ForestLayer::clock_policy`].
+///
+/// If the system clock is stepped backwards, such as by NTP, a child can have
+/// an earlier timestamp than the span it occurred in. Durations are measured
+/// with a monotonic clock and are unaffected, but timestamps are taken from
+/// the wall clock and may not be monotonic within a tree.
+#[cfg(feature = "chrono")]
+#[derive(Clone, Copy, Debug, Default, Hash, PartialEq, Eq)]
+pub enum ClockPolicy {
+    /// Timestamps are left as they were recorded.
+    ///
+    /// This is the default.
+    #[default]
+    Ignore,
+
+    /// Timestamps earlier than the parent span's timestamp are raised to it.
+    /// This applies to every node within a skewed child span.
+    Clamp,
+
+    /// Events with an earlier timestamp than their parent span are given a
+    /// `clock_skew` field with the value `true`. Spans are never flagged, and
+    /// are left as they were recorded.
+    Flag,
+}
+
+/// How spans with duplicate uuids within a tree are handled, set by
+/// [`ForestLayer::uuid_collisions`].
+///
+/// Spans inherit the uuid of their parent unless one is provided with a `uuid`
+/// field, so a span with the same uuid as its parent is never a collision. A
+/// collision is a span that was given a uuid which already belongs to another
+/// span in the same tree.
+#[cfg(feature = "uuid")]
+#[derive(Clone, Copy, Debug, Default, Hash, PartialEq, Eq)]
+pub enum UuidCollisionPolicy {
+    /// Duplicate uuids are left as they were recorded.
+    ///
+    /// This is the default.
+    #[default]
+    Ignore,
+
+    /// The colliding span is given a fresh uuid, which is also applied to
+    /// every node within it that inherited the duplicate uuid.
+    Regenerate,
+
+    /// A tree with a collision is rejected with a [`UuidCollisionError`]
+    /// instead of being processed. See [`Processor::process_rejected`] for
+    /// where it ends up.
+    Error,
+}
+
+/// Error with which a tree is rejected under [`UuidCollisionPolicy::Error`].
+#[cfg(feature = "uuid")]
+#[derive(thiserror::Error, Debug)]
+#[error("span uuid {0} appears more than once in the same tree")]
+pub struct UuidCollisionError(Uuid);
+
+#[cfg(feature = "uuid")]
+impl UuidCollisionError {
+    /// Returns the first uuid that was found more than once.
+    pub fn uuid(&self) -> Uuid {
+        self.0
+    }
+}
+
+/// Apply the [`UuidCollisionPolicy`] to a finalized root `tree`.
+///
+/// Returns the colliding uuid if the policy is [`UuidCollisionPolicy::Error`]
+/// and there is a collision.
+#[cfg(feature = "uuid")]
+fn apply_uuid_policy(tree: &mut Tree, policy: UuidCollisionPolicy) -> Result<(), Uuid> {
+    use std::collections::HashSet;
+
+    fn visit(
+        span: &mut tree::Span,
+        seen: &mut HashSet<Uuid>,
+        policy: UuidCollisionPolicy,
+    ) -> Result<(), Uuid> {
+        let parent_uuid = span.uuid();
+
+        for node in span.nodes.iter_mut() {
+            let child_uuid = match node {
+                Tree::Span(child) => child.uuid(),
+                Tree::Event(_) => continue,
+            };
+
+            if child_uuid != parent_uuid && !seen.insert(child_uuid) {
+                match policy {
+                    UuidCollisionPolicy::Ignore => {}
+                    UuidCollisionPolicy::Regenerate => {
+                        // The parent has a different uuid, so every occurrence
+                        // of the old uuid in this subtree was inherited.
+                        let fresh = Uuid::new_v4();
+                        node.walk_mut(|node| match node {
+                            Tree::Event(event) => {
+                                if event.shared.uuid == child_uuid {
+                                    event.shared.uuid = fresh;
+                                }
+                                if event.span_uuid == Some(child_uuid) {
+                                    event.span_uuid = Some(fresh);
+                                }
+                            }
+                            Tree::Span(span) => {
+                                if span.shared.uuid == child_uuid {
+                                    span.shared.uuid = fresh;
+                                }
+                                if span.parent_uuid == Some(child_uuid) {
+                                    span.parent_uuid = Some(fresh);
+                                }
+                            }
+                        });
+                        seen.insert(fresh);
+                    }
+                    UuidCollisionPolicy::Error => return Err(child_uuid),
+                }
+            }
+
+            if let Tree::Span(child) = node {
+                visit(child, seen, policy)?;
+            }
+        }
+
+        Ok(())
+    }
+
+    if policy == UuidCollisionPolicy::Ignore {
+        return Ok(());
+    }
+
+    match tree {
+        Tree::Span(span) => {
+            let mut seen = HashSet::new();
+            seen.insert(span.uuid());
+            visit(span, &mut seen, policy)
+        }
+        Tree::Event(_) => Ok(()),
+    }
+}
+
+/// Returns `true` with probability `rate`.
+fn sample(rate: f64) -> bool {
+    use std::collections::hash_map::RandomState;
+    use std::hash::{BuildHasher, Hasher};
+
+    if rate >= 1.0 {
+        return true;
+    } else if rate <= 0.0 {
+        return false;
+    }
+
+    // Each `RandomState` is seeded differently, which is enough randomness
+    // for sampling without depending on an RNG.
+    let bits = RandomState::new().build_hasher().finish() >> 11;
+    (bits as f64 / (1u64 << 53) as f64) < rate
+}
+
+/// Format a span field that isn't reserved by the layer, truncating its value
+/// to [`ForestLayer::max_field_len`].
+fn span_field(key: &'static str, value: &dyn fmt::Debug, config: &Config) -> tree::Field {
+    let mut value = format!("{:?}", value);
+    if let Some(max_len) = config.max_field_len {
+        truncate(&mut value, max_len);
+    }
+    tree::Field::new(key, value)
+}
+
+/// Truncate `value` to at most `max_len` bytes, appending a marker with the
+/// number of bytes that were removed.
+fn truncate(value: &mut String, max_len: usize) {
+    if value.len() <= max_len {
+        return;
+    }
+
+    let mut end = max_len;
+    while !value.is_char_boundary(end) {
+        end -= 1;
+    }
+
+    let removed = value.len() - end;
+    value.truncate(end);
+    value.push_str(&format!("…(+{} bytes)", removed));
+}
+
+/// Apply the [`ClockPolicy`] to a child `node` of a span opened at `parent`.
+#[cfg(feature = "chrono")]
+fn apply_clock_policy(parent: DateTime<Utc>, node: &mut Tree, policy: ClockPolicy) {
+    match policy {
+        ClockPolicy::Ignore => {}
+        ClockPolicy::Clamp => node.walk_mut(|node| match node {
+            Tree::Event(event) => {
+                event.shared.timestamp = event.shared.timestamp.max(parent);
+            }
+            Tree::Span(span) => {
+                span.shared.timestamp = span.shared.timestamp.max(parent);
+                span.end_timestamp = span.end_timestamp.max(span.shared.timestamp);
+            }
+        }),
+        ClockPolicy::Flag => {
+            if let Tree::Event(event) = node {
+                if event.shared.timestamp < parent {
+                    event
+                        .fields
+                        .push(tree::Field::new("clock_skew", "true".to_owned()));
+                }
+            }
+        }
+    }
+}
+
+/// The target of events recorded by the panic hook installed by
+/// [`install_panic_hook`].
+pub(crate) const PANIC_TARGET: &str = "tracing_forest::panic";
+
+/// Install a process-wide panic hook that records each panic as an `ERROR`
+/// event in the current span before calling the previously installed hook.
+///
+/// The hook is only installed once, no matter how many times this is called.
+#[cfg(feature = "tokio")]
+pub(crate) fn install_panic_hook() {
+    static INSTALLED: Once = Once::new();
+
+    INSTALLED.call_once(|| {
+        let previous = std::panic::take_hook();
+        std::panic::set_hook(Box::new(move |info| {
+            let payload = info.payload();
+            let message = payload
+                .downcast_ref::<&str>()
+                .copied()
+                .or_else(|| payload.downcast_ref::<String>().map(String::as_str))
+                .unwrap_or("Box<dyn Any>");
+
+            match info.location() {
+                Some(location) => {
+                    tracing::error!(target: PANIC_TARGET, location = %location, "{}", message)
+                }
+                None => tracing::error!(target: PANIC_TARGET, "{}", message),
+            }
+
+            previous(info);
+        }));
+    });
+}
+
+fn panic_tag() -> Tag {
+    Tag::builder().suffix("panic").icon('💥').build()
+}
+
+/// A function deriving the display name of a span, set by
+/// [`ForestLayer::span_name_fn`].
+#[derive(Clone)]
+struct SpanNameFn(Arc<DeriveSpanName>);
+
+type DeriveSpanName =
+    dyn for<'a> Fn(&'a tree::Span, &'a [tree::Field]) -> Cow<'a, str> + Send + Sync;
+
+impl fmt::Debug for SpanNameFn {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("SpanNameFn(..)")
+    }
+}
+
+/// A function computing the tag of a closed span, set by
+/// [`ForestLayer::finalize_tag`].
+#[derive(Clone)]
+struct FinalizeTag(Arc<DeriveTag>);
+
+type DeriveTag = dyn Fn(&tree::Span) -> Option<Tag> + Send + Sync;
+
+impl fmt::Debug for FinalizeTag {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("FinalizeTag(..)")
+    }
+}
+
+/// A callback for completed root trees, registered by [`ForestLayer::on_tree`].
+#[derive(Clone)]
+struct OnTree(Arc<dyn Fn(&Tree) + Send + Sync>);
+
+impl fmt::Debug for OnTree {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("OnTree(..)")
+    }
+}
+
+/// Configuration shared by all spans and events collected by a [`ForestLayer`].
+#[derive(Clone, Debug)]
+struct Config {
+    status_field: &'static str,
+    child_order: ChildOrder,
+    #[cfg(feature = "chrono")]
+    clock_policy: ClockPolicy,
+    max_field_len: Option<usize>,
+    lazy_fields: bool,
+    max_children: Option<usize>,
+    span_children_capacity: usize,
+    flush_threshold: Option<Duration>,
+    sample_rate: f64,
+    min_event_level: Option<Level>,
+    #[cfg(feature = "uuid")]
+    uuid_collisions: UuidCollisionPolicy,
+    span_name_fn: Option<SpanNameFn>,
+    finalize_tag: Option<FinalizeTag>,
+    open_spans: Option<OpenSpanTracker>,
+    on_tree: Vec<OnTree>,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Config {
+            status_field: "otel.status_code",
+            child_order: ChildOrder::default(),
+            #[cfg(feature = "chrono")]
+            clock_policy: ClockPolicy::default(),
+            max_field_len: None,
+            lazy_fields: false,
+            max_children: None,
+            span_children_capacity: 0,
+            flush_threshold: None,
+            sample_rate: 1.0,
+            min_event_level: None,
+            #[cfg(feature = "uuid")]
+            uuid_collisions: UuidCollisionPolicy::default(),
+            span_name_fn: None,
+            finalize_tag: None,
+            open_spans: None,
+            on_tree: Vec::new(),
+        }
+    }
+}
+
 /// A [`Layer`] that collects and processes trace data while preserving
 /// contextual coherence.
+///
+/// A `ForestLayer` is generic over two types:
+/// * `P`, the [`Processor`] that each completed [`Tree`] is sent to.
+/// * `T`, the [`TagParser`] used to attach a [`Tag`] to each event.
+///
+/// The [`worker_task`] and [`capture`] functions construct and configure a
+/// `ForestLayer` automatically. For manual composition within a
+/// [`Registry`], use [`ForestLayer::new`] or [`ForestLayer::from_processor`].
+///
+/// # Performance
+///
+/// Field values are formatted into strings as soon as an event is recorded,
+/// since tracing only lends them to the layer for the duration of the
+/// [`Visit`] call. Integer, float, and boolean values are the exception when
+/// [`ForestLayer::lazy_fields`] is enabled: they're copied, and only formatted
+/// if a processor reads them. The `allocations` benchmark measures the
+/// difference.
+///
+/// Span fields are always formatted and stored in [`Span::fields`] when a span
+/// opens or is recorded, costing one allocation per field, even if no
+/// processor reads them, such as a [`Pretty`] formatter without
+/// [`Pretty::show_span_fields`]. The `allocations` benchmark also measures
+/// this. Spans that don't need to carry data into the tree can leave their
+/// fields out, or record them on an event instead.
+///
+/// [`Span::fields`]: crate::tree::Span::fields
+/// [`Pretty`]: crate::printer::Pretty
+/// [`Pretty::show_span_fields`]: crate::printer::Pretty::show_span_fields
+///
+/// Fields are never visited for events that the layer is going to discard
+/// anyway: events inside a tree that wasn't sampled by
+/// [`ForestLayer::sample_rate`], events past [`ForestLayer::max_children`],
+/// and events below [`ForestLayer::min_event_level`]. The `discarded`
+/// benchmark group measures this path.
+///
+/// Events that are disabled at the callsite, such as by a [`LevelFilter`] or
+/// an `EnvFilter`, never reach the layer at all. Prefer filtering at the
+/// subscriber level over discarding data in a [`Processor`] when events are
+/// expected to be dropped.
+///
+/// # Filtering
+///
+/// Like any other [`Layer`], a `ForestLayer` can be given a per-layer filter
+/// with [`Layer::with_filter`]. This is the recommended way to get `RUST_LOG`
+/// support, since disabled spans and events never reach the layer:
+/// ```
+/// use tracing_forest::{traits::*, util::*};
+/// use tracing_subscriber::{Layer, Registry};
+///
+/// Registry::default()
+///     .with(ForestLayer::default().with_filter(EnvFilter::from_default_env()))
+///     .init();
+/// ```
+///
+/// When using a [`worker_task`] or [`capture`] runtime, [`Builder::build_filtered`]
+/// does this composition.
+///
+/// [`Tag`]: crate::Tag
+/// [`LevelFilter`]: tracing::metadata::LevelFilter
+/// [`worker_task`]: crate::runtime::worker_task
+/// [`capture`]: crate::runtime::capture
+/// [`Builder::build_filtered`]: crate::runtime::Builder::build_filtered
 #[derive(Clone, Debug)]
 pub struct ForestLayer<P, T> {
     processor: P,
     tag: T,
+    config: Config,
 }
 
 impl<P: Processor, T: TagParser> ForestLayer<P, T> {
     /// Create a new `ForestLayer` from a [`Processor`] and a [`TagParser`].
+    ///
+    /// # Examples
+    ///
+    /// Manually composing a `ForestLayer` that captures trees into a buffer:
+    /// ```
+    /// use std::sync::{Arc, Mutex};
+    /// use tracing_forest::{processor, traits::*, util::*, Tag};
+    /// use tracing_subscriber::Registry;
+    ///
+    /// fn tag_admin(event: &Event) -> Option<Tag> {
+    ///     match event.metadata().target() {
+    ///         "admin" => Some(Tag::builder().prefix("admin").level(Level::INFO).build()),
+    ///         _ => None,
+    ///     }
+    /// }
+    ///
+    /// let trees = Arc::new(Mutex::new(Vec::new()));
+    ///
+    /// let processor = processor::from_fn({
+    ///     let trees = trees.clone();
+    ///     move |tree| {
+    ///         trees.lock().unwrap().push(tree);
+    ///         Ok(())
+    ///     }
+    /// });
+    ///
+    /// let subscriber = Registry::default().with(ForestLayer::new(processor, tag_admin));
+    ///
+    /// tracing::subscriber::with_default(subscriber, || {
+    ///     info!(target: "admin", "some info for the admin");
+    /// });
+    ///
+    /// let trees = trees.lock().unwrap();
+    /// let event = trees[0].event().unwrap();
+    /// assert!(event.tag().unwrap().to_string() == "admin.info");
+    /// ```
     pub fn new(processor: P, tag: T) -> Self {
-        ForestLayer { processor, tag }
+        ForestLayer {
+            processor,
+            tag,
+            config: Config::default(),
+        }
+    }
+
+    /// Set the name of the field used to set a span's [`SpanStatus`].
+    ///
+    /// Recording `"ok"` or `"error"` (case insensitive) to this field, either
+    /// when the span is created or later through [`tracing::Span::record`],
+    /// sets the status of the span. Defaults to `otel.status_code`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use tracing::info_span;
+    /// # use tracing_forest::tree::SpanStatus;
+    /// # #[tokio::main(flavor = "current_thread")]
+    /// # async fn main() -> Result<(), Box<dyn std::error::Error>> {
+    /// let logs = tracing_forest::capture()
+    ///     .build_with(|layer| {
+    ///         use tracing_subscriber::layer::SubscriberExt;
+    ///         tracing_subscriber::Registry::default().with(layer.status_field("status"))
+    ///     })
+    ///     .on(async {
+    ///         let span = info_span!("request", status = tracing::field::Empty);
+    ///         // -- snip --
+    ///         span.record("status", "error");
+    ///     })
+    ///     .await;
+    ///
+    /// assert!(logs[0].span()?.status() == SpanStatus::Error);
+    /// # Ok(())
+    /// # }
+    /// ```
+    ///
+    /// [`SpanStatus`]: crate::tree::SpanStatus
+    pub fn status_field(mut self, name: &'static str) -> Self {
+        self.config.status_field = name;
+        self
+    }
+
+    /// Set the order that the children of each span are stored in.
+    ///
+    /// Spans are added to their parent when they close, so by default, the
+    /// children of a span are in [`ChildOrder::CloseTime`] order. When rendering
+    /// timelines of concurrent child spans, [`ChildOrder::StartTime`] is
+    /// typically more useful.
+    pub fn child_order(mut self, order: ChildOrder) -> Self {
+        self.config.child_order = order;
+        self
+    }
+
+    /// Set how timestamps that are earlier than their parent span's timestamp
+    /// are handled.
+    ///
+    /// See [`ClockPolicy`] for details.
+    #[cfg(feature = "chrono")]
+    pub fn clock_policy(mut self, policy: ClockPolicy) -> Self {
+        self.config.clock_policy = policy;
+        self
+    }
+
+    /// Set how spans that were given the uuid of another span in the same tree
+    /// are handled.
+    ///
+    /// Collisions are detected when a root span closes, before the tree is
+    /// sent to the processor. See [`UuidCollisionPolicy`] for details.
+    ///
+    /// # Panics
+    ///
+    /// With [`UuidCollisionPolicy::Error`], closing a root span panics if its
+    /// tree contains a collision and the processor doesn't handle rejected
+    /// trees, such as with a fallback from [`Processor::or`].
+    #[cfg(feature = "uuid")]
+    pub fn uuid_collisions(mut self, policy: UuidCollisionPolicy) -> Self {
+        self.config.uuid_collisions = policy;
+        self
+    }
+
+    /// Set the maximum length in bytes of event and span field values.
+    ///
+    /// Longer values are truncated when they're recorded, so every
+    /// [`Processor`] sees the truncated value. Truncation never splits a
+    /// character, and a marker with the number of removed bytes is appended,
+    /// e.g. `aaaa…(+4900 bytes)`.
+    pub fn max_field_len(mut self, max_len: usize) -> Self {
+        self.config.max_field_len = Some(max_len);
+        self
+    }
+
+    /// Set whether to defer formatting primitive event field values.
+    ///
+    /// When enabled, integer, float, and boolean field values are stored as-is
+    /// and formatted the first time [`Field::value`] is called, so trees that
+    /// are dropped or filtered by a [`Processor`] without reading their fields
+    /// never allocate for them. Other values are still formatted eagerly,
+    /// since they're only borrowed while the event is recorded. A value that
+    /// could exceed [`ForestLayer::max_field_len`] is also formatted eagerly so
+    /// that it's truncated as usual.
+    ///
+    /// Disabled by default.
+    ///
+    /// [`Field::value`]: crate::tree::Field::value
+    pub fn lazy_fields(mut self, lazy: bool) -> Self {
+        self.config.lazy_fields = lazy;
+        self
+    }
+
+    /// Set the maximum number of direct children kept by each span.
+    ///
+    /// Only the first `max` children recorded in a span are kept, and any
+    /// further children are replaced by a single synthetic event with a message
+    /// like `…(+K children omitted)`, appended once the span closes. This bounds
+    /// the size of trees for spans with thousands of children. The limit
+    /// applies to each span independently, including nested ones.
+    ///
+    /// Omitted children still count towards the span's [`activity`] and
+    /// [`inner_duration`].
+    ///
+    /// [`activity`]: crate::tree::Span::activity
+    /// [`inner_duration`]: crate::tree::Span::inner_duration
+    pub fn max_children(mut self, max: usize) -> Self {
+        self.config.max_children = Some(max);
+        self
+    }
+
+    /// Set the number of children to preallocate room for in each span.
+    ///
+    /// Spans with many children otherwise grow their list of children
+    /// repeatedly as they're recorded. This is only an optimization and
+    /// doesn't change which children are recorded, but it costs memory for
+    /// every span, including those with few children. The capacity is capped
+    /// by [`max_children`] if it's set. Defaults to 0.
+    ///
+    /// [`max_children`]: ForestLayer::max_children
+    pub fn span_children_capacity(mut self, capacity: usize) -> Self {
+        self.config.span_children_capacity = capacity;
+        self
+    }
+
+    /// Emit partial snapshots of root spans that stay open longer than
+    /// `threshold`.
+    ///
+    /// Once a root span has been open for `threshold`, the next event or
+    /// closing child span within it sends a snapshot of the span to the
+    /// processor, and then at most once per `threshold` after that. Snapshots
+    /// contain everything recorded so far, along with the chain of spans
+    /// that are currently open from the root down to where the snapshot was
+    /// triggered. Every span in a snapshot is marked as [partial], and shares
+    /// its uuid with the complete span that's still sent when it closes, so
+    /// consumers can correlate them.
+    ///
+    /// [partial]: crate::tree::Span::is_partial
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use std::time::Duration;
+    /// # use tracing::{info, info_span};
+    /// # use tracing_subscriber::layer::SubscriberExt;
+    /// # #[tokio::main(flavor = "current_thread")]
+    /// # async fn main() -> Result<(), Box<dyn std::error::Error>> {
+    /// let logs = tracing_forest::capture()
+    ///     .build_with(|layer| {
+    ///         tracing_subscriber::Registry::default()
+    ///             .with(layer.flush_threshold(Duration::from_millis(10)))
+    ///     })
+    ///     .on(async {
+    ///         info_span!("long_running").in_scope(|| {
+    ///             std::thread::sleep(Duration::from_millis(20));
+    ///             info!("still going");
+    ///         });
+    ///     })
+    ///     .await;
+    ///
+    /// assert!(logs[0].span()?.is_partial());
+    /// assert!(!logs[1].span()?.is_partial());
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn flush_threshold(mut self, threshold: Duration) -> Self {
+        self.config.flush_threshold = Some(threshold);
+        self
+    }
+
+    /// Derive the names that spans are displayed with from the span and its
+    /// fields.
+    ///
+    /// The function is called when each span closes, with the fields that the
+    /// span was created with or later recorded. Values are formatted with
+    /// `Debug`, so string values are quoted unless they're recorded with `%`.
+    /// The derived name is returned by [`Span::display_name`] and used by the
+    /// formatters, while [`Span::name`] keeps the original name.
+    ///
+    /// [`Span::display_name`]: crate::tree::Span::display_name
+    /// [`Span::name`]: crate::tree::Span::name
+    ///
+    /// # Examples
+    ///
+    /// Displaying request spans by their route:
+    /// ```
+    /// # use std::borrow::Cow;
+    /// # use tracing::info_span;
+    /// # use tracing_subscriber::layer::SubscriberExt;
+    /// # #[tokio::main(flavor = "current_thread")]
+    /// # async fn main() -> Result<(), Box<dyn std::error::Error>> {
+    /// let logs = tracing_forest::capture()
+    ///     .build_with(|layer| {
+    ///         tracing_subscriber::Registry::default().with(layer.span_name_fn(|span, fields| {
+    ///             match fields.iter().find(|field| field.key() == "route") {
+    ///                 Some(route) => Cow::Owned(format!("{} {}", span.name(), route.value())),
+    ///                 None => Cow::Borrowed(span.name()),
+    ///             }
+    ///         }))
+    ///     })
+    ///     .on(async {
+    ///         info_span!("request", route = %"/users").in_scope(|| {});
+    ///     })
+    ///     .await;
+    ///
+    /// assert!(logs[0].span()?.display_name() == "request /users");
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn span_name_fn<F>(mut self, f: F) -> Self
+    where
+        F: 'static + for<'a> Fn(&'a tree::Span, &'a [tree::Field]) -> Cow<'a, str> + Send + Sync,
+    {
+        self.config.span_name_fn = Some(SpanNameFn(Arc::new(f)));
+        self
+    }
+
+    /// Set a function that computes the tag of each span once it closes.
+    ///
+    /// Unlike tags set at the call site, the function sees the completed span,
+    /// so the tag can depend on its durations, status, and children. If the
+    /// function returns `Some`, the tag replaces the span's tag, and otherwise
+    /// the span keeps its tag. Spans are finalized from the innermost outwards.
+    ///
+    /// # Examples
+    ///
+    /// Tagging spans that took longer than 100ms as slow:
+    /// ```
+    /// # use std::time::Duration;
+    /// # use tracing::{info_span, Level};
+    /// # use tracing_forest::Tag;
+    /// # use tracing_subscriber::layer::SubscriberExt;
+    /// # #[tokio::main(flavor = "current_thread")]
+    /// # async fn main() -> Result<(), Box<dyn std::error::Error>> {
+    /// let logs = tracing_forest::capture()
+    ///     .build_with(|layer| {
+    ///         tracing_subscriber::Registry::default().with(layer.finalize_tag(|span| {
+    ///             (span.total_duration() > Duration::from_millis(100))
+    ///                 .then(|| Tag::builder().prefix("slow").level(Level::WARN).build())
+    ///         }))
+    ///     })
+    ///     .on(async {
+    ///         info_span!("fast").in_scope(|| {});
+    ///     })
+    ///     .await;
+    ///
+    /// assert!(logs[0].span()?.tag().is_none());
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn finalize_tag<F>(mut self, f: F) -> Self
+    where
+        F: 'static + Fn(&tree::Span) -> Option<Tag> + Send + Sync,
+    {
+        self.config.finalize_tag = Some(FinalizeTag(Arc::new(f)));
+        self
+    }
+
+    /// Keep track of the spans that are currently open in `tracker`.
+    ///
+    /// Spans are added to the tracker when they're first entered, and removed
+    /// when they close. This costs a lock on every span's first enter, record,
+    /// and close.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use tracing::info_span;
+    /// # use tracing_forest::layer::OpenSpanTracker;
+    /// # use tracing_subscriber::layer::SubscriberExt;
+    /// # #[tokio::main(flavor = "current_thread")]
+    /// # async fn main() {
+    /// let tracker = OpenSpanTracker::new();
+    ///
+    /// tracing_forest::capture()
+    ///     .build_with(|layer| {
+    ///         tracing_subscriber::Registry::default().with(layer.track_open_spans(tracker.clone()))
+    ///     })
+    ///     .on(async {
+    ///         info_span!("request").in_scope(|| {
+    ///             assert!(tracker.open_spans()[0].name() == "request");
+    ///         });
+    ///         assert!(tracker.open_spans().is_empty());
+    ///     })
+    ///     .await;
+    /// # }
+    /// ```
+    pub fn track_open_spans(mut self, tracker: OpenSpanTracker) -> Self {
+        self.config.open_spans = Some(tracker);
+        self
+    }
+
+    /// Register a callback that's invoked with each completed root tree,
+    /// before the tree is given to the processor.
+    ///
+    /// This is a lightweight alternative to a [`Processor`] for side effects
+    /// like incrementing a counter or triggering an alert, and doesn't replace
+    /// the layer's processor. Callbacks are invoked in the order they were
+    /// registered. Partial snapshots from a [flush threshold] aren't complete,
+    /// so callbacks aren't invoked on them.
+    ///
+    /// [flush threshold]: ForestLayer::flush_threshold
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use std::sync::atomic::{AtomicUsize, Ordering};
+    /// # use std::sync::Arc;
+    /// # use tracing::error;
+    /// # use tracing_subscriber::layer::SubscriberExt;
+    /// # #[tokio::main(flavor = "current_thread")]
+    /// # async fn main() {
+    /// let errors = Arc::new(AtomicUsize::new(0));
+    ///
+    /// tracing_forest::capture()
+    ///     .build_with(|layer| {
+    ///         let errors = errors.clone();
+    ///         tracing_subscriber::Registry::default().with(layer.on_tree(move |tree| {
+    ///             if tree.count_events(|event| event.level() == tracing::Level::ERROR) > 0 {
+    ///                 errors.fetch_add(1, Ordering::Relaxed);
+    ///             }
+    ///         }))
+    ///     })
+    ///     .on(async {
+    ///         error!("failed");
+    ///     })
+    ///     .await;
+    ///
+    /// assert!(errors.load(Ordering::Relaxed) == 1);
+    /// # }
+    /// ```
+    pub fn on_tree<F>(mut self, f: F) -> Self
+    where
+        F: 'static + Fn(&Tree) + Send + Sync,
+    {
+        self.config.on_tree.push(OnTree(Arc::new(f)));
+        self
+    }
+
+    /// Invokes the tree callbacks on a completed root tree, and then processes
+    /// it.
+    fn finish(&self, tree: Tree) {
+        for on_tree in &self.config.on_tree {
+            (on_tree.0)(&tree);
+        }
+        self.processor.process(tree).expect(fail::PROCESSING_ERROR)
+    }
+
+    /// Set the fraction of trees that are sampled, between `0.0` and `1.0`.
+    ///
+    /// The decision is made once when a root span opens, and every span and
+    /// event within it follows the root's decision, so trees are never partially
+    /// sampled. A root span that isn't sampled is still sent to the processor
+    /// when it closes so that its duration can be observed, but none of its
+    /// children are kept, and its [`sampled`] method returns `false`. Events
+    /// outside of any span are sampled individually, and are dropped entirely
+    /// if they aren't sampled.
+    ///
+    /// This defaults to `1.0`, which samples every tree.
+    ///
+    /// [`sampled`]: crate::tree::Span::sampled
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use tracing::{info, info_span};
+    /// # use tracing_subscriber::layer::SubscriberExt;
+    /// # #[tokio::main(flavor = "current_thread")]
+    /// # async fn main() -> Result<(), Box<dyn std::error::Error>> {
+    /// let logs = tracing_forest::capture()
+    ///     .build_with(|layer| tracing_subscriber::Registry::default().with(layer.sample_rate(0.0)))
+    ///     .on(async {
+    ///         info_span!("request").in_scope(|| {
+    ///             info!("not kept");
+    ///         });
+    ///     })
+    ///     .await;
+    ///
+    /// let request = logs[0].span()?;
+    /// assert!(!request.sampled());
+    /// assert!(request.nodes().is_empty());
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn sample_rate(mut self, rate: f64) -> Self {
+        self.config.sample_rate = rate;
+        self
+    }
+
+    /// Drop events that are more verbose than `level`.
+    ///
+    /// Unlike filtering with a [`Filter`], which hides events from the layer
+    /// entirely, events dropped this way are counted by the span they occurred
+    /// in, as returned by [`Span::filtered_count`]. This shows where spans are
+    /// hiding activity. Spans are kept regardless of their level.
+    ///
+    /// [`Filter`]: tracing_subscriber::layer::Filter
+    /// [`Span::filtered_count`]: crate::tree::Span::filtered_count
+    pub fn min_event_level(mut self, level: Level) -> Self {
+        self.config.min_event_level = Some(level);
+        self
+    }
+
+    /// Send a partial snapshot of the root span of `current` if it's been open
+    /// for longer than the flush threshold since it was opened or last flushed.
+    fn flush_partial<S>(&self, current: &SpanRef<S>)
+    where
+        S: for<'a> LookupSpan<'a>,
+    {
+        let threshold = match self.config.flush_threshold {
+            Some(threshold) => threshold,
+            None => return,
+        };
+
+        let root = current
+            .scope()
+            .last()
+            .expect("a span's scope contains the span itself");
+        let now = Instant::now();
+
+        {
+            let mut extensions = root.extensions_mut();
+            let opened = extensions
+                .get_mut::<OpenedSpan>()
+                .expect(fail::OPENED_SPAN_NOT_IN_EXTENSIONS);
+
+            if now - opened.last_flush.unwrap_or(opened.opened) < threshold {
+                return;
+            }
+            opened.last_flush = Some(now);
+        }
+
+        let mut snapshot: Option<tree::Span> = None;
+        for span_ref in current.scope() {
+            let mut span = span_ref
+                .extensions()
+                .get::<OpenedSpan>()
+                .expect(fail::OPENED_SPAN_NOT_IN_EXTENSIONS)
+                .span
+                .clone();
+            span.partial = true;
+
+            if let Some(child) = snapshot.take() {
+                #[cfg(feature = "uuid")]
+                let child = {
+                    let mut child = child;
+                    child.parent_uuid = Some(span.uuid());
+                    child
+                };
+                span.nodes.push(Tree::Span(child));
+            }
+            snapshot = Some(span);
+        }
+
+        if let Some(snapshot) = snapshot {
+            self.processor
+                .process(Tree::Span(snapshot))
+                .expect(fail::PROCESSING_ERROR);
+        }
     }
 }
 
 impl<P: Processor> From<P> for ForestLayer<P, NoTag> {
     fn from(processor: P) -> Self {
+        ForestLayer::from_processor(processor)
+    }
+}
+
+impl<P: Processor> ForestLayer<P, NoTag> {
+    /// Create a new `ForestLayer` from a [`Processor`] that doesn't tag events.
+    ///
+    /// This is equivalent to `ForestLayer::from(processor)`.
+    pub fn from_processor(processor: P) -> Self {
         ForestLayer::new(processor, NoTag)
     }
 }
@@ -144,10 +1282,7 @@ impl ForestLayer<Sink, NoTag> {
 
 impl Default for ForestLayer<PrettyPrinter, NoTag> {
     fn default() -> Self {
-        ForestLayer {
-            processor: PrettyPrinter::new(),
-            tag: NoTag,
-        }
+        ForestLayer::new(PrettyPrinter::new(), NoTag)
     }
 }
 
@@ -159,23 +1294,150 @@ where
 {
     fn on_new_span(&self, attrs: &Attributes, id: &Id, ctx: Context<S>) {
         let span = ctx.span(id).expect(fail::SPAN_NOT_IN_CONTEXT);
-        let opened = OpenedSpan::new(attrs, &ctx);
+        let mut opened = OpenedSpan::new(attrs, &self.config, &ctx);
+        opened.span.tag = self.tag.parse_span(attrs);
+
+        // Only root spans make a sampling decision, which the rest of the tree
+        // inherits.
+        opened.span.sampled = match span.parent() {
+            Some(parent) => {
+                parent
+                    .extensions()
+                    .get::<OpenedSpan>()
+                    .expect(fail::OPENED_SPAN_NOT_IN_EXTENSIONS)
+                    .span
+                    .sampled
+            }
+            None => sample(self.config.sample_rate),
+        };
 
         let mut extensions = span.extensions_mut();
         extensions.insert(opened);
     }
 
+    fn on_record(&self, id: &Id, values: &Record, ctx: Context<S>) {
+        let span = ctx.span(id).expect(fail::SPAN_NOT_IN_CONTEXT);
+        let mut extensions = span.extensions_mut();
+        let opened = extensions
+            .get_mut::<OpenedSpan>()
+            .expect(fail::OPENED_SPAN_NOT_IN_EXTENSIONS);
+        opened.record(values, &self.config);
+
+        if let Some(tracker) = &self.config.open_spans {
+            tracker.recorded(id.into_u64(), opened.span.fields());
+        }
+    }
+
+    fn on_follows_from(&self, id: &Id, follows: &Id, ctx: Context<S>) {
+        let follows = match ctx.span(follows) {
+            Some(follows) => {
+                follows
+                    .extensions()
+                    .get::<OpenedSpan>()
+                    .expect(fail::OPENED_SPAN_NOT_IN_EXTENSIONS)
+                    .span
+                    .id
+            }
+            None => return,
+        };
+
+        ctx.span(id)
+            .expect(fail::SPAN_NOT_IN_CONTEXT)
+            .extensions_mut()
+            .get_mut::<OpenedSpan>()
+            .expect(fail::OPENED_SPAN_NOT_IN_EXTENSIONS)
+            .span
+            .follows_from
+            .push(follows);
+    }
+
+    // Callsites disabled by a filter never reach the hooks below, since
+    // `tracing` checks `Subscriber::enabled` (or a per-layer filter) first. The
+    // remaining cost is from events that are enabled but discarded, which are
+    // handled before their fields are visited.
     fn on_event(&self, event: &Event, ctx: Context<S>) {
+        // Events re-emitted from finished trees were already collected.
+        if event.metadata().target() == processor::REEMIT_TARGET {
+            return;
+        }
+
         struct Visitor {
             message: Option<String>,
             fields: FieldSet,
             immediate: bool,
+            hidden: bool,
+            max_field_len: Option<usize>,
+            lazy_fields: bool,
+            #[cfg(feature = "uuid")]
+            uuid: Option<Uuid>,
+        }
+
+        impl Visitor {
+            fn record_primitive(&mut self, field: &Field, value: Primitive) {
+                // No primitive is formatted into more than 40 bytes.
+                let fits = self.max_field_len.is_none_or(|max_len| max_len >= 40);
+                match field.name() {
+                    "message" | "forest.id" | "forest.hidden" => {}
+                    key if self.lazy_fields && fits => {
+                        self.fields.push(tree::Field::deferred(key, value));
+                        return;
+                    }
+                    _ => {}
+                }
+                self.record_debug(field, &format_args!("{}", value));
+            }
+
+            /// Sets the event's uuid, or keeps an invalid `value` as an
+            /// ordinary field so that the mistake is visible in the output.
+            #[cfg(feature = "uuid")]
+            fn record_uuid(&mut self, value: &str) {
+                match id::try_parse(value.as_bytes()) {
+                    Some(uuid) => self.uuid = Some(uuid),
+                    None => {
+                        let mut value = format!("{:?}", value);
+                        if let Some(max_len) = self.max_field_len {
+                            truncate(&mut value, max_len);
+                        }
+                        self.fields.push(tree::Field::new("forest.id", value));
+                    }
+                }
+            }
         }
 
         impl Visit for Visitor {
             fn record_bool(&mut self, field: &Field, value: bool) {
                 match field.name() {
                     "immediate" => self.immediate |= value,
+                    "forest.hidden" => self.hidden |= value,
+                    _ => self.record_primitive(field, Primitive::Bool(value)),
+                }
+            }
+
+            fn record_i64(&mut self, field: &Field, value: i64) {
+                self.record_primitive(field, Primitive::I64(value));
+            }
+
+            fn record_u64(&mut self, field: &Field, value: u64) {
+                self.record_primitive(field, Primitive::U64(value));
+            }
+
+            fn record_i128(&mut self, field: &Field, value: i128) {
+                self.record_primitive(field, Primitive::I128(value));
+            }
+
+            fn record_u128(&mut self, field: &Field, value: u128) {
+                self.record_primitive(field, Primitive::U128(value));
+            }
+
+            fn record_f64(&mut self, field: &Field, value: f64) {
+                self.record_primitive(field, Primitive::F64(value));
+            }
+
+            fn record_str(&mut self, field: &Field, value: &str) {
+                match field.name() {
+                    "message" if self.message.is_none() => self.message = Some(value.to_owned()),
+                    #[cfg(feature = "uuid")]
+                    "forest.id" => self.record_uuid(value),
                     _ => self.record_debug(field, &value),
                 }
             }
@@ -184,8 +1446,69 @@ where
                 let value = format!("{:?}", value);
                 match field.name() {
                     "message" if self.message.is_none() => self.message = Some(value),
-                    key => self.fields.push(tree::Field::new(key, value)),
+                    #[cfg(feature = "uuid")]
+                    "forest.id" => self.record_uuid(value.trim_matches('"')),
+                    "forest.hidden" => self.hidden |= value == "true",
+                    key => {
+                        let mut value = value;
+                        if let Some(max_len) = self.max_field_len {
+                            truncate(&mut value, max_len);
+                        }
+                        self.fields.push(tree::Field::new(key, value));
+                    }
+                }
+            }
+
+            #[cfg(all(tracing_unstable, feature = "valuable"))]
+            fn record_value(&mut self, field: &Field, value: valuable::Value<'_>) {
+                let mut debug = format!("{:?}", value);
+                if let Some(max_len) = self.max_field_len {
+                    truncate(&mut debug, max_len);
                 }
+                self.fields
+                    .push(tree::Field::structured(field.name(), debug, value.into()));
+            }
+        }
+
+        // A span that's closing no longer has an `OpenedSpan`, in which case
+        // the event is treated as a root event.
+        let current_span = ctx
+            .event_span(event)
+            .filter(|span| span.extensions().get::<OpenedSpan>().is_some());
+
+        if let Some(level) = self.config.min_event_level {
+            if *event.metadata().level() > level {
+                if let Some(parent) = current_span.as_ref() {
+                    parent
+                        .extensions_mut()
+                        .get_mut::<OpenedSpan>()
+                        .expect(fail::OPENED_SPAN_NOT_IN_EXTENSIONS)
+                        .span
+                        .filtered_event_count += 1;
+                }
+                return;
+            }
+        }
+
+        let sampled = current_span.is_some() || sample(self.config.sample_rate);
+
+        // Events that may be written immediately are always visited.
+        if event.metadata().fields().field("immediate").is_none() {
+            match current_span.as_ref() {
+                Some(parent) => {
+                    let discard = parent
+                        .extensions_mut()
+                        .get_mut::<OpenedSpan>()
+                        .expect(fail::OPENED_SPAN_NOT_IN_EXTENSIONS)
+                        .discard_event(&self.config);
+
+                    if discard {
+                        self.flush_partial(parent);
+                        return;
+                    }
+                }
+                None if !sampled => return,
+                None => {}
             }
         }
 
@@ -193,51 +1516,81 @@ where
             message: None,
             fields: FieldSet::default(),
             immediate: false,
+            hidden: false,
+            max_field_len: self.config.max_field_len,
+            lazy_fields: self.config.lazy_fields,
+            #[cfg(feature = "uuid")]
+            uuid: None,
         };
 
         event.record(&mut visitor);
 
+        #[cfg(feature = "uuid")]
+        let span_uuid = current_span.as_ref().map(|parent| {
+            parent
+                .extensions()
+                .get::<OpenedSpan>()
+                .expect(fail::OPENED_SPAN_NOT_IN_EXTENSIONS)
+                .uuid()
+        });
+
+        #[cfg(feature = "uuid")]
+        let uuid = visitor.uuid.or(span_uuid).unwrap_or_else(Uuid::nil);
+
         let shared = tree::Shared {
             #[cfg(feature = "uuid")]
-            uuid: Uuid::nil(),
+            uuid,
             #[cfg(feature = "chrono")]
             timestamp: Utc::now(),
             level: *event.metadata().level(),
+            recorded_at: tree::since_epoch(),
+            #[cfg(feature = "tokio")]
+            task_id: tokio::task::try_id(),
         };
 
         let tree_event = tree::Event {
             shared,
+            #[cfg(feature = "uuid")]
+            span_uuid,
             message: visitor.message,
-            tag: self.tag.parse(event),
+            tag: if event.metadata().target() == PANIC_TARGET {
+                Some(panic_tag())
+            } else {
+                self.tag.parse(event)
+            },
             fields: visitor.fields,
+            hidden: visitor.hidden,
         };
 
-        let current_span = ctx.event_span(event);
-
-        if visitor.immediate {
+        if visitor.immediate && !visitor.hidden {
             write_immediate(&tree_event, current_span.as_ref()).expect("writing urgent failed");
         }
 
         match current_span.as_ref() {
-            Some(parent) => parent
-                .extensions_mut()
-                .get_mut::<OpenedSpan>()
-                .expect(fail::OPENED_SPAN_NOT_IN_EXTENSIONS)
-                .record_event(tree_event),
-            None => self
-                .processor
-                .process(Tree::Event(tree_event))
-                .expect(fail::PROCESSING_ERROR),
+            Some(parent) => {
+                parent
+                    .extensions_mut()
+                    .get_mut::<OpenedSpan>()
+                    .expect(fail::OPENED_SPAN_NOT_IN_EXTENSIONS)
+                    .record_event(tree_event, &self.config);
+                self.flush_partial(parent);
+            }
+            None if sampled => self.finish(Tree::Event(tree_event)),
+            None => {}
         }
     }
 
     fn on_enter(&self, id: &Id, ctx: Context<S>) {
-        ctx.span(id)
-            .expect(fail::SPAN_NOT_IN_CONTEXT)
-            .extensions_mut()
+        let span = ctx.span(id).expect(fail::SPAN_NOT_IN_CONTEXT);
+        let mut extensions = span.extensions_mut();
+        let opened = extensions
             .get_mut::<OpenedSpan>()
-            .expect(fail::OPENED_SPAN_NOT_IN_EXTENSIONS)
-            .enter();
+            .expect(fail::OPENED_SPAN_NOT_IN_EXTENSIONS);
+        opened.enter();
+
+        if let Some(tracker) = &self.config.open_spans {
+            tracker.entered(id.into_u64(), &opened.span);
+        }
     }
 
     fn on_exit(&self, id: &Id, ctx: Context<S>) {
@@ -252,11 +1605,16 @@ where
     fn on_close(&self, id: Id, ctx: Context<S>) {
         let span_ref = ctx.span(&id).expect(fail::SPAN_NOT_IN_CONTEXT);
 
-        let mut span = span_ref
+        let opened = span_ref
             .extensions_mut()
             .remove::<OpenedSpan>()
-            .expect(fail::OPENED_SPAN_NOT_IN_EXTENSIONS)
-            .close();
+            .expect(fail::OPENED_SPAN_NOT_IN_EXTENSIONS);
+        let opened_at = opened.opened;
+
+        if let Some(tracker) = &self.config.open_spans {
+            tracker.closed(id.into_u64());
+        }
+        let mut span = opened.close(&self.config);
 
         // Ensure that the total duration is at least as much as the inner
         // duration. This is caused by when a child span is manually passed
@@ -269,16 +1627,39 @@ where
             span.total_duration = span.inner_duration;
         }
 
+        if let Some(finalize_tag) = &self.config.finalize_tag {
+            if let Some(tag) = (finalize_tag.0)(&span) {
+                span.tag = Some(tag);
+            }
+        }
+
         match span_ref.parent() {
-            Some(parent) => parent
-                .extensions_mut()
-                .get_mut::<OpenedSpan>()
-                .expect(fail::OPENED_SPAN_NOT_IN_EXTENSIONS)
-                .record_span(span),
-            None => self
-                .processor
-                .process(Tree::Span(span))
-                .expect(fail::PROCESSING_ERROR),
+            Some(parent) => {
+                parent
+                    .extensions_mut()
+                    .get_mut::<OpenedSpan>()
+                    .expect(fail::OPENED_SPAN_NOT_IN_EXTENSIONS)
+                    .record_span(span, opened_at, &self.config);
+                self.flush_partial(&parent);
+            }
+            None => {
+                let tree = Tree::Span(span);
+
+                #[cfg(feature = "uuid")]
+                let tree = {
+                    let mut tree = tree;
+                    if let Err(uuid) = apply_uuid_policy(&mut tree, self.config.uuid_collisions) {
+                        let err = processor::error(tree, Box::new(UuidCollisionError(uuid)));
+                        self.processor
+                            .process_rejected(err)
+                            .expect(fail::PROCESSING_ERROR);
+                        return;
+                    }
+                    tree
+                };
+
+                self.finish(tree)
+            }
         }
     }
 }
@@ -363,3 +1744,81 @@ where
 pub fn init() {
     Registry::default().with(ForestLayer::default()).init();
 }
+
+#[cfg(all(test, feature = "chrono"))]
+mod tests {
+    use super::*;
+    use chrono::Duration;
+    use tracing::Level;
+
+    fn shared(timestamp: DateTime<Utc>) -> tree::Shared {
+        tree::Shared {
+            #[cfg(feature = "uuid")]
+            uuid: Uuid::nil(),
+            timestamp,
+            level: Level::INFO,
+            recorded_at: tree::since_epoch(),
+            #[cfg(feature = "tokio")]
+            task_id: None,
+        }
+    }
+
+    fn event(timestamp: DateTime<Utc>) -> Tree {
+        Tree::Event(tree::Event {
+            shared: shared(timestamp),
+            #[cfg(feature = "uuid")]
+            span_uuid: None,
+            message: None,
+            tag: None,
+            fields: FieldSet::default(),
+            hidden: false,
+        })
+    }
+
+    #[test]
+    fn test_clock_policy_ignore() {
+        let parent = Utc::now();
+        let mut node = event(parent - Duration::seconds(5));
+
+        apply_clock_policy(parent, &mut node, ClockPolicy::Ignore);
+
+        let event = node.event().unwrap();
+        assert!(event.timestamp() == parent - Duration::seconds(5));
+        assert!(event.fields().is_empty());
+    }
+
+    #[test]
+    fn test_clock_policy_clamp() {
+        let parent = Utc::now();
+        let skewed = parent - Duration::seconds(5);
+
+        let mut span = tree::Span::new(shared(skewed), "skewed");
+        span.nodes.push(event(skewed));
+        let mut node = Tree::Span(span);
+
+        apply_clock_policy(parent, &mut node, ClockPolicy::Clamp);
+
+        let span = node.span().unwrap();
+        assert!(span.timestamp() == parent);
+        assert!(span.end_timestamp() >= span.timestamp());
+        assert!(span.nodes()[0].event().unwrap().timestamp() == parent);
+    }
+
+    #[test]
+    fn test_clock_policy_flag() {
+        let parent = Utc::now();
+
+        let mut skewed = event(parent - Duration::seconds(5));
+        apply_clock_policy(parent, &mut skewed, ClockPolicy::Flag);
+
+        let fields = skewed.event().unwrap().fields();
+        assert!(fields.len() == 1);
+        assert!(fields[0].key() == "clock_skew");
+        assert!(fields[0].value() == "true");
+
+        let mut in_order = event(parent + Duration::seconds(5));
+        apply_clock_policy(parent, &mut in_order, ClockPolicy::Flag);
+
+        assert!(in_order.event().unwrap().fields().is_empty());
+    }
+}
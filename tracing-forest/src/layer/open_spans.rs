@@ -0,0 +1,122 @@
+use crate::tree::{self, Shared};
+#[cfg(feature = "chrono")]
+use chrono::{DateTime, Utc};
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex, PoisonError};
+use std::time::Duration;
+use tracing::Level;
+#[cfg(feature = "uuid")]
+use uuid::Uuid;
+
+/// A handle to the spans that are currently open in a [`ForestLayer`], set by
+/// [`ForestLayer::track_open_spans`].
+///
+/// A span is open once it's been entered, and until it closes. This is useful
+/// for debugging deadlocks and hangs, where the spans that never close are the
+/// ones that are stuck. Clones of an `OpenSpanTracker` share the same spans, so
+/// a clone can be kept to inspect them after giving one to a layer.
+///
+/// # Examples
+///
+/// ```
+/// use tracing_forest::layer::OpenSpanTracker;
+/// use tracing_forest::ForestLayer;
+///
+/// let tracker = OpenSpanTracker::new();
+/// let layer = ForestLayer::default().track_open_spans(tracker.clone());
+///
+/// assert!(tracker.open_spans().is_empty());
+/// ```
+///
+/// [`ForestLayer`]: crate::layer::ForestLayer
+/// [`ForestLayer::track_open_spans`]: crate::layer::ForestLayer::track_open_spans
+#[derive(Clone, Debug, Default)]
+pub struct OpenSpanTracker {
+    spans: Arc<Mutex<HashMap<u64, SpanSnapshot>>>,
+}
+
+/// A shallow snapshot of an open span, returned by
+/// [`OpenSpanTracker::open_spans`].
+///
+/// Snapshots don't contain the span's children.
+#[derive(Clone, Debug)]
+pub struct SpanSnapshot {
+    shared: Shared,
+    name: &'static str,
+    fields: Vec<tree::Field>,
+}
+
+impl OpenSpanTracker {
+    /// Create a new `OpenSpanTracker` without any open spans.
+    pub fn new() -> Self {
+        OpenSpanTracker::default()
+    }
+
+    /// Returns snapshots of the spans that are currently open, in the order
+    /// they were opened.
+    pub fn open_spans(&self) -> Vec<SpanSnapshot> {
+        let mut spans: Vec<SpanSnapshot> = self.lock().values().cloned().collect();
+        spans.sort_by_key(|span| span.shared.recorded_at);
+        spans
+    }
+
+    pub(crate) fn entered(&self, id: u64, span: &tree::Span) {
+        self.lock().entry(id).or_insert_with(|| SpanSnapshot {
+            shared: span.shared.clone(),
+            name: span.name,
+            fields: span.fields().to_vec(),
+        });
+    }
+
+    pub(crate) fn recorded(&self, id: u64, fields: &[tree::Field]) {
+        if let Some(snapshot) = self.lock().get_mut(&id) {
+            snapshot.fields = fields.to_vec();
+        }
+    }
+
+    pub(crate) fn closed(&self, id: u64) {
+        self.lock().remove(&id);
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, HashMap<u64, SpanSnapshot>> {
+        self.spans.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+}
+
+impl SpanSnapshot {
+    /// Returns the span's name.
+    pub fn name(&self) -> &'static str {
+        self.name
+    }
+
+    /// Returns the span's [`Level`].
+    pub fn level(&self) -> Level {
+        self.shared.level()
+    }
+
+    /// Returns the span's [`Uuid`].
+    #[cfg(feature = "uuid")]
+    pub fn uuid(&self) -> Uuid {
+        self.shared.uuid()
+    }
+
+    /// Returns the [`DateTime`] that the span was opened at.
+    #[cfg(feature = "chrono")]
+    pub fn timestamp(&self) -> DateTime<Utc> {
+        self.shared.timestamp()
+    }
+
+    /// Returns when the span was opened, as an offset from the first time
+    /// anything was recorded in the process.
+    ///
+    /// See [`Shared::recorded_at`] for details.
+    pub fn recorded_at(&self) -> Duration {
+        self.shared.recorded_at()
+    }
+
+    /// Returns the span's fields as of the snapshot, including values recorded
+    /// after the span was created.
+    pub fn fields(&self) -> &[tree::Field] {
+        &self.fields
+    }
+}
@@ -39,11 +39,9 @@
 //! ```
 //! Then, add [`tracing_forest::init`](crate::init) to your main function:
 //! ```
-//! fn main() {
-//!     // Initialize a default `ForestLayer` subscriber
-//!     tracing_forest::init();
-//!     // ...
-//! }
+//! // Initialize a default `ForestLayer` subscriber
+//! tracing_forest::init();
+//! // ...
 //! ```
 //! This crate also provides tools for much more advanced configurations:
 //! ```
@@ -203,6 +201,18 @@
 //! let span = info_span!("my_span", uuid = %id);
 //! ```
 //!
+//! Similarly, an event's `Uuid` can be set explicitly through a field named
+//! `forest.id`. Unlike other fields, it won't appear in the event's fields,
+//! unless it isn't a valid `Uuid`, in which case the event keeps its default
+//! id and the value is kept as an ordinary field:
+//! ```
+//! # use tracing::info;
+//! # use uuid::Uuid;
+//! let id = Uuid::new_v4();
+//!
+//! info!(forest.id = %id, "correlated with an external system");
+//! ```
+//!
 //! It can also be retreived from the most recently entered span with
 //! [`tracing_forest::id`](crate::id):
 //! ```
@@ -259,14 +269,36 @@
 //! * `ansi`: Enables ANSI terminal colors.
 //! * `smallvec`: Enables some performance optimizations.
 //! * `tokio`: Enables [`worker_task`] and [`capture`].
-//! * `serde`: Enables log trees to be serialized, which is [useful for formatting][serde_fmt].
+//! * `serde`: Enables log trees to be serialized, which is [useful for formatting][serde_fmt],
+//!   and deserialized again. Deserializing leaks each distinct span name, field key, and
+//!   tag, up to a fixed limit.
+//! * `json`: Enables `serde` and [`Forest::to_json`] and [`Forest::from_json`] for persisting
+//!   captured forests as JSON.
+//! * `yaml`: Enables `serde` and the [`Yaml`] formatter.
 //! * `env-filter`: Re-exports [`EnvFilter`] from the [`util`] module.
+//! * `petgraph`: Enables converting log trees into [`petgraph`] graphs with [`Tree::to_digraph`].
+//! * `terminal_size`: Enables [`Pretty`] to wrap fields at the width of the terminal.
+//! * `metrics`: Enables [`MetricsProcessor`] for recording trees to the [`metrics`] facade.
+//! * `valuable`: Enables recording [`valuable`] values as nested [`FieldValue`]s, which
+//!   requires building with `RUSTFLAGS="--cfg tracing_unstable"`.
+//! * `rusqlite`: Enables [`SqliteProcessor`] for storing trees in a SQLite database.
+//! * `regex`: Enables [`Event::message_matches`] for searching messages with a [`Regex`].
 //!
 //! By default, only `smallvec` in enabled.
 //!
 //! [`Uuid`]: uuid::Uuid
 //! [serde_fmt]: crate::printer::Formatter#examples
 //! [`EnvFilter`]: tracing_subscriber::EnvFilter
+//! [`Tree::to_digraph`]: crate::tree::Tree::to_digraph
+//! [`Pretty`]: crate::printer::Pretty
+//! [`MetricsProcessor`]: crate::processor::MetricsProcessor
+//! [`FieldValue`]: crate::tree::FieldValue
+//! [`SqliteProcessor`]: crate::processor::SqliteProcessor
+//! [`Yaml`]: crate::printer::Yaml
+//! [`Forest::to_json`]: crate::tree::Forest::to_json
+//! [`Forest::from_json`]: crate::tree::Forest::from_json
+//! [`Event::message_matches`]: crate::tree::Event::message_matches
+//! [`Regex`]: regex::Regex
 #![doc(issue_tracker_base_url = "https://github.com/QnnOkabayashi/tracing-forest/issues")]
 #![cfg_attr(
     docsrs,
@@ -278,14 +310,15 @@
     deny(rustdoc::broken_intra_doc_links),
 )]
 #![warn(missing_docs)]
+pub mod layer;
 pub mod printer;
 pub mod processor;
 pub mod tag;
+pub mod test;
 pub mod tree;
 #[macro_use]
 mod cfg;
 mod fail;
-mod layer;
 
 pub use layer::{init, ForestLayer};
 pub use printer::{Formatter, PrettyPrinter, Printer};
@@ -0,0 +1,207 @@
+use crate::printer::{EscapePolicy, Formatter};
+use crate::tree::{Event, Tree};
+use std::borrow::Cow;
+use std::fmt::{self, Write};
+
+/// Format logs as newline-delimited JSON for the [Elasticsearch bulk API].
+///
+/// Each event is written as two lines: an action line that indexes the
+/// document, followed by the document itself. Spans don't produce documents
+/// of their own. Instead, each document carries a `span` array containing the
+/// names of its enclosing spans, from the root inwards.
+///
+/// # Examples
+///
+/// ```json
+/// {"index":{"_index":"logs"}}
+/// {"level":"INFO","tag":"info","message":"Some filter info...","span":["try_from_entry_ro","server::internal_search"],"fields":{}}
+/// {"index":{"_index":"logs"}}
+/// {"level":"TRACE","tag":"trace","message":"Finished!","span":["try_from_entry_ro"],"fields":{"count":"3"}}
+/// ```
+///
+/// With the `uuid` and `chrono` features, documents also contain the `uuid`
+/// of the event and its `@timestamp`. Field values are written as strings.
+///
+/// # Configuration
+///
+/// `EsBulk` provides builder methods for configuring its output:
+/// * [`index`](EsBulk::index) sets the index that documents are written to.
+/// * [`index_from_root_span`](EsBulk::index_from_root_span) names the index
+///   after the root span of each tree instead.
+///
+/// [Elasticsearch bulk API]: https://www.elastic.co/guide/en/elasticsearch/reference/current/docs-bulk.html
+#[derive(Clone, Debug)]
+pub struct EsBulk {
+    index: Cow<'static, str>,
+    index_from_root_span: bool,
+}
+
+impl Default for EsBulk {
+    fn default() -> Self {
+        EsBulk::new()
+    }
+}
+
+impl Formatter for EsBulk {
+    type Error = fmt::Error;
+
+    fn fmt(&self, tree: &Tree) -> Result<String, fmt::Error> {
+        let mut writer = String::with_capacity(256);
+
+        let index = match tree {
+            Tree::Span(span) if self.index_from_root_span => index_name(span.display_name())
+                .map(Cow::Owned)
+                .unwrap_or(Cow::Borrowed(&*self.index)),
+            _ => Cow::Borrowed(&*self.index),
+        };
+
+        self.format_tree(tree, &index, &mut Vec::new(), &mut writer)?;
+
+        Ok(writer)
+    }
+}
+
+impl EsBulk {
+    /// Returns a new `EsBulk` formatter with the default configuration, which
+    /// writes to the `logs` index.
+    pub const fn new() -> Self {
+        EsBulk {
+            index: Cow::Borrowed("logs"),
+            index_from_root_span: false,
+        }
+    }
+
+    /// Set the index that documents are written to.
+    ///
+    /// Defaults to `logs`. If [`index_from_root_span`] is enabled, this is only
+    /// used for events that aren't in a span.
+    ///
+    /// [`index_from_root_span`]: EsBulk::index_from_root_span
+    pub fn index(mut self, index: impl Into<Cow<'static, str>>) -> Self {
+        self.index = index.into();
+        self
+    }
+
+    /// Set whether the documents of each tree are written to an index named
+    /// after its root span.
+    ///
+    /// Since Elasticsearch requires lowercase index names without spaces or
+    /// certain punctuation, the span name is lowercased and any disallowed
+    /// characters are replaced with `_`, so `Server::Search` becomes
+    /// `server__search`. Leading `_`, `-`, and `+` characters are removed and
+    /// names are cut to 255 bytes. Trees whose span name leaves no valid index
+    /// name, such as `_` or `..`, are written to the [`index`] instead.
+    ///
+    /// [`index`]: EsBulk::index
+    pub fn index_from_root_span(mut self, index_from_root_span: bool) -> Self {
+        self.index_from_root_span = index_from_root_span;
+        self
+    }
+
+    fn format_tree<'a>(
+        &self,
+        tree: &'a Tree,
+        index: &str,
+        path: &mut Vec<&'a str>,
+        writer: &mut String,
+    ) -> fmt::Result {
+        match tree {
+            Tree::Event(event) if event.is_hidden() => Ok(()),
+            Tree::Event(event) => EsBulk::format_event(event, index, path, writer),
+            Tree::Span(span) => {
+                path.push(span.display_name());
+                for tree in span.nodes() {
+                    self.format_tree(tree, index, path, writer)?;
+                }
+                path.pop();
+                Ok(())
+            }
+        }
+    }
+
+    fn format_event(event: &Event, index: &str, path: &[&str], writer: &mut String) -> fmt::Result {
+        writer.write_str("{\"index\":{\"_index\":")?;
+        write_string(index, writer)?;
+        writer.write_str("}}\n{")?;
+
+        #[cfg(feature = "uuid")]
+        write!(writer, "\"uuid\":\"{}\",", event.uuid())?;
+
+        #[cfg(feature = "chrono")]
+        write!(
+            writer,
+            "\"@timestamp\":\"{}\",",
+            event.timestamp().to_rfc3339()
+        )?;
+
+        write!(writer, "\"level\":\"{}\"", event.level())?;
+
+        if let Some(tag) = event.tag() {
+            writer.write_str(",\"tag\":")?;
+            write_string(&tag.to_string(), writer)?;
+        }
+
+        if let Some(message) = event.message() {
+            writer.write_str(",\"message\":")?;
+            write_string(message, writer)?;
+        }
+
+        writer.write_str(",\"span\":[")?;
+        for (i, name) in path.iter().enumerate() {
+            if i > 0 {
+                writer.write_char(',')?;
+            }
+            write_string(name, writer)?;
+        }
+
+        writer.write_str("],\"fields\":{")?;
+        for (i, field) in event.fields().iter().enumerate() {
+            if i > 0 {
+                writer.write_char(',')?;
+            }
+            write_string(field.key(), writer)?;
+            writer.write_char(':')?;
+            write_string(field.value(), writer)?;
+        }
+
+        writer.write_str("}}\n")
+    }
+}
+
+fn write_string(value: &str, writer: &mut String) -> fmt::Result {
+    writer.write_char('"')?;
+    EscapePolicy::write_json(value, writer)?;
+    writer.write_char('"')
+}
+
+/// The longest index name that Elasticsearch accepts, in bytes.
+const MAX_INDEX_LEN: usize = 255;
+
+/// Converts a span name into a valid Elasticsearch index name, if there is one.
+fn index_name(name: &str) -> Option<String> {
+    let mut index = String::with_capacity(name.len());
+    for c in name.chars() {
+        match c {
+            '\\' | '/' | '*' | '?' | '"' | '<' | '>' | '|' | ' ' | ',' | '#' | ':' => {
+                index.push('_')
+            }
+            c => index.extend(c.to_lowercase()),
+        }
+    }
+
+    let start = index.len() - index.trim_start_matches(['_', '-', '+']).len();
+    index.drain(..start);
+
+    if index.len() > MAX_INDEX_LEN {
+        let mut end = MAX_INDEX_LEN;
+        while !index.is_char_boundary(end) {
+            end -= 1;
+        }
+        index.truncate(end);
+    }
+
+    match index.as_str() {
+        "" | "." | ".." => None,
+        _ => Some(index),
+    }
+}
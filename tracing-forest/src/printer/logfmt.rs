@@ -0,0 +1,253 @@
+use crate::printer::{EscapePolicy, FieldSort, Formatter};
+use crate::tree::{Event, Shared, Span, Tree};
+use std::fmt::{self, Write};
+
+/// Format logs as flat [logfmt] lines, one per event.
+///
+/// Spans don't produce lines of their own by default. Instead, each event
+/// carries a `span` key containing the names of its enclosing spans, joined
+/// by `/`, so the hierarchy can still be recovered by line-oriented tools.
+///
+/// # Examples
+///
+/// ```log
+/// level=INFO tag=info msg="Some filter info..." span=try_from_entry_ro/server::internal_search
+/// level=ERROR tag=admin.error msg="On no, an admin error occurred :(" span=try_from_entry_ro/be::search
+/// level=TRACE tag=trace msg=Finished! span=try_from_entry_ro
+/// ```
+///
+/// # Configuration
+///
+/// `Logfmt` provides builder methods for configuring its output:
+/// * [`span_events`](Logfmt::span_events) writes lines when spans open and close.
+/// * [`escape`](Logfmt::escape) sets how messages and field values are escaped.
+/// * [`sort_fields`](Logfmt::sort_fields) sets the order fields are written in.
+///
+/// [logfmt]: https://brandur.org/logfmt
+#[derive(Clone, Debug, Default)]
+pub struct Logfmt {
+    span_events: bool,
+    escape: EscapePolicy,
+    sort_fields: FieldSort,
+}
+
+impl Formatter for Logfmt {
+    type Error = fmt::Error;
+
+    fn fmt(&self, tree: &Tree) -> Result<String, fmt::Error> {
+        let mut writer = String::with_capacity(256);
+
+        self.format_tree(tree, &mut Vec::new(), &mut writer)?;
+
+        Ok(writer)
+    }
+}
+
+impl Logfmt {
+    /// Returns a new `Logfmt` formatter with the default configuration.
+    pub const fn new() -> Self {
+        Logfmt {
+            span_events: false,
+            escape: EscapePolicy::Json,
+            sort_fields: FieldSort::Insertion,
+        }
+    }
+
+    /// Set whether synthetic `span_open` and `span_close` lines are written
+    /// around the events of each span.
+    ///
+    /// The close line carries the span's total duration in nanoseconds, which
+    /// allows strictly flat systems to reconstruct the hierarchy:
+    /// ```log
+    /// level=INFO msg=span_open span=my_span
+    /// level=INFO tag=info msg=hello span=my_span
+    /// level=INFO msg=span_close span=my_span duration_ns=15300
+    /// ```
+    pub fn span_events(mut self, span_events: bool) -> Self {
+        self.span_events = span_events;
+        self
+    }
+
+    /// Set how messages and field values are escaped.
+    ///
+    /// Defaults to [`EscapePolicy::Json`], which guarantees that each line
+    /// corresponds to exactly one event. With [`EscapePolicy::None`], values
+    /// containing newlines are written verbatim and will split lines.
+    pub fn escape(mut self, escape: EscapePolicy) -> Self {
+        self.escape = escape;
+        self
+    }
+
+    /// Set the order that an event's fields are written in.
+    ///
+    /// Defaults to [`FieldSort::Insertion`]. [`FieldSort::Alphabetical`] makes
+    /// the output deterministic regardless of the order fields were recorded in.
+    pub fn sort_fields(mut self, sort_fields: FieldSort) -> Self {
+        self.sort_fields = sort_fields;
+        self
+    }
+
+    fn format_tree<'a>(
+        &self,
+        tree: &'a Tree,
+        path: &mut Vec<&'a str>,
+        writer: &mut String,
+    ) -> fmt::Result {
+        match tree {
+            Tree::Event(event) if event.is_hidden() => Ok(()),
+            Tree::Event(event) => self.format_event(event, path, writer),
+            Tree::Span(span) => self.format_span(span, path, writer),
+        }
+    }
+
+    fn format_shared(shared: &Shared, writer: &mut String) -> fmt::Result {
+        #[cfg(feature = "uuid")]
+        write!(writer, "uuid={} ", shared.uuid)?;
+
+        #[cfg(feature = "chrono")]
+        write!(writer, "ts={} ", shared.timestamp.to_rfc3339())?;
+
+        write!(writer, "level={}", shared.level)
+    }
+
+    fn format_path(&self, path: &[&str], writer: &mut String) -> fmt::Result {
+        if let Some((first, rest)) = path.split_first() {
+            writer.write_str(" span=")?;
+            let mut joined = String::from(*first);
+            for name in rest {
+                joined.push('/');
+                joined.push_str(name);
+            }
+            self.write_value(&joined, writer)?;
+        }
+        Ok(())
+    }
+
+    fn format_event(&self, event: &Event, path: &[&str], writer: &mut String) -> fmt::Result {
+        Logfmt::format_shared(&event.shared, writer)?;
+
+        if let Some(tag) = event.tag() {
+            writer.write_str(" tag=")?;
+            self.write_value(&tag.to_string(), writer)?;
+        }
+
+        if let Some(message) = event.message() {
+            writer.write_str(" msg=")?;
+            self.write_value(message, writer)?;
+        }
+
+        self.format_path(path, writer)?;
+
+        for field in self.sort_fields.sort(event.fields()) {
+            write!(writer, " {}=", field.key())?;
+            self.write_value(field.value(), writer)?;
+        }
+
+        writeln!(writer)
+    }
+
+    fn format_span<'a>(
+        &self,
+        span: &'a Span,
+        path: &mut Vec<&'a str>,
+        writer: &mut String,
+    ) -> fmt::Result {
+        path.push(span.display_name());
+
+        if self.span_events {
+            Logfmt::format_shared(&span.shared, writer)?;
+            writer.write_str(" msg=span_open")?;
+            self.format_path(path, writer)?;
+            writeln!(writer)?;
+        }
+
+        for tree in span.nodes() {
+            self.format_tree(tree, path, writer)?;
+        }
+
+        if self.span_events {
+            Logfmt::format_shared(&span.shared, writer)?;
+            writer.write_str(" msg=span_close")?;
+            self.format_path(path, writer)?;
+            writeln!(writer, " duration_ns={}", span.total_duration().as_nanos())?;
+        }
+
+        path.pop();
+
+        Ok(())
+    }
+
+    /// Write a logfmt value, quoting it if it contains characters that would
+    /// otherwise be ambiguous.
+    ///
+    /// Values that are already quoted, like strings recorded with `Debug`, are
+    /// written as-is unless the shell escape policy is used, as long as they
+    /// contain no unescaped quotes or control characters. Other values, such
+    /// as quoted strings recorded with `Display`, are escaped like any other.
+    fn write_value(&self, value: &str, writer: &mut String) -> fmt::Result {
+        if self.escape != EscapePolicy::Shell && is_quoted(value) {
+            return writer.write_str(value);
+        }
+
+        let needs_quotes = value.is_empty()
+            || value.chars().any(|c| {
+                c == ' '
+                    || c == '='
+                    || c == '"'
+                    || c == '\\'
+                    || (c == '\'' && self.escape == EscapePolicy::Shell)
+                    || (c.is_control() && self.escape != EscapePolicy::None)
+            });
+
+        if !needs_quotes {
+            return writer.write_str(value);
+        }
+
+        match self.escape {
+            EscapePolicy::None => {
+                writer.write_char('"')?;
+                for c in value.chars() {
+                    match c {
+                        '"' => writer.write_str("\\\"")?,
+                        '\\' => writer.write_str("\\\\")?,
+                        c => writer.write_char(c)?,
+                    }
+                }
+                writer.write_char('"')
+            }
+            EscapePolicy::Json => {
+                writer.write_char('"')?;
+                EscapePolicy::write_json(value, writer)?;
+                writer.write_char('"')
+            }
+            EscapePolicy::Shell => EscapePolicy::write_shell(value, writer),
+        }
+    }
+}
+
+/// Returns `true` if `value` is a single quoted string whose inner quotes are
+/// escaped and that contains no control characters, like the `Debug` output
+/// of a `str`.
+fn is_quoted(value: &str) -> bool {
+    let inner = match value
+        .strip_prefix('"')
+        .and_then(|value| value.strip_suffix('"'))
+    {
+        Some(inner) => inner,
+        None => return false,
+    };
+
+    let mut chars = inner.chars();
+    while let Some(c) = chars.next() {
+        match c {
+            // The guard skips the escaped character. A trailing backslash
+            // would escape the closing quote instead.
+            '\\' if chars.next().is_none() => return false,
+            '\\' => {}
+            '"' => return false,
+            c if c.is_control() => return false,
+            _ => {}
+        }
+    }
+    true
+}
@@ -1,12 +1,32 @@
 //! Utilities for formatting and writing trace trees.
 use crate::processor::{self, Processor};
-use crate::tree::Tree;
+use crate::tree::{Field, Tree};
+use std::any::TypeId;
+use std::borrow::Cow;
 use std::error::Error;
-use std::io::{self, Write};
+use std::fmt::{self, Write as _};
+use std::io::{self, IsTerminal, Write};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex, MutexGuard, PoisonError};
+use tracing::Level;
+use tracing_subscriber::fmt::writer::BoxMakeWriter;
 use tracing_subscriber::fmt::MakeWriter;
 
+mod es_bulk;
+pub use es_bulk::EsBulk;
+mod logfmt;
+pub use logfmt::Logfmt;
 mod pretty;
-pub use pretty::Pretty;
+pub(crate) use pretty::DurationDisplay;
+#[cfg(feature = "chrono")]
+pub use pretty::TimestampFormat;
+pub use pretty::{IconSet, Pretty};
+mod summary_line;
+pub use summary_line::SummaryLine;
+#[cfg(feature = "yaml")]
+mod yaml;
+#[cfg(feature = "yaml")]
+pub use yaml::Yaml;
 
 /// Format a [`Tree`] into a `String`.
 ///
@@ -33,10 +53,13 @@ pub use pretty::Pretty;
 /// Produces the following result:
 /// ```json
 /// {
+///   "schema_version": 6,
 ///   "Event": {
 ///     "uuid": "00000000-0000-0000-0000-000000000000",
 ///     "timestamp": "2022-03-24T16:08:17.761149+00:00",
 ///     "level": "INFO",
+///     "recorded_at_nanos": 1523180,
+///     "parent_id": null,
 ///     "message": "write this as json",
 ///     "tag": "info",
 ///     "fields": {}
@@ -68,11 +91,258 @@ where
     }
 }
 
+/// How formatters escape field values and messages.
+///
+/// Values containing newlines or other control characters can break
+/// line-oriented output, since a single event would span multiple lines.
+///
+/// # Examples
+///
+/// ```
+/// use tracing_forest::printer::{EscapePolicy, Logfmt};
+///
+/// let logfmt = Logfmt::new().escape(EscapePolicy::Shell);
+/// ```
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum EscapePolicy {
+    /// Values are written verbatim.
+    None,
+    /// Quotes, backslashes, and control characters are escaped like in JSON
+    /// strings, e.g. a newline becomes `\n`.
+    #[default]
+    Json,
+    /// Values are wrapped in POSIX shell single quotes, and control characters
+    /// are written using ANSI-C quoting, e.g. a newline becomes `'$'\n''`.
+    Shell,
+}
+
+impl EscapePolicy {
+    /// Write the contents of a JSON string, without the surrounding quotes.
+    pub(crate) fn write_json(value: &str, writer: &mut String) -> fmt::Result {
+        for c in value.chars() {
+            match c {
+                '"' => writer.write_str("\\\"")?,
+                '\\' => writer.write_str("\\\\")?,
+                '\n' => writer.write_str("\\n")?,
+                '\r' => writer.write_str("\\r")?,
+                '\t' => writer.write_str("\\t")?,
+                c if c.is_control() => write!(writer, "\\u{:04x}", c as u32)?,
+                c => writer.write_char(c)?,
+            }
+        }
+        Ok(())
+    }
+
+    /// Write a value wrapped in shell single quotes.
+    pub(crate) fn write_shell(value: &str, writer: &mut String) -> fmt::Result {
+        writer.write_char('\'')?;
+        for c in value.chars() {
+            match c {
+                '\'' => writer.write_str("'\\''")?,
+                '\n' => writer.write_str("'$'\\n''")?,
+                '\r' => writer.write_str("'$'\\r''")?,
+                '\t' => writer.write_str("'$'\\t''")?,
+                c if c.is_control() => write!(writer, "'$'\\x{:02x}''", c as u32)?,
+                c => writer.write_char(c)?,
+            }
+        }
+        writer.write_char('\'')
+    }
+}
+
+/// The order that formatters display an event's fields in.
+///
+/// This only affects how fields are displayed, and the fields of captured
+/// trees are always kept in the order they were recorded.
+///
+/// # Examples
+///
+/// ```
+/// use tracing_forest::printer::{FieldSort, Pretty};
+///
+/// let pretty = Pretty::new().sort_fields(FieldSort::Alphabetical);
+/// ```
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum FieldSort {
+    /// Fields are displayed in the order they were recorded.
+    #[default]
+    Insertion,
+    /// Fields are displayed sorted by key. Fields with the same key keep the
+    /// order they were recorded in.
+    Alphabetical,
+}
+
+impl FieldSort {
+    /// Returns the fields in display order.
+    pub(crate) fn sort(self, fields: &[Field]) -> Vec<&Field> {
+        let mut sorted: Vec<&Field> = fields.iter().collect();
+        if self == FieldSort::Alphabetical {
+            sorted.sort_by_key(|field| field.key());
+        }
+        sorted
+    }
+}
+
 /// A [`Processor`] that formats and writes logs.
-#[derive(Clone, Debug)]
+///
+/// # Colors
+///
+/// ANSI escape sequences from the formatter are only kept by default when
+/// writing to [`MakeStdout`] or [`MakeStderr`] while the stream is a terminal.
+/// Any other writer, including `std::io::stdout` passed as a function or a
+/// custom writer that wraps a terminal, gets uncolored output unless colors
+/// are requested with [`Printer::ansi`]:
+///
+/// ```
+/// use tracing_forest::{printer::Pretty, Printer};
+///
+/// let printer = Printer::new()
+///     .formatter(Pretty::new())
+///     .writer(std::io::stdout)
+///     .ansi(true);
+/// ```
+#[derive(Debug)]
 pub struct Printer<S, W> {
     formatter: S,
     make_writer: W,
+    separator: String,
+    written: AtomicBool,
+    ansi: Option<bool>,
+    threshold: Option<Level>,
+    stderr: Option<SplitWriter>,
+    header: Option<Header>,
+}
+
+/// A function computing the line written before each tree, set by
+/// [`Printer::header`].
+#[derive(Clone)]
+struct Header(Arc<dyn Fn(&Tree) -> String + Send + Sync>);
+
+impl fmt::Debug for Header {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("Header(..)")
+    }
+}
+
+/// The writer for trees at `WARN` or more severe, set by
+/// [`Printer::split_streams`] or [`Printer::stderr_writer`].
+#[derive(Clone, Debug)]
+struct SplitWriter {
+    make_writer: Arc<BoxMakeWriter>,
+    is_stderr: bool,
+}
+
+impl<S: Clone, W: Clone> Clone for Printer<S, W> {
+    fn clone(&self) -> Self {
+        Printer {
+            formatter: self.formatter.clone(),
+            make_writer: self.make_writer.clone(),
+            separator: self.separator.clone(),
+            written: AtomicBool::new(self.written.load(Ordering::Relaxed)),
+            ansi: self.ansi,
+            threshold: self.threshold,
+            stderr: self.stderr.clone(),
+            header: self.header.clone(),
+        }
+    }
+}
+
+/// Returns a single character representing a [`Level`], for compact formatters.
+///
+/// # Examples
+///
+/// ```
+/// use tracing::Level;
+/// use tracing_forest::printer::level_char;
+///
+/// assert!(level_char(Level::WARN) == 'W');
+/// ```
+pub fn level_char(level: Level) -> char {
+    match level {
+        Level::TRACE => 'T',
+        Level::DEBUG => 'D',
+        Level::INFO => 'I',
+        Level::WARN => 'W',
+        Level::ERROR => 'E',
+    }
+}
+
+/// Returns a three letter abbreviation of a [`Level`], for compact formatters.
+///
+/// # Examples
+///
+/// ```
+/// use tracing::Level;
+/// use tracing_forest::printer::level_abbrev;
+///
+/// assert!(level_abbrev(Level::WARN) == "WRN");
+/// ```
+pub fn level_abbrev(level: Level) -> &'static str {
+    match level {
+        Level::TRACE => "TRC",
+        Level::DEBUG => "DBG",
+        Level::INFO => "INF",
+        Level::WARN => "WRN",
+        Level::ERROR => "ERR",
+    }
+}
+
+/// Removes ANSI escape sequences, such as colors, from a string.
+///
+/// This is useful for comparing formatted output in snapshot tests.
+///
+/// # Examples
+///
+/// ```
+/// use tracing_forest::printer::strip_ansi;
+///
+/// assert!(strip_ansi("\x1b[1;32mINFO\x1b[0m") == "INFO");
+/// ```
+pub fn strip_ansi(s: &str) -> String {
+    let mut stripped = String::with_capacity(s.len());
+    let mut chars = s.chars();
+
+    while let Some(c) = chars.next() {
+        if c != '\x1b' {
+            stripped.push(c);
+            continue;
+        }
+
+        match chars.next() {
+            // Control sequences end with a byte in the range `@` to `~`.
+            Some('[') => {
+                chars.by_ref().find(|c| ('@'..='~').contains(c));
+            }
+            Some(c) => {
+                stripped.push('\x1b');
+                stripped.push(c);
+            }
+            None => stripped.push('\x1b'),
+        }
+    }
+
+    stripped
+}
+
+/// Replaces control characters other than tabs and newlines with `\xNN`
+/// escapes, so that logged input can't inject terminal escape sequences.
+pub(crate) fn escape_control(value: &str) -> Cow<'_, str> {
+    let is_control = |c: char| c.is_control() && c != '\t' && c != '\n';
+
+    if !value.chars().any(is_control) {
+        return Cow::Borrowed(value);
+    }
+
+    let mut escaped = String::with_capacity(value.len() + 8);
+    for c in value.chars() {
+        if is_control(c) {
+            // `char::is_control` only matches characters below U+00A0.
+            write!(escaped, "\\x{:02x}", c as u32).expect("writing to a `String` can't fail");
+        } else {
+            escaped.push(c);
+        }
+    }
+    Cow::Owned(escaped)
 }
 
 /// A [`MakeWriter`] that writes to stdout.
@@ -89,6 +359,58 @@ pub struct MakeStdout;
 #[derive(Debug)]
 pub struct MakeStderr;
 
+/// A [`MakeWriter`] that writes to a shared in-memory buffer.
+///
+/// Clones of a `MakeBuffer` write to the same buffer, which makes it useful for
+/// inspecting formatted output in tests.
+///
+/// # Examples
+///
+/// ```
+/// use tracing_forest::printer::MakeBuffer;
+/// use tracing_forest::{traits::*, util::*, Printer};
+/// use tracing_subscriber::Registry;
+///
+/// let buffer = MakeBuffer::new();
+/// let layer = ForestLayer::from(Printer::new().writer(buffer.clone()));
+///
+/// tracing::subscriber::with_default(Registry::default().with(layer), || {
+///     info!("Hello, world!");
+/// });
+///
+/// assert!(buffer.contents().contains("Hello, world!"));
+/// ```
+#[derive(Clone, Debug, Default)]
+pub struct MakeBuffer(Arc<Mutex<Vec<u8>>>);
+
+impl MakeBuffer {
+    /// Returns a new, empty `MakeBuffer`.
+    pub fn new() -> Self {
+        MakeBuffer::default()
+    }
+
+    /// Returns the contents of the buffer, replacing invalid UTF-8 sequences
+    /// with `U+FFFD REPLACEMENT CHARACTER`.
+    pub fn contents(&self) -> String {
+        String::from_utf8_lossy(&self.lock()).into_owned()
+    }
+
+    fn lock(&self) -> MutexGuard<'_, Vec<u8>> {
+        // A panic while writing can't leave the bytes in an invalid state.
+        self.0.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+}
+
+impl io::Write for MakeBuffer {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.lock().write(buf)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
 impl<'a> MakeWriter<'a> for MakeStdout {
     type Writer = io::Stdout;
 
@@ -105,6 +427,14 @@ impl<'a> MakeWriter<'a> for MakeStderr {
     }
 }
 
+impl<'a> MakeWriter<'a> for MakeBuffer {
+    type Writer = MakeBuffer;
+
+    fn make_writer(&self) -> Self::Writer {
+        self.clone()
+    }
+}
+
 /// A [`Processor`] that pretty-prints to stdout.
 pub type PrettyPrinter = Printer<Pretty, MakeStdout>;
 
@@ -114,8 +444,14 @@ impl PrettyPrinter {
     /// Use [`Printer::formatter`] and [`Printer::writer`] for custom configuration.
     pub const fn new() -> Self {
         Printer {
-            formatter: Pretty,
+            formatter: Pretty::new(),
             make_writer: MakeStdout,
+            separator: String::new(),
+            written: AtomicBool::new(false),
+            ansi: None,
+            threshold: None,
+            stderr: None,
+            header: None,
         }
     }
 }
@@ -135,6 +471,12 @@ where
         Printer {
             formatter,
             make_writer: self.make_writer,
+            separator: self.separator,
+            written: self.written,
+            ansi: self.ansi,
+            threshold: self.threshold,
+            stderr: self.stderr,
+            header: self.header,
         }
     }
 
@@ -146,7 +488,295 @@ where
         Printer {
             formatter: self.formatter,
             make_writer,
+            separator: self.separator,
+            written: self.written,
+            ansi: self.ansi,
+            threshold: self.threshold,
+            stderr: self.stderr,
+            header: self.header,
+        }
+    }
+
+    /// Set a separator that is written between consecutive trees.
+    ///
+    /// The separator is written before every tree except the first, so it
+    /// never appears before the first tree or after the last. It's empty by
+    /// default, and `"\n"` is a common choice for separating trees with a
+    /// blank line.
+    pub fn separator(mut self, separator: &str) -> Self {
+        self.separator = separator.to_owned();
+        self
+    }
+
+    /// Set a function computing a header line that is written before each tree.
+    ///
+    /// The function is given the whole tree, so the header can summarize it,
+    /// such as with the name and duration of the root span. A newline is
+    /// added after the header if it doesn't end with one. Trees that aren't
+    /// written, such as those below the [`threshold`], don't get a header.
+    ///
+    /// [`threshold`]: Printer::threshold
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use tracing_forest::printer::MakeBuffer;
+    /// use tracing_forest::{traits::*, util::*, Printer};
+    /// use tracing_subscriber::Registry;
+    ///
+    /// let buffer = MakeBuffer::new();
+    /// let printer = Printer::new().writer(buffer.clone()).header(|tree| {
+    ///     match tree.span() {
+    ///         Ok(span) => format!("=== {} ===", span.name()),
+    ///         Err(_) => "=== event ===".to_string(),
+    ///     }
+    /// });
+    ///
+    /// tracing::subscriber::with_default(Registry::default().with(ForestLayer::from(printer)), || {
+    ///     info_span!("request").in_scope(|| info!("handled"));
+    /// });
+    ///
+    /// assert!(buffer.contents().starts_with("=== request ===\n"));
+    /// ```
+    pub fn header<H>(mut self, header: H) -> Self
+    where
+        H: 'static + Fn(&Tree) -> String + Send + Sync,
+    {
+        self.header = Some(Header(Arc::new(header)));
+        self
+    }
+
+    /// Set whether ANSI escape sequences produced by the formatter are kept.
+    ///
+    /// By default, they're only kept when the writer is [`MakeStdout`] or
+    /// [`MakeStderr`] and the stream is a terminal, and are removed with
+    /// [`strip_ansi`] otherwise. This means that output written to files or
+    /// in-memory buffers contains no colors, and neither does output written
+    /// through any other writer, even if it's a terminal. Pass `true` to keep
+    /// colors for such writers.
+    pub fn ansi(mut self, ansi: bool) -> Self {
+        self.ansi = Some(ansi);
+        self
+    }
+
+    /// Set whether only trees containing an `ERROR` are printed.
+    ///
+    /// This is a shortcut for [`threshold`](Printer::threshold) with
+    /// [`Level::ERROR`], and keeps the output empty unless something went
+    /// wrong, replacing any threshold that was set before. Pass `false` to
+    /// print every tree again; this only removes an `ERROR` threshold, so a
+    /// less severe one set with [`threshold`](Printer::threshold) is kept.
+    ///
+    /// # Examples
+    ///
+    /// Printing errors to the console while a separate processor captures
+    /// everything:
+    /// ```
+    /// use tracing_forest::processor::{self, Processor};
+    /// use tracing_forest::{ForestLayer, PrettyPrinter};
+    ///
+    /// let everything = processor::from_fn(|_tree| {
+    ///     // store the tree somewhere
+    ///     Ok(())
+    /// });
+    /// let console = PrettyPrinter::new().errors_only(true);
+    ///
+    /// let layer = ForestLayer::from(processor::from_fn(move |tree| {
+    ///     console.process(tree.clone())?;
+    ///     everything.process(tree)
+    /// }));
+    /// ```
+    pub fn errors_only(mut self, errors_only: bool) -> Self {
+        if errors_only {
+            self.threshold = Some(Level::ERROR);
+        } else if self.threshold == Some(Level::ERROR) {
+            self.threshold = None;
         }
+        self
+    }
+
+    /// Only print trees that contain a node at `level` or more severe.
+    ///
+    /// Trees are compared by their most severe level, as returned by
+    /// [`Span::max_level`]. Skipped trees are discarded without an error.
+    ///
+    /// [`Span::max_level`]: crate::tree::Span::max_level
+    pub fn threshold(mut self, level: Level) -> Self {
+        self.threshold = Some(level);
+        self
+    }
+
+    /// Set whether trees containing a `WARN` or `ERROR` are written to stderr.
+    ///
+    /// Trees are compared by their most severe level, as returned by
+    /// [`Span::max_level`]. Trees at `WARN` or more severe are written to
+    /// stderr, and the rest are written to the [writer](Printer::writer), which
+    /// is stdout by default. This separates diagnostics from normal output, as
+    /// is conventional for command line tools. Pass `false` to write every tree
+    /// to the writer again.
+    ///
+    /// [`Span::max_level`]: crate::tree::Span::max_level
+    pub fn split_streams(mut self, split_streams: bool) -> Self {
+        self.stderr = if split_streams {
+            Some(SplitWriter {
+                make_writer: Arc::new(BoxMakeWriter::new(MakeStderr)),
+                is_stderr: true,
+            })
+        } else {
+            None
+        };
+        self
+    }
+
+    /// Split streams as with [`split_streams`](Printer::split_streams), but
+    /// write trees at `WARN` or more severe to `make_writer` instead of stderr.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use tracing_forest::printer::MakeBuffer;
+    /// use tracing_forest::{traits::*, util::*, Printer};
+    /// use tracing_subscriber::Registry;
+    ///
+    /// let (stdout, stderr) = (MakeBuffer::new(), MakeBuffer::new());
+    /// let printer = Printer::new()
+    ///     .writer(stdout.clone())
+    ///     .stderr_writer(stderr.clone());
+    ///
+    /// tracing::subscriber::with_default(Registry::default().with(ForestLayer::from(printer)), || {
+    ///     info!("normal output");
+    ///     warn!("diagnostic");
+    /// });
+    ///
+    /// assert!(stdout.contents().contains("normal output"));
+    /// assert!(stderr.contents().contains("diagnostic"));
+    /// ```
+    pub fn stderr_writer<E>(mut self, make_writer: E) -> Self
+    where
+        E: 'static + for<'a> MakeWriter<'a> + Send + Sync,
+    {
+        self.stderr = Some(SplitWriter {
+            make_writer: Arc::new(BoxMakeWriter::new(make_writer)),
+            is_stderr: TypeId::of::<E>() == TypeId::of::<MakeStderr>(),
+        });
+        self
+    }
+
+    fn keeps_ansi(&self, split: Option<&SplitWriter>) -> bool {
+        self.ansi.unwrap_or_else(|| {
+            if let Some(split) = split {
+                return split.is_stderr && io::stderr().is_terminal();
+            }
+
+            let writer = TypeId::of::<W>();
+            if writer == TypeId::of::<MakeStdout>() {
+                io::stdout().is_terminal()
+            } else if writer == TypeId::of::<MakeStderr>() {
+                io::stderr().is_terminal()
+            } else {
+                false
+            }
+        })
+    }
+}
+
+impl<F, W> Printer<F, W>
+where
+    F: 'static + Formatter,
+    W: 'static + for<'a> MakeWriter<'a>,
+{
+    /// Formats and writes a borrowed [`Tree`].
+    pub(crate) fn write_tree(&self, tree: &Tree) -> Result<(), Box<dyn Error + Send + Sync>> {
+        let max_level = match tree {
+            Tree::Event(event) => event.level(),
+            Tree::Span(span) => span.max_level(),
+        };
+
+        // `Level`s compare by verbosity, so more severe levels are smaller.
+        if self
+            .threshold
+            .is_some_and(|threshold| max_level > threshold)
+        {
+            return Ok(());
+        }
+
+        let split = self.stderr.as_ref().filter(|_| max_level <= Level::WARN);
+
+        let mut string = self.formatter.fmt(tree)?;
+
+        // Formatters write nothing for trees that are hidden events.
+        if string.is_empty() {
+            return Ok(());
+        }
+
+        if let Some(Header(header)) = &self.header {
+            let mut line = header(tree);
+            if !line.ends_with('\n') {
+                line.push('\n');
+            }
+            string.insert_str(0, &line);
+        }
+
+        if string.contains('\x1b') && !self.keeps_ansi(split) {
+            string = strip_ansi(&string);
+        }
+
+        if !self.separator.is_empty() && self.written.swap(true, Ordering::Relaxed) {
+            // Write the separator and the tree together so that they can't be
+            // interleaved with other writes.
+            string.insert_str(0, &self.separator);
+        }
+
+        match split {
+            Some(split) => split
+                .make_writer
+                .make_writer()
+                .write_all(string.as_bytes())?,
+            None => self
+                .make_writer
+                .make_writer()
+                .write_all(string.as_bytes())?,
+        }
+        Ok(())
+    }
+
+    /// Formats and writes each tree of an in-memory forest, in order.
+    ///
+    /// This renders trees that weren't produced by a live subscriber, such as
+    /// ones that were captured earlier and deserialized, which is useful for
+    /// offline log viewers. Trees are separated by the configured
+    /// [`separator`](Printer::separator) as usual.
+    ///
+    /// # Errors
+    ///
+    /// Returns the first error encountered while formatting or writing a tree,
+    /// in which case the remaining trees aren't written.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use tracing::info;
+    /// use tracing_forest::printer::MakeBuffer;
+    /// use tracing_forest::Printer;
+    ///
+    /// # #[tokio::main(flavor = "current_thread")]
+    /// # async fn main() -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+    /// let logs = tracing_forest::capture()
+    ///     .build()
+    ///     .on(async {
+    ///         info!("Hello, world!");
+    ///     })
+    ///     .await;
+    ///
+    /// let buffer = MakeBuffer::new();
+    /// Printer::new().writer(buffer.clone()).print_forest(&logs)?;
+    ///
+    /// assert!(buffer.contents().contains("Hello, world!"));
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn print_forest(&self, trees: &[Tree]) -> Result<(), Box<dyn Error + Send + Sync>> {
+        trees.iter().try_for_each(|tree| self.write_tree(tree))
     }
 }
 
@@ -162,14 +792,9 @@ where
     W: 'static + for<'a> MakeWriter<'a>,
 {
     fn process(&self, tree: Tree) -> processor::Result {
-        let string = match self.formatter.fmt(&tree) {
-            Ok(s) => s,
-            Err(e) => return Err(processor::error(tree, e.into())),
-        };
-
-        match self.make_writer.make_writer().write_all(string.as_bytes()) {
+        match self.write_tree(&tree) {
             Ok(()) => Ok(()),
-            Err(e) => Err(processor::error(tree, e.into())),
+            Err(e) => Err(processor::error(tree, e)),
         }
     }
 }
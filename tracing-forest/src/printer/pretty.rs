@@ -1,9 +1,12 @@
-use crate::printer::Formatter;
-use crate::tree::{Event, Shared, Span, Tree};
+use crate::printer::{escape_control, level_char, EscapePolicy, FieldSort, Formatter};
+use crate::tree::{Event, Shared, Span, SpanColor, SpanStatus, Tree};
 use crate::Tag;
 use ansi_term::Color;
+#[cfg(feature = "chrono")]
+use chrono::SecondsFormat;
 use std::fmt::{self, Write};
 use tracing::Level;
+use unicode_width::{UnicodeWidthChar, UnicodeWidthStr};
 
 #[cfg(feature = "smallvec")]
 type IndentVec = smallvec::SmallVec<[Indent; 32]>;
@@ -19,12 +22,12 @@ type IndentVec = Vec<Indent>;
 /// <NAME> [ <DURATION> | <BODY> / <ROOT> ]
 /// ```
 /// * `DURATION` represents the total time the span was entered for. If the span
-/// was used to instrument a `Future` that sleeps, then that time won't be counted
-/// since the `Future` won't be polled during that time, and so the span won't enter.
+///   was used to instrument a `Future` that sleeps, then that time won't be counted
+///   since the `Future` won't be polled during that time, and so the span won't enter.
 /// * `BODY` represents the percent time the span is entered relative to the root
-/// span, *excluding* time that any child spans are entered.
+///   span, *excluding* time that any child spans are entered.
 /// * `ROOT` represents the percent time the span is entered relative to the root
-/// span, *including* time that any child spans are entered.
+///   span, *including* time that any child spans are entered.
 ///
 /// As a mental model, look at `ROOT` to quickly narrow down which branches are
 /// costly, and look at `BASE` to pinpoint exactly which spans are expensive.
@@ -55,8 +58,49 @@ type IndentVec = Vec<Indent>;
 /// WARN     │     ┕━ 🚧 [filter.warn]: Some filter warning
 /// TRACE    ┕━ 📍 [trace]: Finished!
 /// ```
-#[derive(Debug)]
-pub struct Pretty;
+///
+/// # Configuration
+///
+/// `Pretty` provides builder methods for configuring its output:
+/// * [`footer`](Pretty::footer) writes a summary line after each tree.
+/// * [`escape`](Pretty::escape) sets how messages and field values are escaped.
+/// * [`wrap`](Pretty::wrap) wraps long lists of fields onto continuation lines.
+/// * [`sort_fields`](Pretty::sort_fields) sets the order fields are displayed in.
+/// * [`ditto_unchanged_fields`](Pretty::ditto_unchanged_fields) abbreviates
+///   values repeated from the previous event.
+/// * [`max_message_len`](Pretty::max_message_len) truncates long messages.
+/// * [`show_span_fields`](Pretty::show_span_fields) shows span fields after
+///   span names.
+/// * [`show_task_id`](Pretty::show_task_id) shows the Tokio task of each line,
+///   which requires the `tokio` feature.
+/// * [`ansi`](Pretty::ansi) sets whether ANSI colors are used.
+/// * [`inherit_span_tag`](Pretty::inherit_span_tag) shows span tags on untagged events.
+/// * [`icon_set`](Pretty::icon_set) sets the icons used for level tags.
+/// * [`timestamp_format`](Pretty::timestamp_format) sets how timestamps are
+///   displayed, which requires the `chrono` feature.
+#[derive(Clone, Debug)]
+pub struct Pretty {
+    footer: bool,
+    icon_set: IconSet,
+    inherit_span_tag: bool,
+    escape: Option<EscapePolicy>,
+    wrap: Option<Option<usize>>,
+    ansi: bool,
+    sort_fields: FieldSort,
+    ditto_unchanged_fields: bool,
+    max_message_len: Option<usize>,
+    show_span_fields: bool,
+    #[cfg(feature = "tokio")]
+    show_task_id: bool,
+    #[cfg(feature = "chrono")]
+    timestamp_format: TimestampFormat,
+}
+
+impl Default for Pretty {
+    fn default() -> Self {
+        Pretty::new()
+    }
+}
 
 impl Formatter for Pretty {
     type Error = fmt::Error;
@@ -64,45 +108,382 @@ impl Formatter for Pretty {
     fn fmt(&self, tree: &Tree) -> Result<String, fmt::Error> {
         let mut writer = String::with_capacity(256);
 
-        Pretty::format_tree(tree, None, &mut IndentVec::new(), &mut writer)?;
+        if tree.is_hidden() {
+            return Ok(writer);
+        }
+
+        self.format_tree(
+            tree,
+            None,
+            Inherited::default(),
+            None,
+            &mut IndentVec::new(),
+            &mut writer,
+        )?;
+
+        if self.footer {
+            Pretty::format_footer(tree, &mut writer)?;
+        }
 
         Ok(writer)
     }
 }
 
 impl Pretty {
+    /// Returns a new `Pretty` formatter with the default configuration.
+    pub const fn new() -> Self {
+        Pretty {
+            footer: false,
+            icon_set: IconSet::Emoji,
+            inherit_span_tag: false,
+            escape: None,
+            wrap: None,
+            ansi: true,
+            sort_fields: FieldSort::Insertion,
+            ditto_unchanged_fields: false,
+            max_message_len: None,
+            show_span_fields: false,
+            #[cfg(feature = "tokio")]
+            show_task_id: false,
+            #[cfg(feature = "chrono")]
+            timestamp_format: TimestampFormat::Human,
+        }
+    }
+
+    /// Set whether a summary line is written after each tree.
+    ///
+    /// The summary contains the number of events, the number of events at the
+    /// `ERROR` level, and the duration of the root span if there is one:
+    /// ```log
+    /// INFO     my_span [ 3.40ms | 100.00% ]
+    /// INFO     ┝━ ｉ [info]: first
+    /// ERROR    ┕━ 🚨 [error]: second
+    /// └─ 2 events, 1 error, 3.40ms
+    /// ```
+    pub fn footer(mut self, footer: bool) -> Self {
+        self.footer = footer;
+        self
+    }
+
+    /// Set how messages and field values are escaped.
+    ///
+    /// By default, values are written verbatim except for newlines, which are
+    /// replaced with `⏎` so that each event stays on its own line, and other
+    /// control characters besides tabs, which are written as `\xNN` escapes so
+    /// that logged input can't inject terminal escape sequences. Span names
+    /// are always escaped this way.
+    pub fn escape(mut self, escape: EscapePolicy) -> Self {
+        self.escape = Some(escape);
+        self
+    }
+
+    /// Set the width that an event's fields are wrapped at, or `None` to
+    /// disable wrapping.
+    ///
+    /// Fields that would extend a line past the width are moved onto
+    /// continuation lines, aligned under the message and keeping the tree's
+    /// connectors intact:
+    /// ```log
+    /// INFO     my_span [ 3.40ms | 100.00% ]
+    /// INFO     ┝━ ｉ [info]: request | method: GET
+    ///          │             | path: /index.html
+    /// INFO     ┕━ ｉ [info]: response
+    /// ```
+    ///
+    /// By default, the width of the terminal is used if stdout is a terminal
+    /// and the `terminal_size` feature is enabled, and wrapping is disabled
+    /// otherwise.
+    pub fn wrap(mut self, width: Option<usize>) -> Self {
+        self.wrap = Some(width);
+        self
+    }
+
+    /// Set the order that an event's fields are displayed in.
+    ///
+    /// Defaults to [`FieldSort::Insertion`]. [`FieldSort::Alphabetical`] makes
+    /// the output deterministic regardless of the order fields were recorded in,
+    /// which is useful for snapshots and diffing.
+    pub fn sort_fields(mut self, sort_fields: FieldSort) -> Self {
+        self.sort_fields = sort_fields;
+        self
+    }
+
+    /// Set whether field values that are unchanged from the previous sibling
+    /// event are displayed as a ditto mark (`〃`).
+    ///
+    /// A field is unchanged if the event immediately before it in the same span
+    /// has a field with the same key and value. This compresses the output of
+    /// tight loops that repeat the same fields:
+    /// ```log
+    /// INFO     my_span [ 3.40ms | 100.00% ]
+    /// INFO     ┝━ ｉ [info]: retrying | job: "sync" | attempt: 1
+    /// INFO     ┕━ ｉ [info]: retrying | job: 〃 | attempt: 2
+    /// ```
+    pub fn ditto_unchanged_fields(mut self, ditto_unchanged_fields: bool) -> Self {
+        self.ditto_unchanged_fields = ditto_unchanged_fields;
+        self
+    }
+
+    /// Set the maximum width of event messages, in display columns.
+    ///
+    /// Longer messages are truncated with a trailing `…`, which counts towards
+    /// the width. Wide characters, such as CJK characters, count as two
+    /// columns, and combining characters count as none. Messages are
+    /// truncated after they're escaped, so escapes like `⏎` count towards the
+    /// width too. This only affects rendering, so [`Event::message`] still
+    /// returns the full message. By default, messages aren't truncated.
+    ///
+    /// [`Event::message`]: crate::tree::Event::message
+    pub fn max_message_len(mut self, max_message_len: usize) -> Self {
+        self.max_message_len = Some(max_message_len);
+        self
+    }
+
+    /// Set whether a span's own fields are shown after its name, like
+    /// `tracing`'s own formatter does:
+    /// ```log
+    /// INFO     request{user_id=7 path="/login"} [ 1.20ms | 100.00% ]
+    /// INFO     ┕━ ｉ [info]: received
+    /// ```
+    /// Fields are shown in the order set by [`sort_fields`], with their
+    /// values escaped like the fields of events. Spans without fields are
+    /// shown as usual. Defaults to `false`.
+    ///
+    /// [`sort_fields`]: Pretty::sort_fields
+    pub fn show_span_fields(mut self, show_span_fields: bool) -> Self {
+        self.show_span_fields = show_span_fields;
+        self
+    }
+
+    /// Set whether the [`task_id`] of each event and span is shown in a column
+    /// after the timestamp, which tells apart interleaved trees from
+    /// different Tokio tasks:
+    /// ```log
+    /// task 12    INFO     request [ 1.20ms | 100.00% ]
+    /// task 12    INFO     ┕━ ｉ [info]: received
+    /// ```
+    /// The column is left blank for nodes recorded outside of a task. Defaults
+    /// to `false`.
+    ///
+    /// [`task_id`]: crate::tree::Shared::task_id
+    #[cfg(feature = "tokio")]
+    pub fn show_task_id(mut self, show_task_id: bool) -> Self {
+        self.show_task_id = show_task_id;
+        self
+    }
+
+    /// Set the icons used for tags created from a [`Level`], such as the
+    /// default tags of untagged events.
+    ///
+    /// This only affects how tags are displayed, and tags with custom icons are
+    /// unchanged.
+    pub fn icon_set(mut self, icon_set: IconSet) -> Self {
+        self.icon_set = icon_set;
+        self
+    }
+
+    /// Set whether events without a tag are displayed with the tag of the
+    /// nearest enclosing span that has one, as parsed by
+    /// [`TagParser::parse_span`].
+    ///
+    /// The span's tag is prefixed to the tag derived from the event's level,
+    /// but the trees themselves are unchanged:
+    /// ```log
+    /// INFO     request [ 3.40ms | 100.00% ]
+    /// INFO     ┝━ ｉ [http.info]: received
+    /// WARN     ┕━ 🚧 [http.warn]: slow response
+    /// ```
+    ///
+    /// [`TagParser::parse_span`]: crate::tag::TagParser::parse_span
+    pub fn inherit_span_tag(mut self, inherit_span_tag: bool) -> Self {
+        self.inherit_span_tag = inherit_span_tag;
+        self
+    }
+
+    /// Set whether ANSI colors are used, which requires the `ansi` feature.
+    ///
+    /// Spans whose `forest.color` field names a [`SpanColor`] are displayed in
+    /// that color, along with the untagged events inside them.
+    ///
+    /// This is enabled by default. Note that a [`Printer`] also removes colors
+    /// from the output when it isn't writing to a terminal.
+    ///
+    /// [`Printer`]: crate::printer::Printer
+    pub fn ansi(mut self, ansi: bool) -> Self {
+        self.ansi = ansi;
+        self
+    }
+
+    /// Set how the timestamp at the start of each line is displayed.
+    ///
+    /// Defaults to [`TimestampFormat::Human`]. [`TimestampFormat::None`] omits
+    /// timestamps, which keeps the output stable for diffing.
+    #[cfg(feature = "chrono")]
+    pub fn timestamp_format(mut self, timestamp_format: TimestampFormat) -> Self {
+        self.timestamp_format = timestamp_format;
+        self
+    }
+
+    /// Formats the `Tree` into its individual lines, without trailing newlines.
+    ///
+    /// This is useful for displays that manage their own scrolling, such as
+    /// TUIs, which can index into and highlight the returned lines. Each node
+    /// of the tree is one line, plus any continuation lines from [wrapping] or
+    /// the [footer].
+    ///
+    /// [wrapping]: Pretty::wrap
+    /// [footer]: Pretty::footer
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use tracing::{info, info_span};
+    /// use tracing_forest::printer::Pretty;
+    /// # #[tokio::main(flavor = "current_thread")]
+    /// # async fn main() {
+    /// # let logs = tracing_forest::capture()
+    /// #     .build()
+    /// #     .on(async {
+    /// #         info_span!("my_span").in_scope(|| info!("inside"));
+    /// #     })
+    /// #     .await;
+    /// let lines = Pretty::new().ansi(false).wrap(None).fmt_lines(&logs[0]);
+    ///
+    /// assert!(lines.len() == 2);
+    /// assert!(lines[0].contains("my_span"));
+    /// # }
+    /// ```
+    pub fn fmt_lines(&self, tree: &Tree) -> Vec<String> {
+        let formatted = self.fmt(tree).expect("writing to a `String` can't fail");
+        formatted.lines().map(str::to_string).collect()
+    }
+
+    /// The display width of the uuid, timestamp, and level at the start of
+    /// each line.
+    fn shared_width(&self) -> usize {
+        #[cfg(feature = "chrono")]
+        let timestamp_width = match self.timestamp_format {
+            TimestampFormat::Human | TimestampFormat::Rfc3339Nanos => 37,
+            TimestampFormat::UnixNanos => 21,
+            TimestampFormat::None => 0,
+        };
+        #[cfg(not(feature = "chrono"))]
+        let timestamp_width = 0;
+
+        #[cfg(feature = "tokio")]
+        let task_width = 11 * self.show_task_id as usize;
+        #[cfg(not(feature = "tokio"))]
+        let task_width = 0;
+
+        9 + 37 * (cfg!(feature = "uuid") as usize) + timestamp_width + task_width
+    }
+
+    fn wrap_width(&self) -> Option<usize> {
+        match self.wrap {
+            Some(width) => width,
+            #[cfg(feature = "terminal_size")]
+            None => terminal_size::terminal_size().map(|(width, _)| width.0 as usize),
+            #[cfg(not(feature = "terminal_size"))]
+            None => None,
+        }
+    }
+
     fn format_tree(
+        &self,
         tree: &Tree,
         duration_root: Option<f64>,
+        inherited: Inherited,
+        previous: Option<&Event>,
         indent: &mut IndentVec,
         writer: &mut String,
     ) -> fmt::Result {
         match tree {
             Tree::Event(event) => {
-                Pretty::format_shared(&event.shared, writer)?;
+                self.format_shared(&event.shared, writer)?;
                 Pretty::format_indent(indent, writer)?;
-                Pretty::format_event(event, writer)
+                self.format_event(event, inherited, previous, indent, writer)
             }
             Tree::Span(span) => {
-                Pretty::format_shared(&span.shared, writer)?;
+                self.format_shared(&span.shared, writer)?;
                 Pretty::format_indent(indent, writer)?;
-                Pretty::format_span(span, duration_root, indent, writer)
+                self.format_span(span, duration_root, inherited, indent, writer)
+            }
+        }
+    }
+
+    fn format_footer(tree: &Tree, writer: &mut String) -> fmt::Result {
+        fn count(tree: &Tree, events: &mut usize, errors: &mut usize) {
+            match tree {
+                Tree::Event(event) if event.is_hidden() => {}
+                Tree::Event(event) => {
+                    *events += 1;
+                    if event.level() == Level::ERROR {
+                        *errors += 1;
+                    }
+                }
+                Tree::Span(span) => {
+                    for node in span.nodes() {
+                        count(node, events, errors);
+                    }
+                }
             }
         }
+
+        let (mut events, mut errors) = (0, 0);
+        count(tree, &mut events, &mut errors);
+
+        write!(
+            writer,
+            "└─ {} event{}, {} error{}",
+            events,
+            if events == 1 { "" } else { "s" },
+            errors,
+            if errors == 1 { "" } else { "s" },
+        )?;
+
+        if let Tree::Span(span) = tree {
+            let total_duration = span.total_duration().as_nanos() as f64;
+            write!(writer, ", {}", DurationDisplay(total_duration))?;
+        }
+
+        writeln!(writer)
     }
 
-    fn format_shared(shared: &Shared, writer: &mut String) -> fmt::Result {
+    fn format_shared(&self, shared: &Shared, writer: &mut String) -> fmt::Result {
         #[cfg(feature = "uuid")]
         write!(writer, "{} ", shared.uuid)?;
 
         #[cfg(feature = "chrono")]
-        write!(writer, "{:<36} ", shared.timestamp.to_rfc3339())?;
+        match self.timestamp_format {
+            TimestampFormat::Human => write!(writer, "{:<36} ", shared.timestamp.to_rfc3339())?,
+            TimestampFormat::Rfc3339Nanos => write!(
+                writer,
+                "{:<36} ",
+                shared
+                    .timestamp
+                    .to_rfc3339_opts(SecondsFormat::Nanos, false)
+            )?,
+            TimestampFormat::UnixNanos => match shared.timestamp.timestamp_nanos_opt() {
+                Some(nanos) => write!(writer, "{:<20} ", nanos)?,
+                None => write!(writer, "{:<20} ", "")?,
+            },
+            TimestampFormat::None => {}
+        }
+
+        #[cfg(feature = "tokio")]
+        if self.show_task_id {
+            match shared.task_id {
+                Some(id) => write!(writer, "{:<10} ", format!("task {}", id))?,
+                None => write!(writer, "{:<10} ", "")?,
+            }
+        }
 
         #[cfg(feature = "ansi")]
-        return write!(writer, "{:<8} ", ColorLevel(shared.level));
+        if self.ansi {
+            return write!(writer, "{:<8} ", ColorLevel(shared.level));
+        }
 
-        #[cfg(not(feature = "ansi"))]
-        return write!(writer, "{:<8} ", shared.level);
+        write!(writer, "{:<8} ", shared.level)
     }
 
     fn format_indent(indent: &[Indent], writer: &mut String) -> fmt::Result {
@@ -112,39 +493,204 @@ impl Pretty {
         Ok(())
     }
 
-    fn format_event(event: &Event, writer: &mut String) -> fmt::Result {
-        let tag = event.tag().unwrap_or_else(|| Tag::from(event.level()));
+    fn format_event(
+        &self,
+        event: &Event,
+        inherited: Inherited,
+        previous: Option<&Event>,
+        indent: &[Indent],
+        writer: &mut String,
+    ) -> fmt::Result {
+        let start = writer.len();
 
-        write!(writer, "{} [{}]: ", tag.icon(), tag)?;
+        match (event.tag(), inherited.tag) {
+            (Some(tag), _) => {
+                self.format_icon(tag.icon(), tag.icon_level(), writer)?;
+                write!(writer, " [{}]: ", tag)?;
+            }
+            (None, Some(span_tag)) if self.inherit_span_tag => {
+                let tag = Tag::from(event.level());
+                self.format_icon(tag.icon(), tag.icon_level(), writer)?;
+                write!(writer, " [{}.{}]: ", span_tag, tag)?;
+            }
+            (None, _) => {
+                let tag = Tag::from(event.level());
+                self.format_icon(tag.icon(), tag.icon_level(), writer)?;
+                write!(writer, " [{}]: ", tag)?;
+            }
+        }
+
+        let align =
+            self.shared_width() + Indent::WIDTH * indent.len() + display_width(&writer[start..]);
 
+        // Untagged events take on the color of their span.
+        #[cfg(feature = "ansi")]
+        if let Some(color) = inherited
+            .color
+            .filter(|_| self.ansi && event.tag().is_none())
+        {
+            let tag = writer.split_off(start);
+            write!(writer, "{}", ansi_color(color).paint(tag))?;
+        }
+
+        let start = writer.len();
         if let Some(message) = event.message() {
-            writer.write_str(message)?;
+            self.write_escaped(message, writer)?;
+            if let Some(max_len) = self.max_message_len {
+                // Escapes such as `⏎` take up columns too, so the escaped
+                // message is what's truncated.
+                truncate_message(writer, start, max_len);
+            }
         }
+        let mut column = align + display_width(&writer[start..]);
+
+        let width = self.wrap_width();
+        let mut field_str = String::new();
+
+        for field in self.sort_fields.sort(event.fields()) {
+            field_str.clear();
+            write!(field_str, "| {}: ", field.key())?;
+            let unchanged = self.ditto_unchanged_fields
+                && previous.is_some_and(|previous| previous.fields().contains(field));
+            if unchanged {
+                field_str.write_char(DITTO)?;
+            } else {
+                self.write_escaped(field.value(), &mut field_str)?;
+            }
+            let field_width = display_width(&field_str);
+
+            match width {
+                Some(width) if column > align && column + 1 + field_width > width => {
+                    writeln!(writer)?;
+                    self.format_continuation(indent, align, writer)?;
+                    column = align;
+                }
+                _ => {
+                    writer.write_char(' ')?;
+                    column += 1;
+                }
+            }
 
-        for field in event.fields().iter() {
-            write!(writer, " | {}: {}", field.key(), field.value())?;
+            writer.write_str(&field_str)?;
+            column += field_width;
         }
 
         writeln!(writer)
     }
 
+    /// Writes `icon`, which is replaced according to the [`IconSet`] if it's
+    /// the default icon of `level`. Custom icons are always written as is.
+    fn format_icon(&self, icon: char, level: Option<Level>, writer: &mut String) -> fmt::Result {
+        let level = match level {
+            Some(level) => level,
+            None => return writer.write_char(icon),
+        };
+
+        match self.icon_set {
+            IconSet::Emoji => writer.write_char(icon),
+            IconSet::NerdFont => writer.write_char(match level {
+                Level::TRACE => '\u{f041}',
+                Level::DEBUG => '\u{f188}',
+                Level::INFO => '\u{f05a}',
+                Level::WARN => '\u{f071}',
+                Level::ERROR => '\u{f057}',
+            }),
+            IconSet::Ascii => write!(writer, "[{}]", level_char(level)),
+        }
+    }
+
+    fn format_continuation(
+        &self,
+        indent: &[Indent],
+        align: usize,
+        writer: &mut String,
+    ) -> fmt::Result {
+        let shared_width = self.shared_width();
+        write!(writer, "{:1$}", "", shared_width)?;
+
+        if let Some((last, rest)) = indent.split_last() {
+            Pretty::format_indent(rest, writer)?;
+            match last {
+                Indent::Fork | Indent::Line => writer.write_str(Indent::Line.repr())?,
+                Indent::Turn | Indent::Null => writer.write_str(Indent::Null.repr())?,
+            }
+        }
+
+        let used = shared_width + Indent::WIDTH * indent.len();
+        write!(writer, "{:1$}", "", align - used)
+    }
+
+    fn write_escaped(&self, value: &str, writer: &mut String) -> fmt::Result {
+        match self.escape {
+            None => {
+                for (i, line) in value.split('\n').enumerate() {
+                    if i > 0 {
+                        writer.write_char('⏎')?;
+                    }
+                    writer.write_str(&escape_control(line))?;
+                }
+                Ok(())
+            }
+            Some(EscapePolicy::None) => writer.write_str(value),
+            Some(EscapePolicy::Json) => EscapePolicy::write_json(value, writer),
+            Some(EscapePolicy::Shell) => {
+                let is_plain = value
+                    .chars()
+                    .all(|c| c.is_alphanumeric() || "-_./:@,+%".contains(c));
+
+                if is_plain && !value.is_empty() {
+                    writer.write_str(value)
+                } else {
+                    EscapePolicy::write_shell(value, writer)
+                }
+            }
+        }
+    }
+
     fn format_span(
+        &self,
         span: &Span,
         duration_root: Option<f64>,
+        inherited: Inherited,
         indent: &mut IndentVec,
         writer: &mut String,
     ) -> fmt::Result {
+        let inherited = Inherited {
+            tag: span.tag().or(inherited.tag),
+            color: span.color().or(inherited.color),
+        };
         let total_duration = span.total_duration().as_nanos() as f64;
         let inner_duration = span.inner_duration().as_nanos() as f64;
         let root_duration = duration_root.unwrap_or(total_duration);
         let percent_total_of_root_duration = 100.0 * total_duration / root_duration;
 
-        write!(
-            writer,
-            "{} [ {} | ",
-            span.name(),
-            DurationDisplay(total_duration)
-        )?;
+        let name = escape_control(span.display_name());
+
+        #[cfg(feature = "ansi")]
+        if self.ansi && span.status() == SpanStatus::Error {
+            write!(writer, "{}", Color::Red.bold().paint(name))?;
+        } else if let Some(color) = span.color().filter(|_| self.ansi) {
+            write!(writer, "{}", ansi_color(color).paint(name))?;
+        } else {
+            writer.write_str(&name)?;
+        }
+
+        #[cfg(not(feature = "ansi"))]
+        writer.write_str(&name)?;
+
+        if self.show_span_fields && !span.fields().is_empty() {
+            writer.write_char('{')?;
+            for (i, field) in self.sort_fields.sort(span.fields()).into_iter().enumerate() {
+                if i > 0 {
+                    writer.write_char(' ')?;
+                }
+                write!(writer, "{}=", field.key())?;
+                self.write_escaped(field.value(), writer)?;
+            }
+            writer.write_char('}')?;
+        }
+
+        write!(writer, " [ {} | ", DurationDisplay(total_duration))?;
 
         if inner_duration > 0.0 {
             let base_duration = span.base_duration().as_nanos() as f64;
@@ -154,7 +700,9 @@ impl Pretty {
 
         writeln!(writer, "{:.2}% ]", percent_total_of_root_duration)?;
 
-        if let Some((last, remaining)) = span.nodes().split_last() {
+        let nodes: Vec<&Tree> = span.nodes().iter().filter(|n| !n.is_hidden()).collect();
+
+        if let Some((last, remaining)) = nodes.split_last() {
             match indent.last_mut() {
                 Some(edge @ Indent::Turn) => *edge = Indent::Null,
                 Some(edge @ Indent::Fork) => *edge = Indent::Line,
@@ -163,17 +711,37 @@ impl Pretty {
 
             indent.push(Indent::Fork);
 
-            for tree in remaining {
+            // The previous visible sibling, if it's an event, for ditto marks.
+            let previous = |i: usize| {
+                let previous = i.checked_sub(1).map(|i| nodes[i]);
+                previous.and_then(|node| node.event().ok())
+            };
+
+            for (i, tree) in remaining.iter().enumerate() {
                 if let Some(edge) = indent.last_mut() {
                     *edge = Indent::Fork;
                 }
-                Pretty::format_tree(tree, Some(root_duration), indent, writer)?;
+                self.format_tree(
+                    tree,
+                    Some(root_duration),
+                    inherited,
+                    previous(i),
+                    indent,
+                    writer,
+                )?;
             }
 
             if let Some(edge) = indent.last_mut() {
                 *edge = Indent::Turn;
             }
-            Pretty::format_tree(last, Some(root_duration), indent, writer)?;
+            self.format_tree(
+                last,
+                Some(root_duration),
+                inherited,
+                previous(remaining.len()),
+                indent,
+                writer,
+            )?;
 
             indent.pop();
         }
@@ -182,6 +750,48 @@ impl Pretty {
     }
 }
 
+/// How [`Pretty`] displays the timestamp at the start of each line, set by
+/// [`Pretty::timestamp_format`].
+#[cfg(feature = "chrono")]
+#[derive(Clone, Copy, Debug, Default, Hash, PartialEq, Eq)]
+pub enum TimestampFormat {
+    /// RFC 3339 with as many fractional digits as needed, like
+    /// `2022-03-24T16:08:17.761149+00:00`.
+    ///
+    /// This is the default.
+    #[default]
+    Human,
+
+    /// RFC 3339 with exactly nine fractional digits, like
+    /// `2022-03-24T16:08:17.761149000+00:00`.
+    Rfc3339Nanos,
+
+    /// Nanoseconds since the Unix epoch as a bare integer, like
+    /// `1648138097761149000`.
+    UnixNanos,
+
+    /// No timestamp.
+    None,
+}
+
+/// The icons used by [`Pretty`] for tags created from a [`Level`], set by
+/// [`Pretty::icon_set`].
+#[derive(Clone, Copy, Debug, Default, Hash, PartialEq, Eq)]
+pub enum IconSet {
+    /// Emoji icons, like `🚧` for `WARN`.
+    ///
+    /// This is the default.
+    #[default]
+    Emoji,
+
+    /// [Nerd Font](https://www.nerdfonts.com) glyphs from Font Awesome.
+    NerdFont,
+
+    /// Bracketed ASCII letters, like `[W]` for `WARN`, for maximum
+    /// compatibility.
+    Ascii,
+}
+
 enum Indent {
     Null,
     Line,
@@ -189,7 +799,45 @@ enum Indent {
     Turn,
 }
 
+/// Returns the number of terminal columns that `s` occupies, treating wide
+/// characters like CJK ideographs, fullwidth forms, and emoji as two columns.
+fn display_width(s: &str) -> usize {
+    UnicodeWidthStr::width(s)
+}
+
+fn char_width(c: char) -> usize {
+    UnicodeWidthChar::width(c).unwrap_or(0)
+}
+
+/// Truncates the message written to `writer` after `start` to at most
+/// `max_width` display columns, replacing the end with `…` if it doesn't fit.
+///
+/// Zero-width characters, such as combining marks, stay with the character
+/// before them.
+fn truncate_message(writer: &mut String, start: usize, max_width: usize) {
+    if display_width(&writer[start..]) <= max_width {
+        return;
+    }
+
+    let mut width = 0;
+    let mut end = writer.len();
+    for (i, c) in writer[start..].char_indices() {
+        width += char_width(c);
+        // Leave room for the ellipsis.
+        if width + 1 > max_width {
+            end = start + i;
+            break;
+        }
+    }
+    writer.truncate(end);
+    if max_width > 0 {
+        writer.push('…');
+    }
+}
+
 impl Indent {
+    const WIDTH: usize = 3;
+
     fn repr(&self) -> &'static str {
         match self {
             Self::Null => "   ",
@@ -200,7 +848,7 @@ impl Indent {
     }
 }
 
-struct DurationDisplay(f64);
+pub(crate) struct DurationDisplay(pub(crate) f64);
 
 // Taken from chrono
 impl fmt::Display for DurationDisplay {
@@ -220,6 +868,31 @@ impl fmt::Display for DurationDisplay {
     }
 }
 
+/// What events inherit from their enclosing spans.
+#[derive(Clone, Copy, Default)]
+struct Inherited {
+    tag: Option<Tag>,
+    color: Option<SpanColor>,
+}
+
+/// Displayed in place of a field value that's unchanged from the previous
+/// sibling event, set by [`Pretty::ditto_unchanged_fields`].
+const DITTO: char = '〃';
+
+#[cfg(feature = "ansi")]
+fn ansi_color(color: SpanColor) -> Color {
+    match color {
+        SpanColor::Black => Color::Black,
+        SpanColor::Red => Color::Red,
+        SpanColor::Green => Color::Green,
+        SpanColor::Yellow => Color::Yellow,
+        SpanColor::Blue => Color::Blue,
+        SpanColor::Magenta => Color::Purple,
+        SpanColor::Cyan => Color::Cyan,
+        SpanColor::White => Color::White,
+    }
+}
+
 // From tracing-tree
 struct ColorLevel(Level);
 
@@ -0,0 +1,64 @@
+use crate::printer::{DurationDisplay, EscapePolicy, Formatter};
+use crate::tree::Tree;
+use std::fmt::{self, Write};
+use tracing::Level;
+
+/// Format each tree as a single summary line, regardless of how many spans
+/// and events it contains.
+///
+/// Each line contains the level and name of the root span, its total
+/// duration, and how many events and error events the tree contains. This is
+/// useful for access logs, where each request should produce exactly one line.
+///
+/// # Examples
+///
+/// ```log
+/// [INFO] try_from_entry_ro 1.34ms events=4 errors=1
+/// [WARN] Oh no, an event outside of a span events=1 errors=0
+/// ```
+///
+/// Events that aren't in a span are summarized with their message in place of
+/// the span name, and without a duration. Names and messages are escaped like
+/// JSON strings so that a newline in them can't break a tree across lines.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct SummaryLine {
+    _priv: (),
+}
+
+impl Formatter for SummaryLine {
+    type Error = fmt::Error;
+
+    fn fmt(&self, tree: &Tree) -> Result<String, fmt::Error> {
+        let mut writer = String::with_capacity(64);
+
+        if tree.is_hidden() {
+            return Ok(writer);
+        }
+
+        match tree {
+            Tree::Event(event) => {
+                write!(writer, "[{}] ", event.level())?;
+                EscapePolicy::write_json(event.message().unwrap_or(""), &mut writer)?;
+            }
+            Tree::Span(span) => {
+                let duration = span.total_duration().as_nanos() as f64;
+                write!(writer, "[{}] ", span.level())?;
+                EscapePolicy::write_json(span.display_name(), &mut writer)?;
+                write!(writer, " {}", DurationDisplay(duration))?;
+            }
+        }
+
+        let events = tree.count_events(|event| !event.is_hidden());
+        let errors = tree.count_events(|event| !event.is_hidden() && event.level() == Level::ERROR);
+        writeln!(writer, " events={} errors={}", events, errors)?;
+
+        Ok(writer)
+    }
+}
+
+impl SummaryLine {
+    /// Returns a new `SummaryLine` formatter.
+    pub const fn new() -> Self {
+        SummaryLine { _priv: () }
+    }
+}
@@ -0,0 +1,105 @@
+use crate::printer::Formatter;
+use crate::tree::{DurationFormat, Tree};
+use serde_yaml::value::{Mapping, TaggedValue, Value};
+
+/// Format logs as a stream of YAML documents, one per tree.
+///
+/// Each tree is serialized the same way as with `serde`, and starts with a
+/// `---` document marker, so that the output of many trees can be read back
+/// as a multi-document stream. Levels, timestamps, and durations are written
+/// as plain scalars.
+///
+/// # Examples
+///
+/// ```yaml
+/// ---
+/// schema_version: 6
+/// Span:
+///   level: INFO
+///   recorded_at_nanos: 2104500
+///   id: 1
+///   follows_from: []
+///   name: try_from_entry_ro
+///   display_name: null
+///   tag: null
+///   fields: {}
+///   status: unset
+///   instrumented: false
+///   color: null
+///   partial: false
+///   sampled: true
+///   nanos_total: 1340000
+///   nanos_nested: 0
+///   nanos_idle: 0
+///   activity:
+///     enters: 1
+///     exits: 1
+///     events: 1
+///   filtered_event_count: 0
+///   nodes:
+///   - Event:
+///       level: INFO
+///       recorded_at_nanos: 2960600
+///       message: Some filter info...
+///       tag: null
+///       fields:
+///         count: '3'
+/// ```
+///
+/// With the `uuid` and `chrono` features, trees also contain their `uuid`s and
+/// timestamps.
+///
+/// # Configuration
+///
+/// `Yaml` provides builder methods for configuring its output:
+/// * [`duration_format`](Yaml::duration_format) sets the unit that span
+///   durations are written in.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct Yaml {
+    duration_format: DurationFormat,
+}
+
+impl Yaml {
+    /// Returns a new `Yaml` formatter with the default configuration.
+    pub fn new() -> Self {
+        Yaml::default()
+    }
+
+    /// Set the unit that span durations are written in.
+    ///
+    /// Defaults to [`DurationFormat::Nanos`].
+    pub fn duration_format(mut self, format: DurationFormat) -> Self {
+        self.duration_format = format;
+        self
+    }
+}
+
+impl Formatter for Yaml {
+    type Error = serde_yaml::Error;
+
+    fn fmt(&self, tree: &Tree) -> Result<String, serde_yaml::Error> {
+        let mut value = serde_yaml::to_value(tree.with_duration_format(self.duration_format))?;
+        untag(&mut value);
+        Ok(format!("---\n{}", serde_yaml::to_string(&value)?))
+    }
+}
+
+/// Rewrites YAML tags, which `serde_yaml` uses for nested enum variants like
+/// `!Event`, into single-key mappings like `Event:`, matching how root trees
+/// and JSON output are written.
+fn untag(value: &mut Value) {
+    match value {
+        Value::Tagged(tagged) => {
+            let TaggedValue { tag, value: inner } = &mut **tagged;
+            untag(inner);
+
+            let key = tag.to_string().trim_start_matches('!').to_owned();
+            let mut mapping = Mapping::with_capacity(1);
+            mapping.insert(Value::String(key), std::mem::take(inner));
+            *value = Value::Mapping(mapping);
+        }
+        Value::Sequence(sequence) => sequence.iter_mut().for_each(untag),
+        Value::Mapping(mapping) => mapping.values_mut().for_each(untag),
+        _ => {}
+    }
+}
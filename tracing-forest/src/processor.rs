@@ -1,11 +1,30 @@
 //! Trait for processing log trees on completion.
 //!
 //! See [`Processor`] for more details.
-use crate::printer::{MakeStderr, MakeStdout, Pretty, Printer};
-use crate::tree::Tree;
+use crate::printer::{DurationDisplay, Formatter, MakeStderr, MakeStdout, Pretty, Printer};
+use crate::tree::{Span, Tree};
+use crate::{cfg_metrics, cfg_rusqlite, cfg_tokio};
+use std::collections::HashMap;
+use std::convert::TryFrom;
 use std::error;
-use std::sync::Arc;
+use std::io::{self, Write};
+use std::net::{TcpStream, ToSocketAddrs};
+#[cfg(unix)]
+use std::os::unix::net::UnixStream;
+#[cfg(unix)]
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::{self, SyncSender, TrySendError};
+use std::sync::{Arc, Mutex, Once, OnceLock, PoisonError};
+use std::thread;
+use std::time::{Duration, Instant};
 use thiserror::Error;
+use tracing::field::{display, Value};
+use tracing::metadata::Kind;
+use tracing::{Dispatch, Level, Metadata};
+use tracing_core::callsite::Callsite;
+use tracing_core::subscriber::Interest;
+use tracing_subscriber::fmt::MakeWriter;
 
 /// Error type returned if a [`Processor`] fails.
 #[derive(Error, Debug)]
@@ -50,8 +69,22 @@ pub trait Processor: 'static + Sized {
     /// `Box<dyn Error + Send + Sync>`. If the processor is configured with a
     /// fallback processor from [`Processor::or`], then the `Tree` is deferred
     /// to that processor.
+    // `Error` carries the unprocessed `Tree` so that it can be recovered.
+    #[allow(clippy::result_large_err)]
     fn process(&self, tree: Tree) -> Result;
 
+    /// Handle a [`Tree`] that was rejected before it could be processed, such
+    /// as one with a uuid collision under [`UuidCollisionPolicy::Error`].
+    ///
+    /// By default, the error is returned as is, and the layer panics. A
+    /// processor from [`Processor::or`] sends the tree to its fallback instead.
+    ///
+    /// [`UuidCollisionPolicy::Error`]: crate::layer::UuidCollisionPolicy::Error
+    #[allow(clippy::result_large_err)]
+    fn process_rejected(&self, err: Error) -> Result {
+        Err(err)
+    }
+
     /// Returns a `Processor` that first attempts processing with `self`, and
     /// resorts to processing with `fallback` on failure.
     ///
@@ -85,6 +118,59 @@ pub trait Processor: 'static + Sized {
     fn or_none(self) -> WithFallback<Self, Sink> {
         self.or(Sink)
     }
+
+    /// Returns a `Processor` that transforms each [`Tree`] with `f` before
+    /// processing it with `self`.
+    ///
+    /// This is useful for redacting, pruning, or enriching trees without
+    /// changing the downstream processor.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use tracing_forest::processor::Processor;
+    /// use tracing_forest::tree::Tree;
+    /// use tracing_forest::{ForestLayer, PrettyPrinter};
+    ///
+    /// let processor = PrettyPrinter::new().map(|mut tree: Tree| {
+    ///     tree.walk_mut(|node| {
+    ///         if let Tree::Event(event) = node {
+    ///             event.retain_fields(|field| field.key() != "password");
+    ///         }
+    ///     });
+    ///     tree
+    /// });
+    ///
+    /// let layer = ForestLayer::from(processor);
+    /// ```
+    fn map<F>(self, f: F) -> Map<Self, F>
+    where
+        F: 'static + Fn(Tree) -> Tree,
+    {
+        Map { inner: self, f }
+    }
+
+    /// Returns a `Processor` that drops trees whose most severe level is less
+    /// severe than `level`, and forwards the rest to `self`.
+    ///
+    /// The most severe level of a tree is that of the tree itself or anything
+    /// within it, as returned by [`Span::max_level`], so a span is kept if any
+    /// of its descendants is significant enough. This is computed once per
+    /// tree. Dropped trees are discarded without an error.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use tracing::Level;
+    /// use tracing_forest::processor::Processor;
+    /// use tracing_forest::{ForestLayer, PrettyPrinter};
+    ///
+    /// // Only print trees containing a warning or an error.
+    /// let layer = ForestLayer::from(PrettyPrinter::new().min_level(Level::WARN));
+    /// ```
+    fn min_level(self, level: Level) -> MinLevel<Self> {
+        MinLevel { inner: self, level }
+    }
 }
 
 /// A [`Processor`] composed of a primary and a fallback `Processor`.
@@ -96,6 +182,26 @@ pub struct WithFallback<P, F> {
     fallback: F,
 }
 
+/// A [`Processor`] that transforms each [`Tree`] before forwarding it to an
+/// inner `Processor`.
+///
+/// This type is returned by [`Processor::map`].
+#[derive(Debug)]
+pub struct Map<P, F> {
+    inner: P,
+    f: F,
+}
+
+/// A [`Processor`] that drops trees less severe than a minimum level before
+/// forwarding them to an inner `Processor`.
+///
+/// This type is returned by [`Processor::min_level`].
+#[derive(Debug)]
+pub struct MinLevel<P> {
+    inner: P,
+    level: Level,
+}
+
 /// A [`Processor`] that ignores any incoming logs.
 ///
 /// This processor cannot fail.
@@ -108,6 +214,1271 @@ pub struct Sink;
 #[derive(Debug)]
 pub struct FromFn<F>(F);
 
+/// A [`Processor`] that checks that each [`Tree`] is internally consistent
+/// before forwarding it to an inner `Processor`.
+///
+/// This is intended for tests, where catching malformed trees early is more
+/// valuable than continuing. The following invariants are checked for every span:
+/// * The duration of child spans doesn't exceed the total duration of the span.
+/// * The duration of child spans is at least the sum of the total durations of
+///   the span's direct child spans. It may be more, since children that were
+///   omitted by [`max_children`] or removed by [`Forest::filter`] still count.
+/// * The span was exited as many times as it was entered, so it isn't left
+///   open.
+///
+/// Partial snapshots written because of a [`flush_threshold`] describe spans
+/// that are still open, so they're forwarded without being checked.
+///
+/// [`max_children`]: crate::ForestLayer::max_children
+/// [`flush_threshold`]: crate::ForestLayer::flush_threshold
+/// [`Forest::filter`]: crate::tree::Forest::filter
+///
+/// # Panics
+///
+/// Processing panics with a description of the offending span if any invariant
+/// doesn't hold.
+///
+/// # Examples
+///
+/// ```
+/// use tracing_forest::processor::ValidatingProcessor;
+/// use tracing_forest::{ForestLayer, PrettyPrinter};
+///
+/// let layer = ForestLayer::from(ValidatingProcessor::new(PrettyPrinter::new()));
+/// ```
+#[derive(Debug)]
+pub struct ValidatingProcessor<P>(P);
+
+impl<P: Processor> ValidatingProcessor<P> {
+    /// Create a new `ValidatingProcessor` that forwards valid trees to `inner`.
+    pub fn new(inner: P) -> Self {
+        ValidatingProcessor(inner)
+    }
+}
+
+/// A [`Processor`] that only forwards trees containing an event with a given
+/// field to an inner `Processor`, and drops the rest.
+///
+/// A tree matches if any event in it has a field whose key and value are
+/// exactly equal to the configured key and value. Values are compared in the
+/// form they were recorded in, so `request_id = 42` has the value `42`. String
+/// values recorded like `request_id = "abc"` are quoted, and match both `abc`
+/// and `"abc"`.
+///
+/// This is useful for isolating the logs of a single request.
+///
+/// # Examples
+///
+/// ```
+/// use tracing_forest::processor::FieldMatchProcessor;
+/// use tracing_forest::{ForestLayer, PrettyPrinter};
+///
+/// let processor = FieldMatchProcessor::new(PrettyPrinter::new(), "request_id", "42");
+/// let layer = ForestLayer::from(processor);
+/// ```
+#[derive(Debug)]
+pub struct FieldMatchProcessor<P> {
+    inner: P,
+    key: String,
+    value: String,
+}
+
+impl<P: Processor> FieldMatchProcessor<P> {
+    /// Create a new `FieldMatchProcessor` that forwards trees containing an
+    /// event with the field `key` set to `value` to `inner`.
+    pub fn new(inner: P, key: impl Into<String>, value: impl Into<String>) -> Self {
+        FieldMatchProcessor {
+            inner,
+            key: key.into(),
+            value: value.into(),
+        }
+    }
+
+    fn matches(&self, tree: &Tree) -> bool {
+        match tree {
+            Tree::Event(event) => event
+                .fields()
+                .iter()
+                .any(|field| field.key() == self.key && self.matches_value(field.value())),
+            Tree::Span(span) => span.nodes().iter().any(|node| self.matches(node)),
+        }
+    }
+
+    fn matches_value(&self, value: &str) -> bool {
+        let unquoted = value
+            .strip_prefix('"')
+            .and_then(|value| value.strip_suffix('"'));
+
+        value == self.value || unquoted == Some(self.value.as_str())
+    }
+}
+
+/// A [`Processor`] that accumulates span durations by span name, and writes a
+/// table of duration percentiles when flushed or dropped.
+///
+/// This provides a lightweight profiling summary at the end of a run, such as
+/// when a [`worker_task`] shuts down. Trees are consumed and not forwarded.
+///
+/// Since `Drop` can't return an error, a failure to write the summary when
+/// the processor is dropped is printed to stderr instead. Call [`flush`]
+/// beforehand to handle the error.
+///
+/// # Examples
+///
+/// ```
+/// use tracing_forest::processor::SummaryProcessor;
+/// use tracing_forest::{traits::*, util::*};
+/// use tracing_subscriber::Registry;
+///
+/// let layer = ForestLayer::from(SummaryProcessor::new(std::io::stderr));
+///
+/// tracing::subscriber::with_default(Registry::default().with(layer), || {
+///     for _ in 0..3 {
+///         info_span!("request").in_scope(|| {});
+///     }
+/// });
+/// // The summary is written to stderr when the layer is dropped:
+/// // span        count      p50      p95      p99      max
+/// // request         3   1.20µs   2.10µs   2.10µs   2.10µs
+/// ```
+///
+/// [`worker_task`]: crate::runtime::worker_task
+/// [`flush`]: SummaryProcessor::flush
+#[derive(Debug)]
+pub struct SummaryProcessor<W: for<'a> MakeWriter<'a>> {
+    durations: Mutex<HashMap<&'static str, Vec<Duration>>>,
+    make_writer: W,
+}
+
+impl<W: for<'a> MakeWriter<'a>> SummaryProcessor<W> {
+    /// Create a new `SummaryProcessor` that writes its summary to `make_writer`.
+    pub fn new(make_writer: W) -> Self {
+        SummaryProcessor {
+            durations: Mutex::new(HashMap::new()),
+            make_writer,
+        }
+    }
+
+    /// Writes the summary of all spans processed since the last flush, and
+    /// resets the accumulated durations.
+    ///
+    /// Nothing is written if no spans were processed.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if writing the summary fails.
+    pub fn flush(&self) -> io::Result<()> {
+        let durations = std::mem::take(
+            &mut *self
+                .durations
+                .lock()
+                .unwrap_or_else(PoisonError::into_inner),
+        );
+
+        if durations.is_empty() {
+            return Ok(());
+        }
+
+        let summary = summarize(durations);
+        self.make_writer.make_writer().write_all(summary.as_bytes())
+    }
+
+    fn record(&self, tree: &Tree, durations: &mut HashMap<&'static str, Vec<Duration>>) {
+        if let Tree::Span(span) = tree {
+            durations
+                .entry(span.name)
+                .or_default()
+                .push(span.total_duration());
+
+            for node in span.nodes() {
+                self.record(node, durations);
+            }
+        }
+    }
+}
+
+impl<W: for<'a> MakeWriter<'a>> Drop for SummaryProcessor<W> {
+    fn drop(&mut self) {
+        if let Err(err) = self.flush() {
+            eprintln!("failed to write span summary: {}", err);
+        }
+    }
+}
+
+/// A [`Processor`] that counts `ERROR` events by message, for a digest of the
+/// most frequent errors.
+///
+/// Every `ERROR` event in each tree is counted, however deeply it's nested.
+/// Events without a message are counted under the empty string. Trees are
+/// consumed and not forwarded, so the processor is usually shared through an
+/// [`Arc`] to read the digest while the layer holds it.
+///
+/// # Examples
+///
+/// ```
+/// use std::sync::Arc;
+/// use tracing_forest::processor::ErrorDigestProcessor;
+/// use tracing_forest::{traits::*, util::*};
+/// use tracing_subscriber::Registry;
+///
+/// let digest = Arc::new(ErrorDigestProcessor::new());
+/// let layer = ForestLayer::from(digest.clone());
+///
+/// tracing::subscriber::with_default(Registry::default().with(layer), || {
+///     for _ in 0..2 {
+///         info_span!("request").in_scope(|| error!("connection reset"));
+///     }
+///     error!("disk full");
+/// });
+///
+/// assert!(digest.digest()[0] == ("connection reset".to_string(), 2));
+/// ```
+#[derive(Debug, Default)]
+pub struct ErrorDigestProcessor {
+    counts: Mutex<HashMap<String, usize>>,
+}
+
+impl ErrorDigestProcessor {
+    /// Create a new `ErrorDigestProcessor` that hasn't counted any errors.
+    pub fn new() -> Self {
+        ErrorDigestProcessor::default()
+    }
+
+    /// Returns each distinct error message with the number of times it
+    /// occurred, most frequent first.
+    ///
+    /// Messages that occurred equally often are sorted alphabetically, so the
+    /// digest is deterministic.
+    pub fn digest(&self) -> Vec<(String, usize)> {
+        let mut digest: Vec<(String, usize)> = self
+            .counts
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .iter()
+            .map(|(message, &count)| (message.clone(), count))
+            .collect();
+
+        digest.sort_by(|(a, a_count), (b, b_count)| b_count.cmp(a_count).then_with(|| a.cmp(b)));
+        digest
+    }
+
+    fn record(tree: &Tree, counts: &mut HashMap<String, usize>) {
+        match tree {
+            Tree::Event(event) if event.level() == Level::ERROR => {
+                let message = event.message().unwrap_or_default();
+                match counts.get_mut(message) {
+                    Some(count) => *count += 1,
+                    None => {
+                        counts.insert(message.to_owned(), 1);
+                    }
+                }
+            }
+            Tree::Event(_) => {}
+            Tree::Span(span) => {
+                for node in span.nodes() {
+                    ErrorDigestProcessor::record(node, counts);
+                }
+            }
+        }
+    }
+}
+
+/// The target of events re-emitted by [`ReemitProcessor`], which
+/// [`ForestLayer`]s ignore.
+///
+/// [`ForestLayer`]: crate::ForestLayer
+pub(crate) const REEMIT_TARGET: &str = "tracing_forest::reemit";
+
+/// A [`Processor`] that re-emits the events of each tree as flat `tracing`
+/// events, so that another subscriber can handle them.
+///
+/// This feeds trees into pipelines built on other subscribers, such as a JSON
+/// formatter. Each event is emitted at its original level with the target
+/// `tracing_forest::reemit`, its message, and the following fields:
+/// * `span`: the names of the enclosing spans from the root, joined with `/`,
+///   or empty for events outside of any span.
+/// * `fields`: the event's fields as space separated `key=value` pairs.
+///
+/// Spans themselves and [hidden] events aren't re-emitted. Trees are consumed
+/// and not forwarded.
+///
+/// Events are dispatched to the given subscriber directly, since `tracing`
+/// discards events emitted to the default subscriber while a subscriber is
+/// handling another event. `ForestLayer`s ignore events with the re-emitted
+/// target, so if the subscriber contains one, the events aren't collected
+/// into trees again.
+///
+/// [hidden]: crate::tree::Event::is_hidden
+///
+/// # Examples
+///
+/// ```
+/// use tracing_forest::processor::ReemitProcessor;
+/// use tracing_forest::ForestLayer;
+///
+/// let downstream = tracing_subscriber::fmt().finish();
+/// let layer = ForestLayer::from(ReemitProcessor::new(downstream));
+/// ```
+#[derive(Clone, Debug)]
+pub struct ReemitProcessor {
+    dispatch: Dispatch,
+}
+
+/// The callsite of events re-emitted at a level by [`ReemitProcessor`].
+struct ReemitCallsite(Metadata<'static>);
+
+impl Callsite for ReemitCallsite {
+    fn set_interest(&self, _interest: Interest) {}
+
+    fn metadata(&self) -> &Metadata<'static> {
+        &self.0
+    }
+}
+
+macro_rules! reemit_callsite {
+    ($name:ident, $level:expr) => {
+        static $name: ReemitCallsite = ReemitCallsite(Metadata::new(
+            "reemitted event",
+            REEMIT_TARGET,
+            $level,
+            Some(file!()),
+            Some(line!()),
+            Some(module_path!()),
+            tracing_core::field::FieldSet::new(
+                &["message", "span", "fields"],
+                tracing_core::identify_callsite!(&$name),
+            ),
+            Kind::EVENT,
+        ));
+    };
+}
+
+reemit_callsite!(REEMIT_TRACE, Level::TRACE);
+reemit_callsite!(REEMIT_DEBUG, Level::DEBUG);
+reemit_callsite!(REEMIT_INFO, Level::INFO);
+reemit_callsite!(REEMIT_WARN, Level::WARN);
+reemit_callsite!(REEMIT_ERROR, Level::ERROR);
+
+impl ReemitProcessor {
+    /// Create a new `ReemitProcessor` that emits events to `dispatch`, which
+    /// can be any subscriber.
+    pub fn new(dispatch: impl Into<Dispatch>) -> Self {
+        static REGISTER: Once = Once::new();
+        REGISTER.call_once(|| {
+            for callsite in [
+                &REEMIT_TRACE,
+                &REEMIT_DEBUG,
+                &REEMIT_INFO,
+                &REEMIT_WARN,
+                &REEMIT_ERROR,
+            ] {
+                tracing_core::callsite::register(callsite);
+            }
+        });
+
+        ReemitProcessor {
+            dispatch: dispatch.into(),
+        }
+    }
+
+    fn reemit<'a>(&self, tree: &'a Tree, path: &mut Vec<&'a str>) {
+        let event = match tree {
+            Tree::Event(event) => event,
+            Tree::Span(span) => {
+                path.push(span.display_name());
+                for node in span.nodes() {
+                    self.reemit(node, path);
+                }
+                path.pop();
+                return;
+            }
+        };
+
+        let callsite = match event.level() {
+            Level::TRACE => &REEMIT_TRACE,
+            Level::DEBUG => &REEMIT_DEBUG,
+            Level::INFO => &REEMIT_INFO,
+            Level::WARN => &REEMIT_WARN,
+            Level::ERROR => &REEMIT_ERROR,
+        };
+        let metadata = callsite.metadata();
+
+        if event.is_hidden() || !self.dispatch.enabled(metadata) {
+            return;
+        }
+
+        let span = path.join("/");
+        let mut fields = String::new();
+        for (i, field) in event.fields().iter().enumerate() {
+            if i > 0 {
+                fields.push(' ');
+            }
+            fields.push_str(field.key());
+            fields.push('=');
+            fields.push_str(field.value());
+        }
+        let message = event.message().unwrap_or_default();
+
+        // The fields of re-emitted callsites are always `message`, `span`,
+        // and `fields`, in that order.
+        let keys = metadata.fields();
+        let mut iter = keys.iter();
+        let mut key = || iter.next().expect("re-emitted callsites have three fields");
+        let (message_key, span_key, fields_key) = (key(), key(), key());
+
+        self.dispatch.event(&tracing::Event::new_child_of(
+            None,
+            metadata,
+            &keys.value_set(&[
+                (&message_key, Some(&display(message) as &dyn Value)),
+                (&span_key, Some(&display(span) as &dyn Value)),
+                (&fields_key, Some(&display(fields) as &dyn Value)),
+            ]),
+        ));
+    }
+}
+
+/// A [`Processor`] that streams trees to an [`io::Write`] as the elements of a
+/// single JSON array.
+///
+/// The opening `[` is written with the first tree, each following tree is
+/// preceded by a `,`, and the closing `]` is written by [`finish`], or when
+/// the processor is dropped. Each tree is written as soon as it's processed,
+/// so the array is never held in memory. This is useful for tools that expect
+/// a whole forest as one JSON document instead of one tree per line.
+///
+/// Trees are serialized with a [`Formatter`], which should produce JSON, such
+/// as `serde_json::to_string` with the `serde` feature enabled.
+///
+/// Since `Drop` can't return an error, a failure to close the array when the
+/// processor is dropped is printed to stderr instead. Call [`finish`]
+/// beforehand to handle the error.
+///
+/// # Examples
+///
+/// ```
+/// use std::sync::Arc;
+/// use tracing_forest::processor::JsonArrayWriter;
+/// use tracing_forest::tree::Tree;
+/// use tracing_forest::{traits::*, util::*};
+/// use tracing_subscriber::Registry;
+///
+/// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+/// # let path = std::env::temp_dir().join("tracing-forest-json-array-doctest.json");
+/// let file = std::fs::File::create(&path)?;
+/// let writer = Arc::new(JsonArrayWriter::new(serde_json::to_string::<Tree>, file));
+///
+/// tracing::subscriber::with_default(
+///     Registry::default().with(ForestLayer::from(writer.clone())),
+///     || {
+///         info!("first");
+///         info!("second");
+///     },
+/// );
+///
+/// writer.finish()?;
+/// # let forest: Vec<serde_json::Value> = serde_json::from_reader(std::fs::File::open(&path)?)?;
+/// # assert!(forest.len() == 2);
+/// # Ok(())
+/// # }
+/// ```
+///
+/// [`Formatter`]: crate::printer::Formatter
+/// [`finish`]: JsonArrayWriter::finish
+#[derive(Debug)]
+pub struct JsonArrayWriter<F, W: Write> {
+    formatter: F,
+    state: Mutex<JsonArrayState<W>>,
+}
+
+#[derive(Debug)]
+struct JsonArrayState<W> {
+    writer: W,
+    len: usize,
+    finished: bool,
+}
+
+impl<F: 'static + Formatter, W: Write> JsonArrayWriter<F, W> {
+    /// Create a new `JsonArrayWriter` that serializes trees with `formatter`
+    /// and writes them to `writer`.
+    pub fn new(formatter: F, writer: W) -> Self {
+        JsonArrayWriter {
+            formatter,
+            state: Mutex::new(JsonArrayState {
+                writer,
+                len: 0,
+                finished: false,
+            }),
+        }
+    }
+
+    /// Serializes `tree` and writes it as the next element of the array.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the tree can't be formatted or written, or if the
+    /// array was already [finished](JsonArrayWriter::finish).
+    pub fn push(
+        &self,
+        tree: &Tree,
+    ) -> std::result::Result<(), Box<dyn error::Error + Send + Sync>> {
+        let json = self.formatter.fmt(tree)?;
+        let mut state = self.state.lock().unwrap_or_else(PoisonError::into_inner);
+
+        if state.finished {
+            return Err("the JSON array was already finished".into());
+        }
+
+        let separator = if state.len == 0 { b"[" } else { b"," };
+        state.writer.write_all(separator)?;
+        state.writer.write_all(json.as_bytes())?;
+        state.len += 1;
+        Ok(())
+    }
+
+    /// Returns the number of trees written so far.
+    pub fn len(&self) -> usize {
+        self.state
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .len
+    }
+
+    /// Returns `true` if no trees have been written.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Closes the array and flushes the writer. An array without any trees is
+    /// written as `[]`.
+    ///
+    /// Finishing an array more than once has no effect.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if writing or flushing fails.
+    pub fn finish(&self) -> io::Result<()> {
+        self.state
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .finish()
+    }
+}
+
+impl<W: Write> JsonArrayState<W> {
+    fn finish(&mut self) -> io::Result<()> {
+        if self.finished {
+            return Ok(());
+        }
+        self.finished = true;
+
+        let end: &[u8] = if self.len == 0 { b"[]" } else { b"]" };
+        self.writer.write_all(end)?;
+        self.writer.flush()
+    }
+}
+
+impl<F, W: Write> Drop for JsonArrayWriter<F, W> {
+    fn drop(&mut self) {
+        let state = self.state.get_mut().unwrap_or_else(PoisonError::into_inner);
+        if let Err(err) = state.finish() {
+            eprintln!("failed to finish JSON array: {}", err);
+        }
+    }
+}
+
+/// The address a [`SocketProcessor`] connects to.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum SocketTarget {
+    /// A TCP address, such as `"127.0.0.1:9000"` or `"collector:9000"`.
+    Tcp(String),
+    /// The path of a Unix domain socket.
+    #[cfg(unix)]
+    Unix(PathBuf),
+}
+
+impl SocketTarget {
+    fn connect(&self, timeout: Duration) -> io::Result<Box<dyn Write + Send>> {
+        match self {
+            SocketTarget::Tcp(addr) => {
+                let mut last_err = None;
+                for addr in addr.to_socket_addrs()? {
+                    match TcpStream::connect_timeout(&addr, timeout) {
+                        Ok(stream) => {
+                            stream.set_nodelay(true)?;
+                            stream.set_write_timeout(Some(timeout))?;
+                            return Ok(Box::new(stream));
+                        }
+                        Err(err) => last_err = Some(err),
+                    }
+                }
+                Err(last_err.unwrap_or_else(|| {
+                    io::Error::new(io::ErrorKind::InvalidInput, "address resolved to nothing")
+                }))
+            }
+            #[cfg(unix)]
+            SocketTarget::Unix(path) => {
+                let stream = UnixStream::connect(path)?;
+                stream.set_write_timeout(Some(timeout))?;
+                Ok(Box::new(stream))
+            }
+        }
+    }
+}
+
+/// A [`Processor`] that streams each [`Tree`] over a TCP or Unix domain
+/// socket, such as to a sidecar log collector.
+///
+/// Each tree is serialized with a [`Formatter`], typically
+/// `serde_json::to_string::<Tree>`, and written as a frame: the length of the
+/// serialized tree in bytes as a big-endian `u32`, followed by the tree itself.
+///
+/// Connecting and writing happen on a dedicated `std` thread that is started
+/// when the first tree is processed, so an unreachable or slow collector never
+/// blocks the traced application. This is a plain thread rather than a task on
+/// the [`worker_task`] runtime, so the processor also works outside of tokio,
+/// and its writes never stall the worker task. Frames wait in a buffer of at
+/// most [`capacity`] trees, and processing a tree while the buffer is full
+/// returns an error so that the tree can be handled by a
+/// [fallback](Processor::or). Connecting and each write give up after
+/// [`timeout`], after which the background thread reconnects after
+/// [`reconnect_delay`] and resends the frame that failed.
+///
+/// When the processor is dropped, the background thread delivers the trees
+/// still in the buffer before exiting, unless the collector can't be reached,
+/// in which case they are discarded. Dropping waits at most
+/// [`shutdown_timeout`] for this, after which the thread is detached and the
+/// remaining trees may be lost.
+///
+/// # Examples
+///
+/// ```no_run
+/// use tracing_forest::processor::SocketProcessor;
+/// use tracing_forest::traits::*;
+/// use tracing_forest::tree::Tree;
+///
+/// # #[tokio::main(flavor = "current_thread")]
+/// # async fn main() {
+/// tracing_forest::worker_task()
+///     .map_receiver(|_| {
+///         SocketProcessor::tcp("127.0.0.1:9000", serde_json::to_string::<Tree>).or_stderr()
+///     })
+///     .build()
+///     .on(async {
+///         tracing::info!("hello, collector");
+///     })
+///     .await;
+/// # }
+/// ```
+///
+/// [`Formatter`]: crate::printer::Formatter
+/// [`worker_task`]: crate::runtime::worker_task
+/// [`capacity`]: SocketProcessor::capacity
+/// [`timeout`]: SocketProcessor::timeout
+/// [`reconnect_delay`]: SocketProcessor::reconnect_delay
+/// [`shutdown_timeout`]: SocketProcessor::shutdown_timeout
+#[derive(Debug)]
+pub struct SocketProcessor<F> {
+    formatter: F,
+    target: SocketTarget,
+    capacity: usize,
+    timeout: Duration,
+    reconnect_delay: Duration,
+    shutdown_timeout: Duration,
+    worker: OnceLock<SocketWorker>,
+}
+
+#[derive(Debug)]
+struct SocketWorker {
+    sender: SyncSender<Vec<u8>>,
+    closed: Arc<AtomicBool>,
+    handle: thread::JoinHandle<()>,
+}
+
+impl<F: 'static + Formatter> SocketProcessor<F> {
+    /// Create a new `SocketProcessor` that serializes trees with `formatter`
+    /// and writes them to `target`.
+    ///
+    /// By default, at most 1024 trees are buffered, connecting and writing
+    /// time out after 5 seconds, reconnection is attempted once per second,
+    /// and dropping the processor waits at most 5 seconds.
+    pub fn new(target: SocketTarget, formatter: F) -> Self {
+        SocketProcessor {
+            formatter,
+            target,
+            capacity: 1024,
+            timeout: Duration::from_secs(5),
+            reconnect_delay: Duration::from_secs(1),
+            shutdown_timeout: Duration::from_secs(5),
+            worker: OnceLock::new(),
+        }
+    }
+
+    /// Create a new `SocketProcessor` that connects to the TCP address `addr`.
+    pub fn tcp(addr: impl Into<String>, formatter: F) -> Self {
+        SocketProcessor::new(SocketTarget::Tcp(addr.into()), formatter)
+    }
+
+    /// Create a new `SocketProcessor` that connects to the Unix domain socket
+    /// at `path`.
+    #[cfg(unix)]
+    pub fn unix(path: impl Into<PathBuf>, formatter: F) -> Self {
+        SocketProcessor::new(SocketTarget::Unix(path.into()), formatter)
+    }
+
+    /// Set the maximum number of trees waiting to be written.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `capacity` is 0.
+    pub fn capacity(mut self, capacity: usize) -> Self {
+        assert!(capacity > 0, "capacity must be greater than 0");
+        self.capacity = capacity;
+        self
+    }
+
+    /// Set how long connecting to a TCP target, and each write, may take
+    /// before the connection is considered failed.
+    ///
+    /// Connecting to a Unix domain socket doesn't time out.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `timeout` is zero.
+    pub fn timeout(mut self, timeout: Duration) -> Self {
+        assert!(!timeout.is_zero(), "timeout must be greater than 0");
+        self.timeout = timeout;
+        self
+    }
+
+    /// Set how long to wait before reconnecting after a connection fails.
+    pub fn reconnect_delay(mut self, delay: Duration) -> Self {
+        self.reconnect_delay = delay;
+        self
+    }
+
+    /// Set how long dropping the processor waits for buffered trees to be
+    /// delivered before detaching the background thread.
+    pub fn shutdown_timeout(mut self, timeout: Duration) -> Self {
+        self.shutdown_timeout = timeout;
+        self
+    }
+
+    /// Returns the address this processor connects to.
+    pub fn target(&self) -> &SocketTarget {
+        &self.target
+    }
+
+    /// Serializes `tree` and buffers its frame to be written.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the tree can't be formatted, if it's too large to
+    /// be framed, or if the buffer is full.
+    pub fn send(
+        &self,
+        tree: &Tree,
+    ) -> std::result::Result<(), Box<dyn error::Error + Send + Sync>> {
+        let json = self.formatter.fmt(tree)?;
+        let len = u32::try_from(json.len())
+            .map_err(|_| "the serialized tree is too large to be framed")?;
+
+        let mut frame = Vec::with_capacity(4 + json.len());
+        frame.extend_from_slice(&len.to_be_bytes());
+        frame.extend_from_slice(json.as_bytes());
+
+        let worker = self.worker.get_or_init(|| self.spawn());
+        worker.sender.try_send(frame).map_err(|err| match err {
+            TrySendError::Full(_) => "the socket buffer is full",
+            TrySendError::Disconnected(_) => "the socket thread has stopped",
+        })?;
+        Ok(())
+    }
+
+    fn spawn(&self) -> SocketWorker {
+        let (sender, receiver) = mpsc::sync_channel::<Vec<u8>>(self.capacity);
+        let closed = Arc::new(AtomicBool::new(false));
+        let target = self.target.clone();
+        let timeout = self.timeout;
+        let delay = self.reconnect_delay;
+
+        let handle = thread::spawn({
+            let closed = closed.clone();
+            move || {
+                let mut stream: Option<Box<dyn Write + Send>> = None;
+
+                'frames: for frame in receiver {
+                    loop {
+                        if stream.is_none() {
+                            match target.connect(timeout) {
+                                Ok(connected) => stream = Some(connected),
+                                Err(_) if closed.load(Ordering::Acquire) => break 'frames,
+                                Err(_) => {
+                                    thread::sleep(delay);
+                                    continue;
+                                }
+                            }
+                        }
+
+                        if let Some(conn) = stream.as_mut() {
+                            if conn.write_all(&frame).and_then(|()| conn.flush()).is_ok() {
+                                break;
+                            }
+                        }
+                        // Resend the frame on a new connection.
+                        stream = None;
+                    }
+                }
+            }
+        });
+
+        SocketWorker {
+            sender,
+            closed,
+            handle,
+        }
+    }
+}
+
+impl<F> Drop for SocketProcessor<F> {
+    fn drop(&mut self) {
+        if let Some(worker) = self.worker.take() {
+            worker.closed.store(true, Ordering::Release);
+            drop(worker.sender);
+            // Past the deadline, the thread is detached by dropping its handle.
+            let deadline = Instant::now() + self.shutdown_timeout;
+            while !worker.handle.is_finished() {
+                if Instant::now() >= deadline {
+                    return;
+                }
+                thread::sleep(Duration::from_millis(1));
+            }
+            let _ = worker.handle.join();
+        }
+    }
+}
+
+fn summarize(durations: HashMap<&'static str, Vec<Duration>>) -> String {
+    use std::fmt::Write;
+
+    fn percentile(sorted: &[Duration], percent: usize) -> DurationDisplay {
+        // Nearest-rank method.
+        let rank = (percent * sorted.len()).div_ceil(100);
+        DurationDisplay(sorted[rank.max(1) - 1].as_nanos() as f64)
+    }
+
+    let mut rows: Vec<_> = durations.into_iter().collect();
+    rows.sort_unstable_by_key(|(name, _)| *name);
+
+    let name_width = rows
+        .iter()
+        .map(|(name, _)| name.chars().count())
+        .max()
+        .unwrap_or(0)
+        .max("span".len());
+
+    let mut summary = String::new();
+    let _ = writeln!(
+        summary,
+        "{:<name_width$} {:>8} {:>8} {:>8} {:>8} {:>8}",
+        "span",
+        "count",
+        "p50",
+        "p95",
+        "p99",
+        "max",
+        name_width = name_width,
+    );
+
+    for (name, mut durations) in rows {
+        durations.sort_unstable();
+        let _ = writeln!(
+            summary,
+            "{:<name_width$} {:>8} {:>8} {:>8} {:>8} {:>8}",
+            name,
+            durations.len(),
+            percentile(&durations, 50).to_string(),
+            percentile(&durations, 95).to_string(),
+            percentile(&durations, 99).to_string(),
+            percentile(&durations, 100).to_string(),
+            name_width = name_width,
+        );
+    }
+
+    summary
+}
+
+fn validate(tree: &Tree, path: &mut Vec<&'static str>) {
+    let span = match tree {
+        Tree::Event(_) => return,
+        Tree::Span(span) => span,
+    };
+
+    path.push(span.name);
+
+    let activity = span.activity();
+    assert!(
+        activity.enters == activity.exits,
+        "span `{}` was left open, entered {} times but exited {} times",
+        path.join(" > "),
+        activity.enters,
+        activity.exits,
+    );
+
+    assert!(
+        span.inner_duration() <= span.total_duration(),
+        "span `{}` has an inner duration ({:?}) greater than its total duration ({:?})",
+        path.join(" > "),
+        span.inner_duration(),
+        span.total_duration(),
+    );
+
+    let children_duration: Duration = span
+        .nodes()
+        .iter()
+        .filter_map(|node| node.span().ok())
+        .map(Span::total_duration)
+        .sum();
+
+    assert!(
+        span.inner_duration() >= children_duration,
+        "span `{}` has an inner duration ({:?}) less than the total duration of its child spans ({:?})",
+        path.join(" > "),
+        span.inner_duration(),
+        children_duration,
+    );
+
+    for node in span.nodes() {
+        validate(node, path);
+    }
+
+    path.pop();
+}
+
+cfg_tokio! {
+    /// A [`Processor`] that publishes each [`Tree`] to all current subscribers
+    /// of a [`tokio::sync::broadcast`] channel.
+    ///
+    /// This is useful for live tailing, where multiple consumers such as a web
+    /// UI and a file writer want to observe the same stream of trees. Clones of
+    /// a `BroadcastProcessor` publish to the same channel, so a clone can be
+    /// kept to call [`subscribe`] after the processor is given to a layer.
+    ///
+    /// The channel holds at most `capacity` trees. When a subscriber falls
+    /// behind, the oldest trees are dropped and its next call to `recv` returns
+    /// [`RecvError::Lagged`] with the number of trees it missed. Trees published
+    /// while there are no subscribers are dropped.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use tracing_forest::processor::BroadcastProcessor;
+    /// use tracing_forest::ForestLayer;
+    ///
+    /// let processor = BroadcastProcessor::new(64);
+    /// let receiver = processor.subscribe();
+    ///
+    /// let layer = ForestLayer::from(processor);
+    /// ```
+    ///
+    /// [`subscribe`]: BroadcastProcessor::subscribe
+    /// [`RecvError::Lagged`]: tokio::sync::broadcast::error::RecvError::Lagged
+    #[derive(Clone, Debug)]
+    pub struct BroadcastProcessor {
+        sender: tokio::sync::broadcast::Sender<Tree>,
+    }
+
+    impl BroadcastProcessor {
+        /// Create a new `BroadcastProcessor` whose channel holds at most
+        /// `capacity` trees.
+        ///
+        /// # Panics
+        ///
+        /// Panics if `capacity` is 0.
+        pub fn new(capacity: usize) -> Self {
+            let (sender, _) = tokio::sync::broadcast::channel(capacity);
+            BroadcastProcessor { sender }
+        }
+
+        /// Returns a new receiver of all trees published after this call.
+        pub fn subscribe(&self) -> tokio::sync::broadcast::Receiver<Tree> {
+            self.sender.subscribe()
+        }
+    }
+
+    impl Processor for BroadcastProcessor {
+        fn process(&self, tree: Tree) -> Result {
+            // Sending only fails when there are no subscribers, in which case
+            // there's nobody to publish to.
+            let _ = self.sender.send(tree);
+            Ok(())
+        }
+    }
+}
+
+cfg_metrics! {
+    /// A [`Processor`] that records each [`Tree`] to the [`metrics`] facade.
+    ///
+    /// For every span in a tree, the span's total duration is recorded to the
+    /// `span.duration` histogram with a `span` label containing the name of
+    /// the span. For every event, the `events` counter is incremented with a
+    /// `level` label containing the level of the event.
+    ///
+    /// This bridges trees into dashboards built on the `metrics` ecosystem.
+    /// Since it doesn't write anything itself, it's typically combined with
+    /// another processor in a [`Processor::map`] or a custom [`Processor`].
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use tracing_forest::processor::MetricsProcessor;
+    /// use tracing_forest::ForestLayer;
+    ///
+    /// let layer = ForestLayer::from(MetricsProcessor::new());
+    /// ```
+    #[derive(Clone, Copy, Debug, Default)]
+    pub struct MetricsProcessor(());
+
+    impl MetricsProcessor {
+        /// Create a new `MetricsProcessor`.
+        pub const fn new() -> Self {
+            MetricsProcessor(())
+        }
+    }
+
+    impl Processor for MetricsProcessor {
+        fn process(&self, tree: Tree) -> Result {
+            record_metrics(&tree);
+            Ok(())
+        }
+    }
+
+    fn record_metrics(tree: &Tree) {
+        match tree {
+            Tree::Event(event) => {
+                metrics::counter!("events", "level" => event.level().as_str()).increment(1);
+            }
+            Tree::Span(span) => {
+                metrics::histogram!("span.duration", "span" => span.name)
+                    .record(span.total_duration());
+                span.nodes().iter().for_each(record_metrics);
+            }
+        }
+    }
+}
+
+cfg_rusqlite! {
+    /// The tables created by [`SqliteProcessor`].
+    const SQLITE_SCHEMA: &str = "
+        CREATE TABLE IF NOT EXISTS spans (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            parent_id INTEGER REFERENCES spans(id),
+            uuid TEXT,
+            timestamp TEXT,
+            level TEXT NOT NULL,
+            name TEXT NOT NULL,
+            tag TEXT,
+            status TEXT NOT NULL,
+            total_nanos INTEGER NOT NULL,
+            inner_nanos INTEGER NOT NULL,
+            idle_nanos INTEGER NOT NULL
+        );
+        CREATE TABLE IF NOT EXISTS events (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            span_id INTEGER REFERENCES spans(id),
+            uuid TEXT,
+            timestamp TEXT,
+            level TEXT NOT NULL,
+            message TEXT,
+            tag TEXT
+        );
+        CREATE TABLE IF NOT EXISTS fields (
+            event_id INTEGER NOT NULL REFERENCES events(id),
+            key TEXT NOT NULL,
+            value TEXT NOT NULL
+        );
+        CREATE INDEX IF NOT EXISTS spans_parent_id ON spans(parent_id);
+        CREATE INDEX IF NOT EXISTS events_span_id ON events(span_id);
+        CREATE INDEX IF NOT EXISTS fields_event_id ON fields(event_id);
+    ";
+
+    /// A [`Processor`] that inserts each [`Tree`] into a SQLite database.
+    ///
+    /// Spans, events, and event fields are stored in the `spans`, `events`,
+    /// and `fields` tables, which are created if they don't already exist.
+    /// Rows are linked by autoincrement ids: `spans.parent_id` and
+    /// `events.span_id` refer to the enclosing span, or are `NULL` at the root,
+    /// and `fields.event_id` refers to the event. The `uuid` and `timestamp`
+    /// columns are `NULL` unless the `uuid` and `chrono` features are enabled.
+    ///
+    /// Each tree is inserted in a single transaction.
+    ///
+    /// # Examples
+    ///
+    /// Querying all `ERROR` events anywhere under a span named `request`:
+    /// ```
+    /// use std::sync::Arc;
+    /// use tracing_forest::processor::SqliteProcessor;
+    /// use tracing_forest::{traits::*, util::*};
+    /// use tracing_subscriber::Registry;
+    ///
+    /// # fn main() -> rusqlite::Result<()> {
+    /// let processor = Arc::new(SqliteProcessor::new(rusqlite::Connection::open_in_memory()?)?);
+    ///
+    /// let subscriber = Registry::default().with(ForestLayer::from(processor.clone()));
+    /// tracing::subscriber::with_default(subscriber, || {
+    ///     info_span!("request").in_scope(|| {
+    ///         info_span!("query").in_scope(|| error!("timed out"));
+    ///     });
+    /// });
+    ///
+    /// let errors: i64 = processor.connection().query_row(
+    ///     "WITH RECURSIVE under(id) AS (
+    ///          SELECT id FROM spans WHERE name = 'request'
+    ///          UNION ALL
+    ///          SELECT spans.id FROM spans JOIN under ON spans.parent_id = under.id
+    ///      )
+    ///      SELECT COUNT(*) FROM events
+    ///      WHERE level = 'ERROR' AND span_id IN (SELECT id FROM under)",
+    ///     [],
+    ///     |row| row.get(0),
+    /// )?;
+    ///
+    /// assert!(errors == 1);
+    /// # Ok(())
+    /// # }
+    /// ```
+    #[derive(Debug)]
+    pub struct SqliteProcessor {
+        connection: Mutex<rusqlite::Connection>,
+    }
+
+    impl SqliteProcessor {
+        /// Create a new `SqliteProcessor` that inserts into an open connection,
+        /// creating the tables if they don't exist.
+        ///
+        /// # Errors
+        ///
+        /// Returns an error if the tables can't be created.
+        pub fn new(connection: rusqlite::Connection) -> rusqlite::Result<Self> {
+            connection.execute_batch(SQLITE_SCHEMA)?;
+            Ok(SqliteProcessor {
+                connection: Mutex::new(connection),
+            })
+        }
+
+        /// Create a new `SqliteProcessor` that inserts into the database at
+        /// `path`, creating it and the tables if they don't exist.
+        ///
+        /// # Errors
+        ///
+        /// Returns an error if the database can't be opened or the tables can't
+        /// be created.
+        pub fn open(path: impl AsRef<std::path::Path>) -> rusqlite::Result<Self> {
+            SqliteProcessor::new(rusqlite::Connection::open(path)?)
+        }
+
+        /// Returns the underlying connection, for querying the stored trees.
+        pub fn connection(&self) -> std::sync::MutexGuard<'_, rusqlite::Connection> {
+            self.connection.lock().unwrap_or_else(PoisonError::into_inner)
+        }
+    }
+
+    impl Processor for SqliteProcessor {
+        fn process(&self, tree: Tree) -> Result {
+            let result = self.connection().transaction().and_then(|transaction| {
+                insert_tree(&transaction, &tree, None)?;
+                transaction.commit()
+            });
+
+            match result {
+                Ok(()) => Ok(()),
+                Err(err) => Err(error(tree, err.into())),
+            }
+        }
+    }
+
+    fn insert_tree(
+        connection: &rusqlite::Connection,
+        tree: &Tree,
+        parent_id: Option<i64>,
+    ) -> rusqlite::Result<()> {
+        use crate::tree::SpanStatus;
+
+        fn nanos(duration: Duration) -> i64 {
+            i64::try_from(duration.as_nanos()).unwrap_or(i64::MAX)
+        }
+
+        let (shared, tag) = match tree {
+            Tree::Event(event) => (&event.shared, event.tag()),
+            Tree::Span(span) => (&span.shared, span.tag()),
+        };
+
+        #[cfg(feature = "uuid")]
+        let uuid = Some(shared.uuid.to_string());
+        #[cfg(not(feature = "uuid"))]
+        let uuid: Option<String> = None;
+
+        #[cfg(feature = "chrono")]
+        let timestamp = Some(shared.timestamp.to_rfc3339());
+        #[cfg(not(feature = "chrono"))]
+        let timestamp: Option<String> = None;
+
+        let level = shared.level.as_str();
+        let tag = tag.map(|tag| tag.to_string());
+
+        match tree {
+            Tree::Event(event) => {
+                connection.execute(
+                    "INSERT INTO events (span_id, uuid, timestamp, level, message, tag)
+                     VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+                    rusqlite::params![parent_id, uuid, timestamp, level, event.message(), tag],
+                )?;
+                let event_id = connection.last_insert_rowid();
+
+                for field in event.fields() {
+                    connection.execute(
+                        "INSERT INTO fields (event_id, key, value) VALUES (?1, ?2, ?3)",
+                        rusqlite::params![event_id, field.key(), field.value()],
+                    )?;
+                }
+            }
+            Tree::Span(span) => {
+                let status = match span.status() {
+                    SpanStatus::Unset => "unset",
+                    SpanStatus::Ok => "ok",
+                    SpanStatus::Error => "error",
+                };
+
+                connection.execute(
+                    "INSERT INTO spans (parent_id, uuid, timestamp, level, name, tag, status,
+                                        total_nanos, inner_nanos, idle_nanos)
+                     VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10)",
+                    rusqlite::params![
+                        parent_id,
+                        uuid,
+                        timestamp,
+                        level,
+                        span.name(),
+                        tag,
+                        status,
+                        nanos(span.total_duration()),
+                        nanos(span.inner_duration()),
+                        nanos(span.idle_duration()),
+                    ],
+                )?;
+                let span_id = connection.last_insert_rowid();
+
+                for node in span.nodes() {
+                    insert_tree(connection, node, Some(span_id))?;
+                }
+            }
+        }
+
+        Ok(())
+    }
+}
+
 /// Create a processor that processes incoming logs via a function.
 ///
 /// # Examples
@@ -144,12 +1515,116 @@ where
     P: Processor,
     F: Processor,
 {
+    #[allow(clippy::result_large_err)]
     fn process(&self, tree: Tree) -> Result {
         self.primary.process(tree).or_else(|err| {
             eprintln!("{}, using fallback processor...", err);
             self.fallback.process(err.tree)
         })
     }
+
+    #[allow(clippy::result_large_err)]
+    fn process_rejected(&self, err: Error) -> Result {
+        self.primary.process_rejected(err).or_else(|err| {
+            eprintln!("{}, using fallback processor...", err);
+            self.fallback.process(err.tree)
+        })
+    }
+}
+
+impl<P, F> Processor for Map<P, F>
+where
+    P: Processor,
+    F: 'static + Fn(Tree) -> Tree,
+{
+    fn process(&self, tree: Tree) -> Result {
+        self.inner.process((self.f)(tree))
+    }
+}
+
+impl<P: Processor> Processor for MinLevel<P> {
+    fn process(&self, tree: Tree) -> Result {
+        let max_level = match &tree {
+            Tree::Event(event) => event.level(),
+            Tree::Span(span) => span.max_level(),
+        };
+
+        if max_level > self.level {
+            return Ok(());
+        }
+
+        self.inner.process(tree)
+    }
+}
+
+impl<W> Processor for SummaryProcessor<W>
+where
+    W: 'static + for<'a> MakeWriter<'a>,
+{
+    fn process(&self, tree: Tree) -> Result {
+        let mut durations = self
+            .durations
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner);
+        self.record(&tree, &mut durations);
+        Ok(())
+    }
+}
+
+impl Processor for ErrorDigestProcessor {
+    fn process(&self, tree: Tree) -> Result {
+        let mut counts = self.counts.lock().unwrap_or_else(PoisonError::into_inner);
+        ErrorDigestProcessor::record(&tree, &mut counts);
+        Ok(())
+    }
+}
+
+impl Processor for ReemitProcessor {
+    fn process(&self, tree: Tree) -> Result {
+        self.reemit(&tree, &mut Vec::new());
+        Ok(())
+    }
+}
+
+impl<F, W> Processor for JsonArrayWriter<F, W>
+where
+    F: 'static + Formatter,
+    W: 'static + Write,
+{
+    fn process(&self, tree: Tree) -> Result {
+        match self.push(&tree) {
+            Ok(()) => Ok(()),
+            Err(e) => Err(error(tree, e)),
+        }
+    }
+}
+
+impl<F: 'static + Formatter> Processor for SocketProcessor<F> {
+    fn process(&self, tree: Tree) -> Result {
+        match self.send(&tree) {
+            Ok(()) => Ok(()),
+            Err(e) => Err(error(tree, e)),
+        }
+    }
+}
+
+impl<P: Processor> Processor for ValidatingProcessor<P> {
+    fn process(&self, tree: Tree) -> Result {
+        if !tree.span().is_ok_and(Span::is_partial) {
+            validate(&tree, &mut Vec::new());
+        }
+        self.0.process(tree)
+    }
+}
+
+impl<P: Processor> Processor for FieldMatchProcessor<P> {
+    fn process(&self, tree: Tree) -> Result {
+        if self.matches(&tree) {
+            self.inner.process(tree)
+        } else {
+            Ok(())
+        }
+    }
 }
 
 impl Processor for Sink {
@@ -171,10 +1646,263 @@ impl<P: Processor> Processor for Box<P> {
     fn process(&self, tree: Tree) -> Result {
         self.as_ref().process(tree)
     }
+
+    fn process_rejected(&self, err: Error) -> Result {
+        self.as_ref().process_rejected(err)
+    }
 }
 
 impl<P: Processor> Processor for Arc<P> {
     fn process(&self, tree: Tree) -> Result {
         self.as_ref().process(tree)
     }
+
+    fn process_rejected(&self, err: Error) -> Result {
+        self.as_ref().process_rejected(err)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tree::{Event, Shared};
+
+    fn span(name: &'static str) -> Span {
+        let shared = Shared {
+            #[cfg(feature = "uuid")]
+            uuid: uuid::Uuid::nil(),
+            #[cfg(feature = "chrono")]
+            timestamp: chrono::Utc::now(),
+            level: Level::INFO,
+            recorded_at: crate::tree::since_epoch(),
+            #[cfg(feature = "tokio")]
+            task_id: None,
+        };
+        Span::new(shared, name)
+    }
+
+    #[test]
+    #[should_panic(expected = "span `inconsistent` has an inner duration")]
+    fn test_validate_inner_exceeds_total() {
+        let mut inconsistent = span("inconsistent");
+        inconsistent.total_duration = Duration::from_millis(1);
+        inconsistent.inner_duration = Duration::from_millis(2);
+
+        let _ = ValidatingProcessor::new(Sink).process(Tree::Span(inconsistent));
+    }
+
+    #[test]
+    #[should_panic(expected = "span `parent > child` has an inner duration")]
+    fn test_validate_nested_children_exceed_inner() {
+        let mut grandchild = span("grandchild");
+        grandchild.total_duration = Duration::from_millis(2);
+
+        let mut child = span("child");
+        child.total_duration = Duration::from_millis(3);
+        child.inner_duration = Duration::from_millis(1);
+        child.nodes.push(Tree::Span(grandchild));
+
+        let mut parent = span("parent");
+        parent.total_duration = Duration::from_millis(4);
+        parent.inner_duration = Duration::from_millis(3);
+        parent.nodes.push(Tree::Span(child));
+
+        let _ = ValidatingProcessor::new(Sink).process(Tree::Span(parent));
+    }
+
+    #[test]
+    #[should_panic(expected = "span `open` was left open, entered 2 times but exited 1 times")]
+    fn test_validate_open_span() {
+        let mut open = span("open");
+        open.activity.enters = 2;
+        open.activity.exits = 1;
+
+        let _ = ValidatingProcessor::new(Sink).process(Tree::Span(open));
+    }
+
+    #[test]
+    fn test_validate_skips_partial_snapshots() {
+        let mut open = span("open");
+        open.activity.enters = 1;
+        open.partial = true;
+
+        assert!(ValidatingProcessor::new(Sink)
+            .process(Tree::Span(open))
+            .is_ok());
+    }
+
+    #[test]
+    fn test_validate_consistent() {
+        let mut child = span("child");
+        child.total_duration = Duration::from_millis(2);
+
+        let mut parent = span("parent");
+        parent.total_duration = Duration::from_millis(3);
+        parent.inner_duration = Duration::from_millis(2);
+        parent.nodes.push(Tree::Span(child));
+
+        assert!(ValidatingProcessor::new(Sink)
+            .process(Tree::Span(parent))
+            .is_ok());
+    }
+
+    #[test]
+    #[allow(clippy::result_large_err)]
+    fn test_min_level() {
+        let forwarded = Arc::new(Mutex::new(Vec::new()));
+        let processor = from_fn({
+            let forwarded = forwarded.clone();
+            move |tree| {
+                forwarded.lock().unwrap().push(tree);
+                Ok(())
+            }
+        })
+        .min_level(Level::WARN);
+
+        let mut quiet = span("quiet");
+        quiet.push_node(Tree::Event(Event::new(Shared::new(Level::INFO))));
+        quiet.push_node(Tree::Event(Event::new(Shared::new(Level::DEBUG))));
+
+        let mut failing = span("failing");
+        let mut child = span("child");
+        child.push_node(Tree::Event(Event::new(Shared::new(Level::ERROR))));
+        failing.push_node(Tree::Span(child));
+
+        processor.process(Tree::Span(quiet)).unwrap();
+        processor.process(Tree::Span(failing)).unwrap();
+        processor
+            .process(Tree::Event(Event::new(Shared::new(Level::WARN))))
+            .unwrap();
+        processor
+            .process(Tree::Event(Event::new(Shared::new(Level::TRACE))))
+            .unwrap();
+
+        let forwarded = forwarded.lock().unwrap();
+        assert!(forwarded.len() == 2);
+        assert!(forwarded[0].root_name() == Some("failing"));
+        assert!(forwarded[1].event().unwrap().level() == Level::WARN);
+    }
+
+    #[test]
+    fn test_summary_percentiles() {
+        use crate::printer::MakeBuffer;
+
+        let buffer = MakeBuffer::new();
+        let summary = SummaryProcessor::new(buffer.clone());
+
+        for millis in 1..=100 {
+            let mut request = span("request");
+            request.total_duration = Duration::from_millis(millis);
+            request.inner_duration = Duration::from_millis(millis) / 2;
+
+            let mut query = span("db::query");
+            query.total_duration = Duration::from_millis(millis) / 2;
+            request.nodes.push(Tree::Span(query));
+
+            summary.process(Tree::Span(request)).unwrap();
+        }
+
+        drop(summary);
+
+        let contents = buffer.contents();
+        let lines: Vec<&str> = contents.lines().collect();
+
+        assert!(lines.len() == 3);
+        assert!(lines[0]
+            .split_whitespace()
+            .eq(["span", "count", "p50", "p95", "p99", "max"]));
+        assert!(lines[1].split_whitespace().eq([
+            "db::query",
+            "100",
+            "25.0ms",
+            "47.5ms",
+            "49.5ms",
+            "50.0ms"
+        ]));
+        assert!(lines[2]
+            .split_whitespace()
+            .eq(["request", "100", "50.0ms", "95.0ms", "99.0ms", "100ms"]));
+    }
+
+    #[test]
+    #[cfg(feature = "serde")]
+    fn test_json_array_writer() {
+        let mut buffer = Vec::new();
+        {
+            let writer = JsonArrayWriter::new(serde_json::to_string::<Tree>, &mut buffer);
+            for name in ["first", "second", "third"] {
+                writer.push(&Tree::Span(span(name))).unwrap();
+            }
+            assert!(writer.len() == 3);
+            writer.finish().unwrap();
+            assert!(writer.push(&Tree::Span(span("late"))).is_err());
+        }
+
+        let array: Vec<serde_json::Value> = serde_json::from_slice(&buffer).unwrap();
+        assert!(array.len() == 3);
+        assert!(array[0]["Span"]["name"] == "first");
+        assert!(array[2]["Span"]["name"] == "third");
+    }
+
+    #[test]
+    #[cfg(feature = "serde")]
+    fn test_json_array_writer_empty_on_drop() {
+        let mut buffer = Vec::new();
+        drop(JsonArrayWriter::new(
+            serde_json::to_string::<Tree>,
+            &mut buffer,
+        ));
+
+        assert!(buffer == b"[]");
+    }
+
+    #[test]
+    fn test_summary_flush_resets() {
+        use crate::printer::MakeBuffer;
+
+        let buffer = MakeBuffer::new();
+        let summary = SummaryProcessor::new(buffer.clone());
+
+        summary.process(Tree::Span(span("once"))).unwrap();
+        summary.flush().unwrap();
+        let flushed = buffer.contents();
+        assert!(flushed.contains("once"));
+
+        // Nothing new was processed, so dropping writes nothing more.
+        drop(summary);
+        assert!(buffer.contents() == flushed);
+    }
+
+    #[test]
+    fn test_error_digest() {
+        let event = |level: Level, message: &str| {
+            let mut event = Event::new(Shared::new(level));
+            event.set_message(Some(message.to_string()));
+            Tree::Event(event)
+        };
+
+        let digest = ErrorDigestProcessor::new();
+
+        for _ in 0..3 {
+            let mut request = span("request");
+            request.push_node(event(Level::INFO, "received"));
+            let mut query = span("query");
+            query.push_node(event(Level::ERROR, "connection reset"));
+            request.push_node(Tree::Span(query));
+            digest.process(Tree::Span(request)).unwrap();
+        }
+        digest.process(event(Level::ERROR, "disk full")).unwrap();
+        digest.process(event(Level::ERROR, "timed out")).unwrap();
+        digest.process(event(Level::ERROR, "timed out")).unwrap();
+        digest.process(event(Level::WARN, "disk full")).unwrap();
+
+        assert!(
+            digest.digest()
+                == [
+                    ("connection reset".to_string(), 3),
+                    ("timed out".to_string(), 2),
+                    ("disk full".to_string(), 1),
+                ]
+        );
+    }
 }
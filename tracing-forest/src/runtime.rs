@@ -53,18 +53,18 @@
 //! 
 //! The [`capture`] function offers the ability to programmatically inspect log
 //! trees generated by `tracing-forest`. It is the unit testing analog of
-//! [`worker_task`], except it returns `Vec<Tree>` after the future is completed,
-//! which can be then be inspected.
+//! [`worker_task`], except it returns a [`Forest`] of trees after the future is
+//! completed, which can be then be inspected.
 //! 
 //! ## Examples
 //! 
 //! ```
-//! use tracing_forest::tree::{Tree, Event, Span};
+//! use tracing_forest::tree::{Forest, Tree, Event, Span};
 //! use tracing::{info, info_span};
 //! 
 //! #[tokio::main]
 //! async fn main() -> Result<(), Box<dyn std::error::Error>> {
-//!     let logs: Vec<Tree> = tracing_forest::capture()
+//!     let logs: Forest = tracing_forest::capture()
 //!         .build()
 //!         .on(async {
 //!             info!("Hello, world!");
@@ -102,18 +102,21 @@
 //! 
 //! For full configuration options, see the [`Builder`] documentation.
 use crate::layer::ForestLayer;
-use crate::printer::PrettyPrinter;
-use crate::tree::Tree;
+use crate::printer::{Formatter, PrettyPrinter, Printer};
+use crate::tree::{Forest, Tree};
 use crate::fail;
 use crate::tag::{TagParser, NoTag};
 use crate::processor::{self, Processor, WithFallback};
+use std::collections::VecDeque;
 use std::future::Future;
-use std::iter;
+use std::sync::{Arc, Mutex, PoisonError};
 use tokio::sync::mpsc::{self, UnboundedReceiver};
 use tokio::sync::oneshot;
 use tracing::Subscriber;
 use tracing_subscriber::Registry;
-use tracing_subscriber::layer::{Layered, SubscriberExt as _};
+use tracing_subscriber::fmt::MakeWriter;
+use tracing_subscriber::filter::Filtered;
+use tracing_subscriber::layer::{Filter, Layer as _, Layered, SubscriberExt as _};
 
 /// Begins the configuration of a `ForestLayer` subscriber that sends log trees
 /// to a processing task for formatting and writing.
@@ -152,26 +155,37 @@ pub fn worker_task() -> Builder<InnerSender<impl Processor>, WorkerTask<PrettyPr
 /// [inspecting-trace-data]: crate::runtime#inspecting-trace-data-in-unit-tests-with-capture
 /// [`set_global`]: Builder::set_global
 pub fn capture() -> Builder<InnerSender<impl Processor>, Capture, NoTag> {
-    worker_task_inner(Capture(()), false)
+    worker_task_inner(Capture { printer: None, meta: (), limit: None, capacity: 0 }, false)
 }
 
 fn worker_task_inner<P>(worker_processor: P, is_global: bool) -> Builder<InnerSender<impl Processor>, P, NoTag> {
     let (tx, rx) = mpsc::unbounded_channel();
+    let retained = Arc::new(Mutex::new(Retained::default()));
 
-    let sender_processor = processor::from_fn(move |tree| tx
-        .send(tree)
-        .map_err(|err| {
-            let msg = err.to_string().into();
-            processor::error(err.0, msg)
-        })
-    );
+    let sender_processor = processor::from_fn({
+        let retained = retained.clone();
+        #[allow(clippy::result_large_err)]
+        move |tree| {
+            let mut retained = retained.lock().unwrap_or_else(PoisonError::into_inner);
+            if let Some(limit) = retained.limit {
+                return retained.retain(tree, limit);
+            }
+            drop(retained);
+
+            tx.send(tree).map_err(|err| {
+                let msg = err.to_string().into();
+                processor::error(err.0, msg)
+            })
+        }
+    });
 
     Builder {
         sender_processor: InnerSender(sender_processor),
         worker_processor,
-        receiver: rx,
+        receiver: LogReceiver { channel: rx, retained },
         tag: NoTag,
         is_global,
+        capture_panics: false,
     }
 }
 
@@ -184,31 +198,153 @@ fn worker_task_inner<P>(worker_processor: P, is_global: bool) -> Builder<InnerSe
 /// Configuration options include:
 /// * Setting the [tag][set_tag].
 /// * Installing [globally][set_global].
+/// * Recording [panics][capture_panics] as events.
 /// * Configuring the [internal sender][map_sender] with fallbacks.
 /// * Configuring the [processor][map_receiver] in the worker task.
+/// * Rendering captured trees with a [`Printer`][also_print].
+/// * Attaching [metadata][with_metadata] to captured trees.
+/// * [Limiting][limit] how many captured trees are retained.
+/// * [Preallocating][with_capacity] space for captured trees.
 /// 
 /// To finish the `Runtime`, call the [`build`] method to compose the configured
-/// `ForestLayer` onto a [`Registry`]. Alternatively, the [`build_on`] method
-/// can be used construct arbitrary `Subscriber`s from the configured `ForestLayer`,
-/// which is used in the returned `Runtime`.
+/// `ForestLayer` onto a [`Registry`], or the [`build_filtered`] method to do so
+/// with a filter. Alternatively, the [`build_on`] method can be used construct
+/// arbitrary `Subscriber`s from the configured `ForestLayer`, which is used in
+/// the returned `Runtime`.
 /// 
 /// [builder]: https://rust-lang.github.io/api-guidelines/type-safety.html#builders-enable-construction-of-complex-values-c-builder
 /// [set_tag]: Builder::set_tag
 /// [set_global]: Builder::set_global
+/// [capture_panics]: Builder::capture_panics
 /// [map_sender]: Builder::map_sender
 /// [map_receiver]: Builder::map_receiver
+/// [also_print]: Builder::also_print
+/// [with_metadata]: Builder::with_metadata
+/// [limit]: Builder::limit
+/// [with_capacity]: Builder::with_capacity
 /// [`build`]: Builder::build
+/// [`build_filtered`]: Builder::build_filtered
 /// [`build_on`]: Builder::build_on
 pub struct Builder<Tx, Rx, T> {
     sender_processor: Tx,
     worker_processor: Rx,
-    receiver: UnboundedReceiver<Tree>,
+    receiver: LogReceiver,
     tag: T,
     is_global: bool,
+    capture_panics: bool,
 }
 
 /// A marker type indicating that trace data should be captured for later use.
-pub struct Capture(());
+/// 
+/// The `M` parameter is `()` by default, and becomes [`ForestMeta`] once
+/// [metadata][with_metadata] is attached to the capture.
+/// 
+/// [with_metadata]: Builder::with_metadata
+pub struct Capture<M = ()> {
+    printer: Option<Box<CapturePrinter>>,
+    meta: M,
+    limit: Option<usize>,
+    capacity: usize,
+}
+
+/// User-provided key-value metadata describing a captured forest, set by
+/// [`Builder::with_metadata`].
+/// 
+/// This is separate from the fields of events and spans, and is only intended
+/// for the consumer of the capture, for example to record which test or
+/// scenario produced the trees.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct ForestMeta {
+    entries: Vec<(String, String)>,
+}
+
+impl ForestMeta {
+    /// Returns the value of the first entry with the given key.
+    pub fn get(&self, key: &str) -> Option<&str> {
+        self.entries
+            .iter()
+            .find(|(k, _)| k == key)
+            .map(|(_, value)| value.as_str())
+    }
+
+    /// Returns an iterator over the entries, in the order they were added.
+    pub fn iter(&self) -> impl Iterator<Item = (&str, &str)> {
+        self.entries
+            .iter()
+            .map(|(key, value)| (key.as_str(), value.as_str()))
+    }
+
+    /// Returns the number of entries.
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    /// Returns `true` if there are no entries.
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+}
+
+/// The trees captured by a [`Runtime`] along with the [`ForestMeta`] attached
+/// with [`Builder::with_metadata`], and the number of trees dropped because of
+/// [`Builder::limit`].
+#[derive(Clone, Debug)]
+pub struct CaptureResult {
+    /// The captured trees.
+    pub trees: Forest,
+
+    /// The metadata attached to the capture.
+    pub meta: ForestMeta,
+
+    /// The number of root trees that were dropped to stay within the limit.
+    pub dropped: usize,
+}
+
+type CapturePrinter = dyn Fn(&Tree) + Send;
+
+/// The receiving half of the log channel, along with the trees kept by the
+/// sender instead when a capture has a [limit](Builder::limit).
+struct LogReceiver {
+    channel: UnboundedReceiver<Tree>,
+    retained: Arc<Mutex<Retained>>,
+}
+
+/// A ring buffer of the last root trees of a capture with a
+/// [limit](Builder::limit).
+///
+/// Trees are added by the sender as they're completed, so the limit holds no
+/// matter how rarely the future running in the capture is polled.
+#[derive(Default)]
+struct Retained {
+    limit: Option<usize>,
+    trees: VecDeque<Tree>,
+    dropped: usize,
+    printer: Option<Box<CapturePrinter>>,
+    closed: bool,
+}
+
+impl Retained {
+    /// Adds `tree` to the buffer, dropping the oldest tree once there are more
+    /// than `limit`.
+    #[allow(clippy::result_large_err)]
+    fn retain(&mut self, tree: Tree, limit: usize) -> processor::Result {
+        if self.closed {
+            let msg = "the capture has finished".into();
+            return Err(processor::error(tree, msg));
+        }
+
+        if let Some(printer) = &self.printer {
+            printer(&tree);
+        }
+
+        self.trees.push_back(tree);
+        if self.trees.len() > limit {
+            self.trees.pop_front();
+            self.dropped += 1;
+        }
+        Ok(())
+    }
+}
 
 /// A marker type indicating that trace data should be processed.
 pub struct WorkerTask<P>(P);
@@ -224,6 +360,10 @@ impl<P: Processor> Processor for InnerSender<P> {
     fn process(&self, tree: Tree) -> processor::Result {
         self.0.process(tree)
     }
+
+    fn process_rejected(&self, err: processor::Error) -> processor::Result {
+        self.0.process_rejected(err)
+    }
 }
 
 mod sealed {
@@ -282,10 +422,224 @@ where
             receiver: self.receiver,
             tag: self.tag,
             is_global: self.is_global,
+            capture_panics: self.capture_panics,
+        }
+    }
+}
+
+impl<Tx, M, T> Builder<Tx, Capture<M>, T> {
+    /// Render each captured log tree with a [`Printer`] in addition to returning it.
+    ///
+    /// This is useful for snapshot tests that inspect both the structure of the
+    /// trees and their formatted output, without running the workload twice.
+    /// Trees are rendered in the same order that they're returned.
+    ///
+    /// # Note
+    ///
+    /// This method is only available if called after [`capture`].
+    ///
+    /// # Panics
+    ///
+    /// Running the [`Runtime`] panics if the `Printer` fails to format or write
+    /// a tree.
+    ///
+    /// # Examples
+    ///
+    /// Rendering to an in-memory buffer:
+    /// ```
+    /// use tracing::info;
+    /// use tracing_forest::printer::MakeBuffer;
+    /// use tracing_forest::Printer;
+    ///
+    /// #[tokio::main]
+    /// async fn main() {
+    ///     let buffer = MakeBuffer::new();
+    ///
+    ///     let logs = tracing_forest::capture()
+    ///         .also_print(Printer::new().writer(buffer.clone()))
+    ///         .build()
+    ///         .on(async {
+    ///             info!("Hello, world!");
+    ///         })
+    ///         .await;
+    ///
+    ///     assert!(logs.len() == 1);
+    ///     assert!(buffer.contents().contains("Hello, world!"));
+    /// }
+    /// ```
+    pub fn also_print<F, W>(mut self, printer: Printer<F, W>) -> Self
+    where
+        F: 'static + Formatter + Send,
+        W: 'static + for<'a> MakeWriter<'a> + Send,
+    {
+        self.worker_processor.printer = Some(Box::new(move |tree| {
+            printer.write_tree(tree).expect(fail::PROCESSING_ERROR)
+        }));
+        self
+    }
+
+    /// Preallocate space for `capacity` captured root trees.
+    ///
+    /// This only avoids reallocating while trees are collected, which is
+    /// useful when capturing large forests repeatedly, and doesn't limit how
+    /// many trees can be captured.
+    ///
+    /// # Note
+    ///
+    /// This method is only available if called after [`capture`].
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use tracing::info;
+    ///
+    /// #[tokio::main]
+    /// async fn main() {
+    ///     let logs = tracing_forest::capture()
+    ///         .with_capacity(1024)
+    ///         .build()
+    ///         .on(async {
+    ///             for i in 0..1000 {
+    ///                 info!("event {}", i);
+    ///             }
+    ///         })
+    ///         .await;
+    ///
+    ///     assert!(logs.len() == 1000);
+    /// }
+    /// ```
+    pub fn with_capacity(mut self, capacity: usize) -> Self {
+        self.worker_processor.capacity = capacity;
+        self
+    }
+}
+
+impl<Tx, T> Builder<Tx, Capture, T> {
+    /// Attach a key-value pair of [`ForestMeta`] to the captured trees.
+    /// 
+    /// Once metadata is attached, running the [`Runtime`] returns a
+    /// [`CaptureResult`] containing both the trees and the metadata, instead
+    /// of just the trees. This can be called multiple times to attach more
+    /// entries.
+    /// 
+    /// # Note
+    /// 
+    /// This method is only available if called after [`capture`].
+    /// 
+    /// # Examples
+    /// 
+    /// ```
+    /// use tracing::info;
+    /// 
+    /// #[tokio::main]
+    /// async fn main() {
+    ///     let result = tracing_forest::capture()
+    ///         .with_metadata("test", "login_flow")
+    ///         .build()
+    ///         .on(async {
+    ///             info!("Hello, world!");
+    ///         })
+    ///         .await;
+    /// 
+    ///     assert!(result.trees.len() == 1);
+    ///     assert!(result.meta.get("test") == Some("login_flow"));
+    /// }
+    /// ```
+    pub fn with_metadata(
+        self,
+        key: impl Into<String>,
+        value: impl Into<String>,
+    ) -> Builder<Tx, Capture<ForestMeta>, T> {
+        Builder {
+            sender_processor: self.sender_processor,
+            worker_processor: Capture {
+                printer: self.worker_processor.printer,
+                meta: ForestMeta::default(),
+                limit: self.worker_processor.limit,
+                capacity: self.worker_processor.capacity,
+            },
+            receiver: self.receiver,
+            tag: self.tag,
+            is_global: self.is_global,
+            capture_panics: self.capture_panics,
+        }
+        .with_metadata(key, value)
+    }
+
+    /// Retain only the last `limit` root trees, so that capturing a
+    /// long-running workload can't grow without bound.
+    /// 
+    /// Trees are kept in a ring buffer as soon as they're completed, and the
+    /// oldest ones are dropped once there are more than `limit`, so memory stays
+    /// bounded even if the future is rarely polled. Running the [`Runtime`] then
+    /// returns a [`CaptureResult`] with the retained trees and the number of
+    /// trees that were dropped. If a [`Printer`] was set with
+    /// [`also_print`](Builder::also_print), it still renders every tree, as
+    /// soon as each one is completed.
+    /// 
+    /// # Note
+    /// 
+    /// This method is only available if called after [`capture`].
+    /// 
+    /// # Examples
+    /// 
+    /// ```
+    /// use tracing::info;
+    /// 
+    /// #[tokio::main]
+    /// async fn main() {
+    ///     let result = tracing_forest::capture()
+    ///         .limit(2)
+    ///         .build()
+    ///         .on(async {
+    ///             for i in 0..5 {
+    ///                 info!("event {}", i);
+    ///             }
+    ///         })
+    ///         .await;
+    /// 
+    ///     assert!(result.trees.len() == 2);
+    ///     assert!(result.dropped == 3);
+    /// }
+    /// ```
+    pub fn limit(self, limit: usize) -> Builder<Tx, Capture<ForestMeta>, T> {
+        Builder {
+            sender_processor: self.sender_processor,
+            worker_processor: Capture {
+                printer: self.worker_processor.printer,
+                meta: ForestMeta::default(),
+                limit: Some(limit),
+                capacity: self.worker_processor.capacity,
+            },
+            receiver: self.receiver,
+            tag: self.tag,
+            is_global: self.is_global,
+            capture_panics: self.capture_panics,
         }
     }
 }
 
+impl<Tx, T> Builder<Tx, Capture<ForestMeta>, T> {
+    /// Attach another key-value pair of [`ForestMeta`] to the captured trees.
+    /// 
+    /// Entries are kept in the order they were attached.
+    pub fn with_metadata(mut self, key: impl Into<String>, value: impl Into<String>) -> Self {
+        self.worker_processor
+            .meta
+            .entries
+            .push((key.into(), value.into()));
+        self
+    }
+
+    /// Retain only the last `limit` root trees.
+    /// 
+    /// See [`limit`](Builder::limit) for more details.
+    pub fn limit(mut self, limit: usize) -> Self {
+        self.worker_processor.limit = Some(limit);
+        self
+    }
+}
+
 impl<Tx, Rx, T> Builder<Tx, Rx, T>
 where
     Tx: Processor + sealed::Sealed,
@@ -360,7 +714,7 @@ where
             receiver: self.receiver,
             tag: self.tag,
             is_global: self.is_global,
-
+            capture_panics: self.capture_panics,
         }
     }
 
@@ -397,6 +751,7 @@ where
             receiver: self.receiver,
             tag,
             is_global: self.is_global,
+            capture_panics: self.capture_panics,
         }
     }
 
@@ -432,6 +787,46 @@ where
         self
     }
 
+    /// Set whether or not panics should be recorded as events.
+    /// 
+    /// When enabled, a panic hook is installed that records the panic message
+    /// and location as an `ERROR` event with the `panic` tag inside the span
+    /// that was current when the panic occurred. The previously installed hook
+    /// is still called afterwards, so the default panic output is unaffected.
+    /// 
+    /// # Note
+    /// 
+    /// Panic hooks are process-wide, so the hook stays installed for the rest
+    /// of the program once a `Runtime` with this option has been built.
+    /// 
+    /// # Examples
+    /// 
+    /// ```
+    /// use tracing_forest::util::*;
+    /// 
+    /// #[tokio::main]
+    /// async fn main() {
+    ///     let logs = tracing_forest::capture()
+    ///         .capture_panics(true)
+    ///         .build()
+    ///         .on(async {
+    ///             info_span!("risky").in_scope(|| {
+    ///                 let _ = std::panic::catch_unwind(|| panic!("oh no"));
+    ///             });
+    ///         })
+    ///         .await;
+    /// 
+    ///     let span = logs[0].span().unwrap();
+    ///     let event = span.nodes()[0].event().unwrap();
+    ///     assert!(event.tag().unwrap().to_string() == "panic");
+    ///     assert!(event.message() == Some("oh no"));
+    /// }
+    /// ```
+    pub fn capture_panics(mut self, capture_panics: bool) -> Self {
+        self.capture_panics = capture_panics;
+        self
+    }
+
     /// Finishes the `ForestLayer` by composing it into a [`Registry`], and
     /// returns it as a [`Runtime`].
     /// 
@@ -459,6 +854,38 @@ where
         self.build_on(|x| x)
     }
 
+    /// Finishes the `ForestLayer` by composing it into a [`Registry`] with a
+    /// per-layer [`Filter`], and returns it as a [`Runtime`].
+    ///
+    /// Spans and events disabled by the filter are never seen by the
+    /// `ForestLayer`, so they don't take part in tree construction at all.
+    ///
+    /// [`Filter`]: tracing_subscriber::layer::Filter
+    ///
+    /// # Examples
+    ///
+    /// Filtering with the `RUST_LOG` environment variable:
+    /// ```
+    /// use tracing_forest::util::*;
+    ///
+    /// #[tokio::main]
+    /// async fn main() {
+    ///     tracing_forest::worker_task()
+    ///         .build_filtered(EnvFilter::from_default_env())
+    ///         .on(async {
+    ///             // ...
+    ///         })
+    ///         .await;
+    /// }
+    /// ```
+    #[allow(clippy::type_complexity)]
+    pub fn build_filtered<F>(self, filter: F) -> Runtime<Layered<Filtered<ForestLayer<Tx, T>, F, Registry>, Registry>, Rx>
+    where
+        F: Filter<Registry> + 'static,
+    {
+        self.build_with(|layer| Registry::default().with(layer.with_filter(filter)))
+    }
+
     /// Finishes the `ForestLayer` by calling a function to build a `Subscriber`,
     /// and returns in as a [`Runtime`].
     /// 
@@ -542,6 +969,10 @@ where
         let layer = ForestLayer::new(self.sender_processor, self.tag);
         let subscriber = f(layer);
 
+        if self.capture_panics {
+            crate::layer::install_panic_hook();
+        }
+
         Runtime {
             subscriber,
             worker_processor: self.worker_processor,
@@ -557,7 +988,7 @@ where
 pub struct Runtime<S, P> {
     subscriber: S,
     worker_processor: P, // either `Process<_>` or `Capture`
-    receiver: UnboundedReceiver<Tree>,
+    receiver: LogReceiver,
     is_global: bool,
 }
 
@@ -570,7 +1001,7 @@ where
     pub async fn on(self, f: impl Future<Output = ()>) {
         let (shutdown_tx, mut shutdown_rx) = oneshot::channel();
         let processor = self.worker_processor.0;
-        let mut receiver = self.receiver;
+        let mut receiver = self.receiver.channel;
 
         let handle = tokio::spawn(async move {
             loop {
@@ -612,8 +1043,50 @@ where
     S: Subscriber + Send + Sync,
 {
     /// Execute a future in the context of the configured subscriber, and return
-    /// a `Vec<Tree>` of generated logs.
-    pub async fn on(self, f: impl Future<Output = ()>) -> Vec<Tree> {
+    /// a [`Forest`] of generated logs.
+    pub async fn on(self, f: impl Future<Output = ()>) -> Forest {
+        self.capture(f).await.0
+    }
+}
+
+impl<S> Runtime<S, Capture<ForestMeta>>
+where
+    S: Subscriber + Send + Sync,
+{
+    /// Execute a future in the context of the configured subscriber, and return
+    /// a [`CaptureResult`] of generated logs and the attached metadata.
+    pub async fn on(self, f: impl Future<Output = ()>) -> CaptureResult {
+        let (trees, dropped, meta) = self.capture(f).await;
+        CaptureResult { trees, meta, dropped }
+    }
+}
+
+impl<S, M> Runtime<S, Capture<M>>
+where
+    S: Subscriber + Send + Sync,
+{
+    async fn capture(self, f: impl Future<Output = ()>) -> (Forest, usize, M) {
+        let Capture {
+            printer,
+            meta,
+            limit,
+            capacity,
+        } = self.worker_processor;
+        let LogReceiver {
+            channel: mut receiver,
+            retained,
+        } = self.receiver;
+
+        // With a limit, trees never enter the channel, and are kept by the
+        // sender instead.
+        let mut printer = printer;
+        if limit.is_some() {
+            let mut retained = retained.lock().unwrap_or_else(PoisonError::into_inner);
+            retained.limit = limit;
+            retained.trees.reserve(capacity);
+            retained.printer = printer.take();
+        }
+
         {
             let _guard = if self.is_global {
                 tracing::subscriber::set_global_default(self.subscriber)
@@ -626,10 +1099,19 @@ where
             f.await;
         }
 
-        let mut receiver = self.receiver;
-
         receiver.close();
+        let mut logs = Vec::with_capacity(capacity);
+        while let Ok(tree) = receiver.try_recv() {
+            if let Some(printer) = &printer {
+                printer(&tree);
+            }
+            logs.push(tree);
+        }
+
+        let mut retained = retained.lock().unwrap_or_else(PoisonError::into_inner);
+        retained.closed = true;
+        logs.extend(retained.trees.drain(..));
 
-        iter::from_fn(|| receiver.try_recv().ok()).collect()
+        (Forest::from(logs), retained.dropped, meta)
     }
 }
@@ -73,12 +73,18 @@
 //! ```
 use crate::cfg_serde;
 use std::fmt;
+use std::hash::{Hash, Hasher};
+use tracing::span::Attributes;
 use tracing::{Event, Level};
 
 /// A basic `Copy` type containing information about where an event occurred.
 ///
+/// Tags compare equal when their prefix, suffix, and icon are all equal, and
+/// implement `Hash` accordingly, so they can be used as map keys to group
+/// events by tag.
+///
 /// See the [module-level documentation](mod@crate::tag) for more details.
-#[derive(Debug, Clone, Copy, Hash, PartialEq, Eq)]
+#[derive(Debug, Clone, Copy)]
 pub struct Tag {
     /// Optional prefix for the tag message
     prefix: Option<&'static str>,
@@ -91,6 +97,26 @@ pub struct Tag {
 
     /// An icon, typically emoji, that represents the tag.
     icon: char,
+
+    /// The level that the icon is the default for, if it was set with
+    /// [`Builder::level`] rather than [`Builder::icon`].
+    icon_level: Option<Level>,
+}
+
+impl PartialEq for Tag {
+    fn eq(&self, other: &Self) -> bool {
+        self.prefix == other.prefix && self.suffix == other.suffix && self.icon == other.icon
+    }
+}
+
+impl Eq for Tag {}
+
+impl Hash for Tag {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.prefix.hash(state);
+        self.suffix.hash(state);
+        self.icon.hash(state);
+    }
 }
 
 impl Tag {
@@ -129,6 +155,12 @@ impl Tag {
     pub const fn icon(&self) -> char {
         self.icon
     }
+
+    /// Returns the level whose default icon this tag uses, or `None` if the
+    /// icon was chosen explicitly.
+    pub(crate) const fn icon_level(&self) -> Option<Level> {
+        self.icon_level
+    }
 }
 
 /// Incrementally construct [`Tag`]s.
@@ -147,7 +179,7 @@ pub struct Suffix(&'static str);
 
 /// A type used by [`Builder`] to indicate that the icon has been set.
 #[derive(Copy, Clone, PartialEq, Eq)]
-pub struct Icon(char);
+pub struct Icon(char, Option<Level>);
 
 impl<S, I> Builder<S, I> {
     /// Set the prefix.
@@ -172,7 +204,17 @@ impl<S, I> Builder<S, I> {
         Builder {
             prefix: self.prefix,
             suffix: self.suffix,
-            icon: Icon(icon),
+            icon: Icon(icon, None),
+        }
+    }
+
+    /// Set the icon to the default for `level`, without changing the suffix.
+    #[cfg(feature = "serde")]
+    pub(crate) fn level_icon(self, level: Level) -> Builder<S, Icon> {
+        Builder {
+            prefix: self.prefix,
+            suffix: self.suffix,
+            icon: Icon(level_icon(level), Some(level)),
         }
     }
 
@@ -181,22 +223,32 @@ impl<S, I> Builder<S, I> {
     /// If the `Tag` won't have a prefix, then `Tag::from(level)` can be used as
     /// a shorter alternative.
     pub fn level(self, level: Level) -> Builder<Suffix, Icon> {
-        let (suffix, icon) = match level {
-            Level::TRACE => ("trace", '📍'),
-            Level::DEBUG => ("debug", '🐛'),
-            Level::INFO => ("info", 'ｉ'),
-            Level::WARN => ("warn", '🚧'),
-            Level::ERROR => ("error", '🚨'),
+        let suffix = match level {
+            Level::TRACE => "trace",
+            Level::DEBUG => "debug",
+            Level::INFO => "info",
+            Level::WARN => "warn",
+            Level::ERROR => "error",
         };
 
         Builder {
             prefix: self.prefix,
             suffix: Suffix(suffix),
-            icon: Icon(icon),
+            icon: Icon(level_icon(level), Some(level)),
         }
     }
 }
 
+fn level_icon(level: Level) -> char {
+    match level {
+        Level::TRACE => '📍',
+        Level::DEBUG => '🐛',
+        Level::INFO => 'ｉ',
+        Level::WARN => '🚧',
+        Level::ERROR => '🚨',
+    }
+}
+
 impl Builder<Suffix, Icon> {
     /// Complete the [`Tag`].
     ///
@@ -208,6 +260,7 @@ impl Builder<Suffix, Icon> {
             prefix: self.prefix,
             suffix: self.suffix.0,
             icon: self.icon.0,
+            icon_level: self.icon.1,
         }
     }
 }
@@ -248,6 +301,16 @@ cfg_serde! {
 pub trait TagParser: 'static {
     /// Parse a tag from a [`tracing::Event`]
     fn parse(&self, event: &Event) -> Option<Tag>;
+
+    /// Parse a tag from the [`Attributes`] of a new span.
+    ///
+    /// Spans aren't tagged by default. Span tags can be displayed on the
+    /// events within them with [`Pretty::inherit_span_tag`].
+    ///
+    /// [`Pretty::inherit_span_tag`]: crate::printer::Pretty::inherit_span_tag
+    fn parse_span(&self, _attrs: &Attributes) -> Option<Tag> {
+        None
+    }
 }
 
 /// A `TagParser` that always returns `None`.
@@ -0,0 +1,208 @@
+//! Utilities for testing code that uses `tracing-forest`.
+use crate::printer::Pretty;
+use crate::tree::Tree;
+use std::fmt::Write;
+
+/// The number of unchanged lines shown around each change.
+const CONTEXT: usize = 3;
+
+/// Returns a unified diff of two forests rendered with the [`Pretty`]
+/// formatter, or an empty string if they render the same.
+///
+/// Uuids, timestamps, and span durations are left out of the rendered lines,
+/// since they differ between runs, so this is suitable for comparing a
+/// captured forest against a golden file. Lines only in `expected` start with
+/// `-`, and lines only in `actual` start with `+`.
+///
+/// # Examples
+///
+/// ```
+/// # use tracing::{info, info_span};
+/// # #[tokio::main(flavor = "current_thread")]
+/// # async fn main() {
+/// let expected = tracing_forest::capture()
+///     .build()
+///     .on(async {
+///         info_span!("request").in_scope(|| info!("accepted"));
+///     })
+///     .await;
+///
+/// let actual = tracing_forest::capture()
+///     .build()
+///     .on(async {
+///         info_span!("request").in_scope(|| info!("rejected"));
+///     })
+///     .await;
+///
+/// print!("{}", tracing_forest::test::pretty_diff(&expected, &actual));
+/// # }
+/// ```
+/// Produces the output:
+/// ```diff
+/// --- expected
+/// +++ actual
+/// @@ -1,2 +1,2 @@
+///  INFO     request
+/// -INFO     ┕━ ｉ [info]: accepted
+/// +INFO     ┕━ ｉ [info]: rejected
+/// ```
+pub fn pretty_diff(expected: &[Tree], actual: &[Tree]) -> String {
+    let expected = render(expected);
+    let actual = render(actual);
+    let ops = diff(&expected, &actual);
+
+    if ops.iter().all(|op| matches!(op, Op::Equal(..))) {
+        return String::new();
+    }
+
+    let mut out = String::from("--- expected\n+++ actual\n");
+    for hunk in hunks(&ops) {
+        write_hunk(hunk, &expected, &actual, &mut out).expect("writing to a `String` can't fail");
+    }
+    out
+}
+
+/// Renders each tree without the parts that differ between runs.
+fn render(trees: &[Tree]) -> Vec<String> {
+    let pretty = Pretty::new().ansi(false).wrap(None);
+    #[cfg(feature = "chrono")]
+    let pretty = pretty.timestamp_format(crate::printer::TimestampFormat::None);
+
+    trees
+        .iter()
+        .flat_map(|tree| pretty.fmt_lines(tree))
+        .map(|line| {
+            // Uuids are always 36 characters, followed by a space.
+            #[cfg(feature = "uuid")]
+            let line = line.get(37..).unwrap_or_default().to_owned();
+
+            // Span lines end with their durations, like ` [ 1.2ms | 100.00% ]`.
+            match line.rfind(" [ ") {
+                Some(start) if line.ends_with(" ]") => line[..start].to_owned(),
+                _ => line,
+            }
+        })
+        .collect()
+}
+
+#[derive(Clone, Copy, Debug)]
+enum Op {
+    /// A line at these indices in `expected` and `actual`.
+    Equal(usize, usize),
+    /// A line at this index that's only in `expected`.
+    Delete(usize),
+    /// A line at this index that's only in `actual`.
+    Insert(usize),
+}
+
+/// Returns the edits from `expected` to `actual`, using the longest common
+/// subsequence of their lines.
+fn diff(expected: &[String], actual: &[String]) -> Vec<Op> {
+    let (n, m) = (expected.len(), actual.len());
+
+    // `lcs[i][j]` is the length of the longest common subsequence of
+    // `expected[i..]` and `actual[j..]`.
+    let mut lcs = vec![vec![0usize; m + 1]; n + 1];
+    for i in (0..n).rev() {
+        for j in (0..m).rev() {
+            lcs[i][j] = if expected[i] == actual[j] {
+                lcs[i + 1][j + 1] + 1
+            } else {
+                lcs[i + 1][j].max(lcs[i][j + 1])
+            };
+        }
+    }
+
+    let mut ops = Vec::with_capacity(n.max(m));
+    let (mut i, mut j) = (0, 0);
+    while i < n || j < m {
+        if i < n && j < m && expected[i] == actual[j] {
+            ops.push(Op::Equal(i, j));
+            i += 1;
+            j += 1;
+        } else if i < n && (j == m || lcs[i + 1][j] >= lcs[i][j + 1]) {
+            // Deletions come before insertions, as in other diff tools.
+            ops.push(Op::Delete(i));
+            i += 1;
+        } else {
+            ops.push(Op::Insert(j));
+            j += 1;
+        }
+    }
+    ops
+}
+
+/// Splits the edits into hunks of changes with up to [`CONTEXT`] unchanged
+/// lines around them, merging changes that are close together.
+fn hunks(ops: &[Op]) -> Vec<&[Op]> {
+    let changes: Vec<usize> = (0..ops.len())
+        .filter(|&i| !matches!(ops[i], Op::Equal(..)))
+        .collect();
+
+    let mut hunks = Vec::new();
+    let mut changes = changes.into_iter().peekable();
+    while let Some(first) = changes.next() {
+        let mut last = first;
+        while let Some(&next) = changes.peek() {
+            if next - last > 2 * CONTEXT {
+                break;
+            }
+            last = next;
+            changes.next();
+        }
+
+        let start = first.saturating_sub(CONTEXT);
+        let end = (last + CONTEXT + 1).min(ops.len());
+        hunks.push(&ops[start..end]);
+    }
+    hunks
+}
+
+fn write_hunk(
+    hunk: &[Op],
+    expected: &[String],
+    actual: &[String],
+    out: &mut String,
+) -> std::fmt::Result {
+    let (mut old_start, mut old_len, mut new_start, mut new_len) = (None, 0, None, 0);
+    for op in hunk {
+        match *op {
+            Op::Equal(i, j) => {
+                old_start.get_or_insert(i);
+                new_start.get_or_insert(j);
+                old_len += 1;
+                new_len += 1;
+            }
+            Op::Delete(i) => {
+                old_start.get_or_insert(i);
+                old_len += 1;
+            }
+            Op::Insert(j) => {
+                new_start.get_or_insert(j);
+                new_len += 1;
+            }
+        }
+    }
+
+    // Empty ranges are numbered by the line before them.
+    let range = |start: Option<usize>, len: usize| match start {
+        Some(start) => (start + 1, len),
+        None => (0, 0),
+    };
+    let (old_start, old_len) = range(old_start, old_len);
+    let (new_start, new_len) = range(new_start, new_len);
+    writeln!(
+        out,
+        "@@ -{},{} +{},{} @@",
+        old_start, old_len, new_start, new_len
+    )?;
+
+    for op in hunk {
+        match *op {
+            Op::Equal(i, _) => writeln!(out, " {}", expected[i])?,
+            Op::Delete(i) => writeln!(out, "-{}", expected[i])?,
+            Op::Insert(j) => writeln!(out, "+{}", actual[j])?,
+        }
+    }
+    Ok(())
+}
@@ -0,0 +1,483 @@
+use crate::tag::Tag;
+#[cfg(feature = "valuable")]
+use crate::tree::FieldValue;
+use crate::tree::{
+    Event, Field, FieldSet, Shared, Span, SpanActivity, SpanColor, SpanStatus, Tree, SCHEMA_VERSION,
+};
+#[cfg(feature = "chrono")]
+use chrono::{DateTime, Utc};
+#[cfg(feature = "valuable")]
+use serde::de::SeqAccess;
+use serde::de::{self, Deserialize, Deserializer, IgnoredAny, MapAccess, Visitor};
+use std::collections::BTreeSet;
+use std::fmt;
+use std::sync::Mutex;
+use std::time::Duration;
+use tracing::Level;
+#[cfg(feature = "uuid")]
+use uuid::Uuid;
+
+/// The most distinct strings that deserializing trees may leak over the
+/// lifetime of the process.
+const MAX_INTERNED: usize = 1 << 16;
+
+/// Returns a `'static` copy of `string`, leaking each distinct string once.
+///
+/// Span names, field keys, and tags are `&'static str`s since they normally
+/// come from `tracing` metadata, so deserialized ones have to live forever too.
+/// Once [`MAX_INTERNED`] strings have been leaked, new ones are rejected so
+/// that untrusted input can't grow memory without bound.
+fn intern<E: de::Error>(string: String) -> Result<&'static str, E> {
+    static INTERNED: Mutex<BTreeSet<&'static str>> = Mutex::new(BTreeSet::new());
+
+    let mut interned = INTERNED.lock().unwrap_or_else(|err| err.into_inner());
+    match interned.get(string.as_str()) {
+        Some(existing) => Ok(existing),
+        None if interned.len() >= MAX_INTERNED => Err(de::Error::custom(format_args!(
+            "more than {} distinct span names, field keys, and tags were deserialized",
+            MAX_INTERNED
+        ))),
+        None => {
+            let leaked: &'static str = Box::leak(string.into_boxed_str());
+            interned.insert(leaked);
+            Ok(leaked)
+        }
+    }
+}
+
+fn level<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Level, D::Error> {
+    let level = String::deserialize(deserializer)?;
+    level.parse().map_err(de::Error::custom)
+}
+
+#[cfg(feature = "chrono")]
+fn timestamp<'de, D: Deserializer<'de>>(deserializer: D) -> Result<DateTime<Utc>, D::Error> {
+    let timestamp = String::deserialize(deserializer)?;
+    DateTime::parse_from_rfc3339(&timestamp)
+        .map(|timestamp| timestamp.with_timezone(&Utc))
+        .map_err(de::Error::custom)
+}
+
+fn fields<'de, D: Deserializer<'de>>(deserializer: D) -> Result<FieldSet, D::Error> {
+    struct FieldsVisitor;
+
+    impl<'de> Visitor<'de> for FieldsVisitor {
+        type Value = FieldSet;
+
+        fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+            f.write_str("a map of field keys to string values")
+        }
+
+        #[cfg(not(feature = "valuable"))]
+        fn visit_map<A: MapAccess<'de>>(self, mut map: A) -> Result<FieldSet, A::Error> {
+            let mut fields = FieldSet::default();
+            while let Some((key, value)) = map.next_entry::<String, String>()? {
+                fields.push(Field::new(intern(key)?, value));
+            }
+            Ok(fields)
+        }
+
+        // Trees serialized before schema version 6 only have string values.
+        #[cfg(feature = "valuable")]
+        fn visit_map<A: MapAccess<'de>>(self, mut map: A) -> Result<FieldSet, A::Error> {
+            let mut fields = FieldSet::default();
+            while let Some((key, value)) = map.next_entry::<String, FieldValue>()? {
+                fields.push(match value {
+                    FieldValue::String(value) => Field::new(intern(key)?, value),
+                    value => Field::with_structured_value(intern(key)?, value),
+                });
+            }
+            Ok(fields)
+        }
+    }
+
+    deserializer.deserialize_map(FieldsVisitor)
+}
+
+#[cfg(feature = "valuable")]
+impl<'de> Deserialize<'de> for FieldValue {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        struct FieldValueVisitor;
+
+        impl<'de> Visitor<'de> for FieldValueVisitor {
+            type Value = FieldValue;
+
+            fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+                f.write_str("a field value")
+            }
+
+            fn visit_unit<E>(self) -> Result<FieldValue, E> {
+                Ok(FieldValue::Unit)
+            }
+
+            fn visit_none<E>(self) -> Result<FieldValue, E> {
+                Ok(FieldValue::Unit)
+            }
+
+            fn visit_some<D: Deserializer<'de>>(self, d: D) -> Result<FieldValue, D::Error> {
+                FieldValue::deserialize(d)
+            }
+
+            fn visit_bool<E>(self, value: bool) -> Result<FieldValue, E> {
+                Ok(FieldValue::Bool(value))
+            }
+
+            fn visit_i64<E>(self, value: i64) -> Result<FieldValue, E> {
+                Ok(FieldValue::I64(value))
+            }
+
+            fn visit_u64<E>(self, value: u64) -> Result<FieldValue, E> {
+                Ok(FieldValue::U64(value))
+            }
+
+            fn visit_i128<E>(self, value: i128) -> Result<FieldValue, E> {
+                Ok(FieldValue::I128(value))
+            }
+
+            fn visit_u128<E>(self, value: u128) -> Result<FieldValue, E> {
+                Ok(FieldValue::U128(value))
+            }
+
+            fn visit_f64<E>(self, value: f64) -> Result<FieldValue, E> {
+                Ok(FieldValue::F64(value))
+            }
+
+            fn visit_str<E>(self, value: &str) -> Result<FieldValue, E> {
+                Ok(FieldValue::String(value.to_owned()))
+            }
+
+            fn visit_string<E>(self, value: String) -> Result<FieldValue, E> {
+                Ok(FieldValue::String(value))
+            }
+
+            fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<FieldValue, A::Error> {
+                let mut values = Vec::new();
+                while let Some(value) = seq.next_element()? {
+                    values.push(value);
+                }
+                Ok(FieldValue::List(values))
+            }
+
+            fn visit_map<A: MapAccess<'de>>(self, mut map: A) -> Result<FieldValue, A::Error> {
+                let mut entries = Vec::new();
+                while let Some(entry) = map.next_entry()? {
+                    entries.push(entry);
+                }
+                Ok(FieldValue::Map(entries))
+            }
+        }
+
+        deserializer.deserialize_any(FieldValueVisitor)
+    }
+}
+
+/// Tags are serialized without their icon, so the icon is recovered from the
+/// suffix if it's a level name, or from the level of the node otherwise.
+fn tag<E: de::Error>(tag: String, level: Level) -> Result<Tag, E> {
+    let (prefix, suffix) = match tag.rsplit_once('.') {
+        Some((prefix, suffix)) => (Some(prefix), suffix),
+        None => (None, tag.as_str()),
+    };
+
+    let builder = Tag::builder()
+        .suffix(intern(suffix.to_owned())?)
+        .level_icon(suffix.parse().unwrap_or(level));
+
+    Ok(match prefix {
+        Some(prefix) => builder.prefix(intern(prefix.to_owned())?).build(),
+        None => builder.build(),
+    })
+}
+
+#[derive(serde::Deserialize)]
+struct SharedModel {
+    #[cfg(feature = "uuid")]
+    uuid: Uuid,
+
+    #[cfg(feature = "chrono")]
+    #[serde(deserialize_with = "timestamp")]
+    timestamp: DateTime<Utc>,
+
+    #[serde(deserialize_with = "level")]
+    level: Level,
+
+    // Trees serialized before schema version 5 don't have this.
+    #[serde(default)]
+    recorded_at_nanos: u64,
+}
+
+impl From<SharedModel> for Shared {
+    fn from(model: SharedModel) -> Self {
+        Shared {
+            #[cfg(feature = "uuid")]
+            uuid: model.uuid,
+            #[cfg(feature = "chrono")]
+            timestamp: model.timestamp,
+            level: model.level,
+            recorded_at: Duration::from_nanos(model.recorded_at_nanos),
+            #[cfg(feature = "tokio")]
+            task_id: None,
+        }
+    }
+}
+
+#[derive(serde::Deserialize)]
+struct EventModel {
+    #[serde(flatten)]
+    shared: SharedModel,
+
+    #[cfg(feature = "uuid")]
+    parent_id: Option<Uuid>,
+
+    message: Option<String>,
+
+    tag: Option<String>,
+
+    #[serde(deserialize_with = "fields")]
+    fields: FieldSet,
+
+    // Events serialized before schema version 3 are never hidden.
+    #[serde(default)]
+    hidden: bool,
+}
+
+#[derive(serde::Deserialize)]
+struct SpanModel {
+    #[serde(flatten)]
+    shared: SharedModel,
+
+    #[cfg(feature = "uuid")]
+    parent_id: Option<Uuid>,
+
+    // Spans serialized before schema version 4 get a new identifier.
+    #[serde(default)]
+    id: Option<u64>,
+
+    // Spans serialized before schema version 4 don't have these.
+    #[serde(default)]
+    follows_from: Vec<u64>,
+
+    name: String,
+
+    #[serde(default)]
+    display_name: Option<String>,
+
+    tag: Option<String>,
+
+    // Spans serialized before schema version 3 don't have fields.
+    #[serde(default, deserialize_with = "fields")]
+    fields: FieldSet,
+
+    status: SpanStatus,
+
+    #[serde(default)]
+    instrumented: bool,
+
+    #[serde(default)]
+    color: Option<String>,
+
+    partial: bool,
+
+    #[serde(default = "sampled")]
+    sampled: bool,
+
+    #[cfg(feature = "chrono")]
+    #[serde(deserialize_with = "timestamp")]
+    end_timestamp: DateTime<Utc>,
+
+    #[serde(flatten)]
+    durations: DurationsModel,
+
+    activity: SpanActivity,
+
+    #[serde(default)]
+    filtered_event_count: u64,
+
+    nodes: Vec<NodeModel>,
+}
+
+/// Trees serialized before schema version 4 were always sampled, since
+/// unsampled ones have no nodes worth keeping.
+fn sampled() -> bool {
+    true
+}
+
+/// Span durations in whichever [`DurationFormat`] they were serialized with.
+///
+/// [`DurationFormat`]: crate::tree::DurationFormat
+#[derive(serde::Deserialize)]
+struct DurationsModel {
+    nanos_total: Option<u64>,
+    nanos_nested: Option<u64>,
+    nanos_idle: Option<u64>,
+    millis_total: Option<f64>,
+    millis_nested: Option<f64>,
+    millis_idle: Option<f64>,
+    seconds_total: Option<f64>,
+    seconds_nested: Option<f64>,
+    seconds_idle: Option<f64>,
+}
+
+fn duration<E: de::Error>(
+    nanos: Option<u64>,
+    millis: Option<f64>,
+    seconds: Option<f64>,
+    nanos_key: &'static str,
+) -> Result<Duration, E> {
+    let seconds = match (nanos, millis, seconds) {
+        (Some(nanos), _, _) => return Ok(Duration::from_nanos(nanos)),
+        (None, Some(millis), _) => millis / 1000.0,
+        (None, None, Some(seconds)) => seconds,
+        (None, None, None) => return Err(E::missing_field(nanos_key)),
+    };
+
+    Duration::try_from_secs_f64(seconds).map_err(E::custom)
+}
+
+// Models only live until they're converted, so boxing spans isn't worth it.
+#[allow(clippy::large_enum_variant)]
+#[derive(serde::Deserialize)]
+enum NodeModel {
+    Event(EventModel),
+    Span(SpanModel),
+}
+
+impl EventModel {
+    fn into_event<E: de::Error>(self) -> Result<Event, E> {
+        let level = self.shared.level;
+
+        Ok(Event {
+            shared: self.shared.into(),
+            #[cfg(feature = "uuid")]
+            span_uuid: self.parent_id,
+            message: self.message,
+            tag: self.tag.map(|tag| self::tag(tag, level)).transpose()?,
+            fields: self.fields,
+            hidden: self.hidden,
+        })
+    }
+}
+
+impl SpanModel {
+    fn into_span<E: de::Error>(self) -> Result<Span, E> {
+        let level = self.shared.level;
+        let durations = self.durations;
+
+        let mut span = Span::new(self.shared.into(), intern(self.name)?);
+        #[cfg(feature = "uuid")]
+        {
+            span.parent_uuid = self.parent_id;
+        }
+        if let Some(id) = self.id {
+            span.id = id;
+        }
+        span.follows_from = self.follows_from;
+        span.display_name = self.display_name;
+        span.tag = self.tag.map(|tag| self::tag(tag, level)).transpose()?;
+        span.fields = self.fields;
+        span.status = self.status;
+        span.instrumented = self.instrumented;
+        span.color = self
+            .color
+            .map(|color| {
+                SpanColor::parse(&color)
+                    .ok_or_else(|| E::custom(format_args!("unknown span color `{}`", color)))
+            })
+            .transpose()?;
+        span.partial = self.partial;
+        span.sampled = self.sampled;
+        #[cfg(feature = "chrono")]
+        {
+            span.end_timestamp = self.end_timestamp;
+        }
+        span.total_duration = duration(
+            durations.nanos_total,
+            durations.millis_total,
+            durations.seconds_total,
+            "nanos_total",
+        )?;
+        span.inner_duration = duration(
+            durations.nanos_nested,
+            durations.millis_nested,
+            durations.seconds_nested,
+            "nanos_nested",
+        )?;
+        span.idle_duration = duration(
+            durations.nanos_idle,
+            durations.millis_idle,
+            durations.seconds_idle,
+            "nanos_idle",
+        )?;
+        span.activity = self.activity;
+        span.filtered_event_count = self.filtered_event_count;
+        span.nodes = self
+            .nodes
+            .into_iter()
+            .map(NodeModel::into_tree)
+            .collect::<Result<_, E>>()?;
+
+        Ok(span)
+    }
+}
+
+impl NodeModel {
+    fn into_tree<E: de::Error>(self) -> Result<Tree, E> {
+        match self {
+            NodeModel::Event(event) => event.into_event().map(Tree::Event),
+            NodeModel::Span(span) => span.into_span().map(Tree::Span),
+        }
+    }
+}
+
+/// The oldest [`SCHEMA_VERSION`] that can still be deserialized. Fields added
+/// since then are given defaults when they're missing.
+const OLDEST_SCHEMA_VERSION: u32 = 2;
+
+impl<'de> Deserialize<'de> for Tree {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        deserializer.deserialize_map(TreeVisitor)
+    }
+}
+
+struct TreeVisitor;
+
+impl<'de> Visitor<'de> for TreeVisitor {
+    type Value = Tree;
+
+    fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("a tree with a `schema_version`")
+    }
+
+    fn visit_map<A: MapAccess<'de>>(self, mut map: A) -> Result<Tree, A::Error> {
+        let mut version = None;
+        let mut node = None;
+
+        while let Some(key) = map.next_key::<String>()? {
+            match key.as_str() {
+                "schema_version" => {
+                    let found: u32 = map.next_value()?;
+                    if !(OLDEST_SCHEMA_VERSION..=SCHEMA_VERSION).contains(&found) {
+                        return Err(de::Error::custom(format_args!(
+                            "unsupported schema version {}, expected {} to {}",
+                            found, OLDEST_SCHEMA_VERSION, SCHEMA_VERSION
+                        )));
+                    }
+                    version = Some(found);
+                }
+                "Event" if node.is_none() => node = Some(NodeModel::Event(map.next_value()?)),
+                "Span" if node.is_none() => node = Some(NodeModel::Span(map.next_value()?)),
+                "Event" | "Span" => return Err(de::Error::custom("a tree has exactly one root")),
+                _ => {
+                    map.next_value::<IgnoredAny>()?;
+                }
+            }
+        }
+
+        if version.is_none() {
+            return Err(de::Error::missing_field("schema_version"));
+        }
+
+        node.ok_or_else(|| de::Error::custom("expected an `Event` or `Span` root"))?
+            .into_tree()
+    }
+}
@@ -3,16 +3,93 @@ pub(crate) type FieldSet = smallvec::SmallVec<[Field; 3]>;
 #[cfg(not(feature = "smallvec"))]
 pub(crate) type FieldSet = Vec<Field>;
 
+use std::fmt;
+use std::hash::{Hash, Hasher};
+use std::sync::OnceLock;
+
 /// A key-value pair recorded from trace data.
-#[derive(Clone, Debug, Hash, PartialEq, Eq)]
+#[derive(Clone, Debug)]
 pub struct Field {
     key: &'static str,
-    value: String,
+    value: Value,
+    #[cfg(feature = "valuable")]
+    structured: Option<FieldValue>,
+}
+
+/// A field value, which is either formatted already, or a primitive that is
+/// formatted the first time it's needed.
+#[derive(Clone, Debug)]
+enum Value {
+    Formatted(String),
+    Deferred(Primitive, OnceLock<String>),
+}
+
+/// A primitive field value recorded by [`ForestLayer::lazy_fields`].
+///
+/// [`ForestLayer::lazy_fields`]: crate::layer::ForestLayer::lazy_fields
+#[derive(Clone, Copy, Debug)]
+pub(crate) enum Primitive {
+    I64(i64),
+    U64(u64),
+    I128(i128),
+    U128(u128),
+    F64(f64),
+    Bool(bool),
+}
+
+impl fmt::Display for Primitive {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        // Formatted like the layer formats eagerly recorded values.
+        match self {
+            Primitive::I64(value) => fmt::Debug::fmt(value, f),
+            Primitive::U64(value) => fmt::Debug::fmt(value, f),
+            Primitive::I128(value) => fmt::Debug::fmt(value, f),
+            Primitive::U128(value) => fmt::Debug::fmt(value, f),
+            Primitive::F64(value) => fmt::Debug::fmt(value, f),
+            Primitive::Bool(value) => fmt::Debug::fmt(value, f),
+        }
+    }
 }
 
 impl Field {
-    pub(crate) fn new(key: &'static str, value: String) -> Self {
-        Field { key, value }
+    /// Returns a new `Field` with the given key and value.
+    pub fn new(key: &'static str, value: String) -> Self {
+        Field {
+            key,
+            value: Value::Formatted(value),
+            #[cfg(feature = "valuable")]
+            structured: None,
+        }
+    }
+
+    /// Returns a new `Field` whose value isn't formatted until it's read.
+    pub(crate) fn deferred(key: &'static str, value: Primitive) -> Self {
+        Field {
+            key,
+            value: Value::Deferred(value, OnceLock::new()),
+            #[cfg(feature = "valuable")]
+            structured: None,
+        }
+    }
+
+    /// Returns a new `Field` with the given key and structured value.
+    ///
+    /// The field's [`value`] is the JSON-like representation of `structured`,
+    /// and serializers write `structured` as nested values.
+    ///
+    /// [`value`]: Field::value
+    #[cfg(feature = "valuable")]
+    pub fn with_structured_value(key: &'static str, structured: FieldValue) -> Self {
+        Field::structured(key, structured.to_string(), structured)
+    }
+
+    #[cfg(feature = "valuable")]
+    pub(crate) fn structured(key: &'static str, value: String, structured: FieldValue) -> Self {
+        Field {
+            key,
+            value: Value::Formatted(value),
+            structured: Some(structured),
+        }
     }
 
     /// Returns the field's key.
@@ -21,7 +98,259 @@ impl Field {
     }
 
     /// Returns the field's value.
+    ///
+    /// For structured values, this is their `Debug` representation.
     pub fn value(&self) -> &str {
-        &self.value
+        match &self.value {
+            Value::Formatted(value) => value,
+            Value::Deferred(value, formatted) => formatted.get_or_init(|| value.to_string()),
+        }
+    }
+
+    /// Returns the field's nested representation if it was recorded as a
+    /// structured [`valuable::Value`].
+    ///
+    /// Recording structured values requires `tracing`'s unstable `valuable`
+    /// support, which is only available when building with
+    /// `RUSTFLAGS="--cfg tracing_unstable"`.
+    #[cfg(feature = "valuable")]
+    pub fn structured_value(&self) -> Option<&FieldValue> {
+        self.structured.as_ref()
+    }
+}
+
+// Fields compare by their formatted value, whether or not it was deferred.
+impl PartialEq for Field {
+    fn eq(&self, other: &Self) -> bool {
+        #[cfg(feature = "valuable")]
+        if self.structured != other.structured {
+            return false;
+        }
+        self.key == other.key && self.value() == other.value()
+    }
+}
+
+impl Eq for Field {}
+
+impl Hash for Field {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.key.hash(state);
+        self.value().hash(state);
+        #[cfg(feature = "valuable")]
+        self.structured.hash(state);
+    }
+}
+
+/// An owned, nested representation of a structured [`valuable::Value`].
+///
+/// Structs with named fields and maps become [`FieldValue::Map`] in their
+/// original order, while lists, tuples, and structs with unnamed fields become
+/// [`FieldValue::List`]. Enum values become a single-entry map from the variant
+/// name to its fields, or just the variant name if it has no fields.
+#[cfg(feature = "valuable")]
+#[derive(Clone, Debug)]
+pub enum FieldValue {
+    /// The unit value, `()`.
+    Unit,
+
+    /// A boolean.
+    Bool(bool),
+
+    /// A signed integer.
+    I64(i64),
+
+    /// An unsigned integer.
+    U64(u64),
+
+    /// A signed integer too large for an `i64`.
+    I128(i128),
+
+    /// An unsigned integer too large for a `u64`.
+    U128(u128),
+
+    /// A floating point number.
+    F64(f64),
+
+    /// A string, or a value that's most naturally displayed as one, such as a
+    /// `char`, a path, or an error.
+    String(String),
+
+    /// A sequence of values.
+    List(Vec<FieldValue>),
+
+    /// Key-value pairs, in their original order.
+    Map(Vec<(String, FieldValue)>),
+}
+
+#[cfg(feature = "valuable")]
+mod structured {
+    use super::FieldValue;
+    use std::fmt;
+    use std::hash::{Hash, Hasher};
+    use valuable::{NamedValues, Valuable, Value, Visit};
+
+    impl PartialEq for FieldValue {
+        fn eq(&self, other: &Self) -> bool {
+            use FieldValue::*;
+            match (self, other) {
+                (Unit, Unit) => true,
+                (Bool(a), Bool(b)) => a == b,
+                (I64(a), I64(b)) => a == b,
+                (U64(a), U64(b)) => a == b,
+                (I128(a), I128(b)) => a == b,
+                (U128(a), U128(b)) => a == b,
+                // Compare bits so that equality is reflexive, even for NaN.
+                (F64(a), F64(b)) => a.to_bits() == b.to_bits(),
+                (String(a), String(b)) => a == b,
+                (List(a), List(b)) => a == b,
+                (Map(a), Map(b)) => a == b,
+                _ => false,
+            }
+        }
+    }
+
+    impl Eq for FieldValue {}
+
+    impl Hash for FieldValue {
+        fn hash<H: Hasher>(&self, state: &mut H) {
+            std::mem::discriminant(self).hash(state);
+            match self {
+                FieldValue::Unit => {}
+                FieldValue::Bool(value) => value.hash(state),
+                FieldValue::I64(value) => value.hash(state),
+                FieldValue::U64(value) => value.hash(state),
+                FieldValue::I128(value) => value.hash(state),
+                FieldValue::U128(value) => value.hash(state),
+                FieldValue::F64(value) => value.to_bits().hash(state),
+                FieldValue::String(value) => value.hash(state),
+                FieldValue::List(values) => values.hash(state),
+                FieldValue::Map(entries) => entries.hash(state),
+            }
+        }
+    }
+
+    /// Values are displayed in a JSON-like notation, which is used as the
+    /// [`Field::value`](super::Field::value) of deserialized structured fields.
+    impl fmt::Display for FieldValue {
+        fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+            match self {
+                FieldValue::Unit => f.write_str("()"),
+                FieldValue::Bool(value) => value.fmt(f),
+                FieldValue::I64(value) => value.fmt(f),
+                FieldValue::U64(value) => value.fmt(f),
+                FieldValue::I128(value) => value.fmt(f),
+                FieldValue::U128(value) => value.fmt(f),
+                FieldValue::F64(value) => value.fmt(f),
+                FieldValue::String(value) => write!(f, "{:?}", value),
+                FieldValue::List(values) => {
+                    f.write_str("[")?;
+                    for (i, value) in values.iter().enumerate() {
+                        if i > 0 {
+                            f.write_str(", ")?;
+                        }
+                        value.fmt(f)?;
+                    }
+                    f.write_str("]")
+                }
+                FieldValue::Map(entries) => {
+                    f.write_str("{")?;
+                    for (i, (key, value)) in entries.iter().enumerate() {
+                        if i > 0 {
+                            f.write_str(", ")?;
+                        }
+                        write!(f, "{:?}: {}", key, value)?;
+                    }
+                    f.write_str("}")
+                }
+            }
+        }
+    }
+
+    impl From<Value<'_>> for FieldValue {
+        fn from(value: Value<'_>) -> Self {
+            match value {
+                Value::Unit => FieldValue::Unit,
+                Value::Bool(value) => FieldValue::Bool(value),
+                Value::Char(value) => FieldValue::String(value.to_string()),
+                Value::F32(value) => FieldValue::F64(value.into()),
+                Value::F64(value) => FieldValue::F64(value),
+                Value::I8(value) => FieldValue::I64(value.into()),
+                Value::I16(value) => FieldValue::I64(value.into()),
+                Value::I32(value) => FieldValue::I64(value.into()),
+                Value::I64(value) => FieldValue::I64(value),
+                Value::Isize(value) => FieldValue::I64(value as i64),
+                Value::I128(value) => FieldValue::I128(value),
+                Value::U8(value) => FieldValue::U64(value.into()),
+                Value::U16(value) => FieldValue::U64(value.into()),
+                Value::U32(value) => FieldValue::U64(value.into()),
+                Value::U64(value) => FieldValue::U64(value),
+                Value::Usize(value) => FieldValue::U64(value as u64),
+                Value::U128(value) => FieldValue::U128(value),
+                Value::String(value) => FieldValue::String(value.to_owned()),
+                Value::Path(value) => FieldValue::String(value.display().to_string()),
+                Value::Error(value) => FieldValue::String(value.to_string()),
+                Value::Listable(value) => Collect::visit(value).finish(),
+                Value::Mappable(value) => Collect::visit(value).finish(),
+                Value::Structable(value) => Collect::visit(value).finish(),
+                Value::Tuplable(value) => Collect::visit(value).finish(),
+                Value::Enumerable(value) => {
+                    let name = value.variant().name().to_owned();
+                    match Collect::visit(value).finish() {
+                        FieldValue::List(fields) if fields.is_empty() => FieldValue::String(name),
+                        fields => FieldValue::Map(vec![(name, fields)]),
+                    }
+                }
+                value => FieldValue::String(format!("{:?}", value)),
+            }
+        }
+    }
+
+    /// Collects the contents of a compound value.
+    #[derive(Default)]
+    struct Collect {
+        values: Vec<FieldValue>,
+        entries: Vec<(String, FieldValue)>,
+    }
+
+    impl Collect {
+        fn visit(value: &dyn Valuable) -> Self {
+            let mut collect = Collect::default();
+            value.visit(&mut collect);
+            collect
+        }
+
+        fn finish(self) -> FieldValue {
+            if self.entries.is_empty() {
+                FieldValue::List(self.values)
+            } else {
+                FieldValue::Map(self.entries)
+            }
+        }
+    }
+
+    impl Visit for Collect {
+        fn visit_value(&mut self, value: Value<'_>) {
+            self.values.push(value.into());
+        }
+
+        fn visit_named_fields(&mut self, named_values: &NamedValues<'_>) {
+            for (field, value) in named_values {
+                self.entries
+                    .push((field.name().to_owned(), (*value).into()));
+            }
+        }
+
+        fn visit_unnamed_fields(&mut self, values: &[Value<'_>]) {
+            self.values
+                .extend(values.iter().map(|value| FieldValue::from(*value)));
+        }
+
+        fn visit_entry(&mut self, key: Value<'_>, value: Value<'_>) {
+            let key = match key {
+                Value::String(key) => key.to_owned(),
+                key => format!("{:?}", key),
+            };
+            self.entries.push((key, value.into()));
+        }
     }
 }
@@ -0,0 +1,442 @@
+use crate::printer::{Formatter, Pretty};
+use crate::tree::{Event, Span, Tree};
+#[cfg(feature = "chrono")]
+use chrono::{DateTime, Utc};
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::collections::HashSet;
+use std::iter::FromIterator;
+use std::ops::{Deref, DerefMut};
+use std::time::Duration;
+use std::{slice, vec};
+use tracing::Level;
+#[cfg(feature = "uuid")]
+use uuid::Uuid;
+
+/// A collection of [`Tree`]s, as returned by [`capture`].
+///
+/// `Forest` derefs to `[Tree]`, so it can be indexed and iterated over like a
+/// `Vec<Tree>`, and can be converted to and from one. It also provides
+/// combinators for analyzing every tree at once. With the `serde` feature, it
+/// is serialized as a sequence of trees.
+///
+/// [`capture`]: crate::runtime::capture
+///
+/// # Examples
+///
+/// ```
+/// # use tracing::{info, info_span, warn};
+/// # #[tokio::main(flavor = "current_thread")]
+/// # async fn main() {
+/// let logs = tracing_forest::capture()
+///     .build()
+///     .on(async {
+///         info_span!("request").in_scope(|| {
+///             info!("received");
+///             warn!("slow");
+///         });
+///         info!("standalone");
+///     })
+///     .await;
+///
+/// assert!(logs.len() == 2);
+/// assert!(logs.events().count() == 3);
+/// assert!(logs.spans().count() == 1);
+/// # }
+/// ```
+#[derive(Clone, Debug, Default)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize), serde(transparent))]
+pub struct Forest(Vec<Tree>);
+
+impl Forest {
+    /// Returns a new, empty `Forest`.
+    pub const fn new() -> Self {
+        Forest(Vec::new())
+    }
+
+    /// Returns the trees of the forest.
+    pub fn into_inner(self) -> Vec<Tree> {
+        self.0
+    }
+
+    /// Returns an iterator over every node of every tree, in pre-order.
+    pub fn nodes(&self) -> impl Iterator<Item = &Tree> {
+        let mut stack: Vec<&Tree> = self.0.iter().rev().collect();
+
+        std::iter::from_fn(move || {
+            let node = stack.pop()?;
+            if let Tree::Span(span) = node {
+                stack.extend(span.nodes().iter().rev());
+            }
+            Some(node)
+        })
+    }
+
+    /// Returns an iterator over every event of every tree, in pre-order.
+    pub fn events(&self) -> impl Iterator<Item = &Event> {
+        self.nodes().filter_map(|node| node.event().ok())
+    }
+
+    /// Returns an iterator over every span of every tree, in pre-order.
+    pub fn spans(&self) -> impl Iterator<Item = &Span> {
+        self.nodes().filter_map(|node| node.span().ok())
+    }
+
+    /// Returns the first node in pre-order whose [`Uuid`] is `id`, or `None` if
+    /// no node has it.
+    ///
+    /// Since child spans and events share the `Uuid` of their parent unless
+    /// they're given their own, this returns the outermost node with the
+    /// `Uuid`. The returned subtree can be rendered with any formatter.
+    #[cfg(feature = "uuid")]
+    pub fn subtree_by_uuid(&self, id: Uuid) -> Option<&Tree> {
+        self.nodes().find(|node| match node {
+            Tree::Event(event) => event.uuid() == id,
+            Tree::Span(span) => span.uuid() == id,
+        })
+    }
+
+    /// Returns the total duration of the spans reachable through
+    /// [`follows_from`] links from each span, keyed by that span's [`id`].
+    ///
+    /// A span that follows from another is attributed to it, as are the spans
+    /// that follow from that span, and so on. Each reachable span's
+    /// [`total_duration`] is counted once per origin, even if it's reachable
+    /// through several links. Spans that nothing follows from are omitted.
+    ///
+    /// Spans are keyed by `id` rather than by `Uuid`, since child spans
+    /// inherit the `Uuid` of their parent and distinct origins would be merged.
+    /// Links are serialized, so this also works on deserialized forests, as
+    /// long as all of their trees were recorded by the same process.
+    ///
+    /// [`follows_from`]: Span::follows_from
+    /// [`id`]: Span::id
+    /// [`total_duration`]: Span::total_duration
+    pub fn attribute_follows_from(&self) -> HashMap<u64, Duration> {
+        let mut followers: HashMap<u64, Vec<&Span>> = HashMap::new();
+        for span in self.spans() {
+            for &origin in span.follows_from() {
+                followers.entry(origin).or_default().push(span);
+            }
+        }
+
+        let mut attributed = HashMap::new();
+        for origin in self.spans() {
+            if !followers.contains_key(&origin.id()) {
+                continue;
+            }
+
+            let mut visited = HashSet::new();
+            visited.insert(origin.id());
+            let mut stack = vec![origin.id()];
+            let mut total = Duration::ZERO;
+
+            while let Some(id) = stack.pop() {
+                for follower in followers.get(&id).into_iter().flatten() {
+                    if visited.insert(follower.id()) {
+                        total += follower.total_duration();
+                        stack.push(follower.id());
+                    }
+                }
+            }
+
+            attributed.insert(origin.id(), total);
+        }
+
+        attributed
+    }
+
+    /// Returns the trees of the forest grouped by the name of their root span,
+    /// as returned by [`Tree::root_name`].
+    ///
+    /// Trees that are events are grouped under `None`. Within each group,
+    /// trees are kept in the order they appear in the forest.
+    pub fn group_by_root_name(&self) -> HashMap<Option<&str>, Vec<&Tree>> {
+        let mut groups: HashMap<Option<&str>, Vec<&Tree>> = HashMap::new();
+        for tree in &self.0 {
+            groups.entry(tree.root_name()).or_default().push(tree);
+        }
+        groups
+    }
+
+    /// Returns the number of events per second across the forest.
+    ///
+    /// This is the total number of events divided by the time between the
+    /// earliest and latest timestamps of any node in the forest, including
+    /// when spans closed. A forest without events, or whose nodes all occurred
+    /// at the same instant, has a rate of `0.0`.
+    #[cfg(feature = "chrono")]
+    pub fn event_rate(&self) -> f64 {
+        let mut events = 0;
+        let mut window: Option<(DateTime<Utc>, DateTime<Utc>)> = None;
+        let mut widen = |timestamp: DateTime<Utc>| {
+            window = Some(match window {
+                Some((start, end)) => (start.min(timestamp), end.max(timestamp)),
+                None => (timestamp, timestamp),
+            });
+        };
+
+        for node in self.nodes() {
+            match node {
+                Tree::Event(event) => {
+                    events += 1;
+                    widen(event.timestamp());
+                }
+                Tree::Span(span) => {
+                    let (opened, closed) = span.interval();
+                    widen(opened);
+                    widen(closed);
+                }
+            }
+        }
+
+        let nanos = match window.and_then(|(start, end)| (end - start).num_nanoseconds()) {
+            Some(nanos) if nanos > 0 => nanos,
+            _ => return 0.0,
+        };
+
+        events as f64 / (nanos as f64 / 1e9)
+    }
+
+    /// Removes every event that is more verbose than `level`.
+    ///
+    /// Spans are kept regardless of their level, so that events at or above
+    /// `level` keep their context. Events removed from a span are added to
+    /// its [`filtered_count`]. Trees that are themselves events more verbose
+    /// than `level` are removed entirely.
+    ///
+    /// [`filtered_count`]: Span::filtered_count
+    pub fn filter_level(mut self, level: Level) -> Self {
+        fn prune(span: &mut Span, level: Level) {
+            let before = span.nodes.len();
+            span.nodes.retain_mut(|node| match node {
+                Tree::Event(event) => event.level() <= level,
+                Tree::Span(span) => {
+                    prune(span, level);
+                    true
+                }
+            });
+            span.filtered_event_count += (before - span.nodes.len()) as u64;
+        }
+
+        self.0.retain_mut(|tree| match tree {
+            Tree::Event(event) => event.level() <= level,
+            Tree::Span(span) => {
+                prune(span, level);
+                true
+            }
+        });
+
+        self
+    }
+
+    /// Returns a copy of the forest with only the nodes matching `pred` and
+    /// their ancestors.
+    ///
+    /// `pred` is called on every node, and spans are passed with all of their
+    /// children. A span is kept if it matches or if any of its descendants
+    /// match, but its unmatched children are removed either way. Trees
+    /// without any matching nodes are removed entirely. This is the general
+    /// form of [`filter_level`], which keeps every span regardless.
+    ///
+    /// [`filter_level`]: Forest::filter_level
+    pub fn filter(&self, pred: impl Fn(&Tree) -> bool) -> Forest {
+        fn prune(tree: &mut Tree, pred: &dyn Fn(&Tree) -> bool) -> bool {
+            let matched = pred(tree);
+            match tree {
+                Tree::Event(_) => matched,
+                Tree::Span(span) => {
+                    span.nodes.retain_mut(|node| prune(node, pred));
+                    matched || !span.nodes.is_empty()
+                }
+            }
+        }
+
+        let mut trees = self.0.clone();
+        trees.retain_mut(|tree| prune(tree, &pred));
+        Forest(trees)
+    }
+
+    /// Serializes the forest as a compact JSON array of trees.
+    ///
+    /// Use [`to_json_pretty`] for indented output. Either can be read back
+    /// with [`from_json`].
+    ///
+    /// [`to_json_pretty`]: Forest::to_json_pretty
+    /// [`from_json`]: Forest::from_json
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if a tree can't be serialized.
+    #[cfg(feature = "json")]
+    pub fn to_json(&self) -> serde_json::Result<String> {
+        serde_json::to_string(self)
+    }
+
+    /// Serializes the forest as an indented JSON array of trees.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if a tree can't be serialized.
+    #[cfg(feature = "json")]
+    pub fn to_json_pretty(&self) -> serde_json::Result<String> {
+        serde_json::to_string_pretty(self)
+    }
+
+    /// Deserializes a forest from a JSON array of trees, as written by
+    /// [`to_json`] or [`to_json_pretty`].
+    ///
+    /// [`to_json`]: Forest::to_json
+    /// [`to_json_pretty`]: Forest::to_json_pretty
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if `json` isn't a valid forest, including when a tree
+    /// was written with a newer [`SCHEMA_VERSION`], or when the process
+    /// has already leaked too many distinct span names, field keys, and tags
+    /// (see [`Tree`]).
+    ///
+    /// [`SCHEMA_VERSION`]: crate::tree::SCHEMA_VERSION
+    #[cfg(feature = "json")]
+    pub fn from_json(json: &str) -> serde_json::Result<Forest> {
+        serde_json::from_str(json)
+    }
+
+    /// Renders every tree with the [`Pretty`] formatter, with colors and
+    /// wrapping disabled.
+    ///
+    /// This is equivalent to concatenating [`Tree::to_pretty_lines`] for each
+    /// tree, with `ansi` set to `false`.
+    pub fn render(&self) -> String {
+        let pretty = Pretty::new().ansi(false).wrap(None);
+
+        self.0
+            .iter()
+            .map(|tree| pretty.fmt(tree).expect("writing to a `String` can't fail"))
+            .collect()
+    }
+}
+
+impl Deref for Forest {
+    type Target = [Tree];
+
+    fn deref(&self) -> &[Tree] {
+        &self.0
+    }
+}
+
+impl DerefMut for Forest {
+    fn deref_mut(&mut self) -> &mut [Tree] {
+        &mut self.0
+    }
+}
+
+impl From<Vec<Tree>> for Forest {
+    fn from(trees: Vec<Tree>) -> Self {
+        Forest(trees)
+    }
+}
+
+impl From<Forest> for Vec<Tree> {
+    fn from(forest: Forest) -> Self {
+        forest.0
+    }
+}
+
+impl FromIterator<Tree> for Forest {
+    fn from_iter<I: IntoIterator<Item = Tree>>(iter: I) -> Self {
+        Forest(iter.into_iter().collect())
+    }
+}
+
+impl Extend<Tree> for Forest {
+    fn extend<I: IntoIterator<Item = Tree>>(&mut self, iter: I) {
+        self.0.extend(iter)
+    }
+}
+
+impl IntoIterator for Forest {
+    type Item = Tree;
+    type IntoIter = vec::IntoIter<Tree>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.0.into_iter()
+    }
+}
+
+impl<'a> IntoIterator for &'a Forest {
+    type Item = &'a Tree;
+    type IntoIter = slice::Iter<'a, Tree>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.0.iter()
+    }
+}
+
+#[cfg(all(test, feature = "chrono"))]
+mod tests {
+    use super::*;
+    use crate::tree::{since_epoch, FieldSet, Shared};
+    use chrono::TimeZone;
+
+    fn event_at(millis: i64) -> Tree {
+        Tree::Event(Event {
+            shared: Shared {
+                #[cfg(feature = "uuid")]
+                uuid: uuid::Uuid::nil(),
+                timestamp: Utc.timestamp_millis_opt(millis).unwrap(),
+                level: Level::INFO,
+                recorded_at: since_epoch(),
+                #[cfg(feature = "tokio")]
+                task_id: None,
+            },
+            #[cfg(feature = "uuid")]
+            span_uuid: None,
+            message: None,
+            tag: None,
+            fields: FieldSet::default(),
+            hidden: false,
+        })
+    }
+
+    #[test]
+    fn test_event_rate() {
+        // 5 events over 2 seconds.
+        let forest: Forest = [0, 500, 1000, 1500, 2000]
+            .iter()
+            .map(|&millis| event_at(millis))
+            .collect();
+
+        assert!(forest.event_rate() == 2.5);
+    }
+
+    #[test]
+    fn test_event_rate_includes_span_close() {
+        let mut span = Span::new(
+            Shared {
+                #[cfg(feature = "uuid")]
+                uuid: uuid::Uuid::nil(),
+                timestamp: Utc.timestamp_millis_opt(0).unwrap(),
+                level: Level::INFO,
+                recorded_at: since_epoch(),
+                #[cfg(feature = "tokio")]
+                task_id: None,
+            },
+            "span",
+        );
+        span.end_timestamp = Utc.timestamp_millis_opt(4000).unwrap();
+        span.nodes.push(event_at(1000));
+        span.nodes.push(event_at(2000));
+
+        let forest = Forest::from(vec![Tree::Span(span)]);
+        assert!(forest.event_rate() == 0.5);
+    }
+
+    #[test]
+    fn test_event_rate_zero_window() {
+        assert!(Forest::new().event_rate() == 0.0);
+        assert!(Forest::from(vec![event_at(1000)]).event_rate() == 0.0);
+        assert!(Forest::from(vec![event_at(1000), event_at(1000)]).event_rate() == 0.0);
+    }
+}
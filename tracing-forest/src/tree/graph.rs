@@ -0,0 +1,96 @@
+use crate::tree::{Event, Span, Tree};
+use petgraph::graph::{DiGraph, NodeIndex};
+use std::collections::HashMap;
+
+/// A reference to a node of a [`Tree`], used as the node weight of the graph
+/// returned by [`Tree::to_digraph`].
+#[derive(Clone, Copy, Debug)]
+pub enum TreeNodeRef<'a> {
+    /// A reference to an [`Event`].
+    Event(&'a Event),
+
+    /// A reference to a [`Span`].
+    Span(&'a Span),
+}
+
+/// The kind of an edge in the graph returned by [`Tree::to_digraph`].
+#[derive(Clone, Copy, Debug, Hash, PartialEq, Eq)]
+pub enum EdgeKind {
+    /// An edge from a span to one of its children.
+    Child,
+
+    /// An edge from a span to another span that follows from it, as set by
+    /// [`tracing::Span::follows_from`].
+    FollowsFrom,
+}
+
+impl Tree {
+    /// Converts the tree into a [`petgraph`] directed graph.
+    ///
+    /// Every span and event in the tree becomes a node, with a
+    /// [`EdgeKind::Child`] edge from each span to each of its children, and a
+    /// [`EdgeKind::FollowsFrom`] edge from each span to the spans that follow
+    /// from it. `follows_from` links to spans outside of the tree are omitted.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use tracing::{info, info_span};
+    /// # #[tokio::main(flavor = "current_thread")]
+    /// # async fn main() -> Result<(), Box<dyn std::error::Error>> {
+    /// let logs = tracing_forest::capture()
+    ///     .build()
+    ///     .on(async {
+    ///         info_span!("root").in_scope(|| {
+    ///             info!("child");
+    ///         });
+    ///     })
+    ///     .await;
+    ///
+    /// let graph = logs[0].to_digraph();
+    ///
+    /// assert!(graph.node_count() == 2);
+    /// assert!(graph.edge_count() == 1);
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn to_digraph(&self) -> DiGraph<TreeNodeRef<'_>, EdgeKind> {
+        let mut graph = DiGraph::new();
+        let mut spans = HashMap::new();
+
+        add_node(self, &mut graph, &mut spans);
+
+        for index in graph.node_indices() {
+            if let TreeNodeRef::Span(span) = graph[index] {
+                for origin in span.follows_from() {
+                    if let Some(&origin) = spans.get(origin) {
+                        graph.add_edge(origin, index, EdgeKind::FollowsFrom);
+                    }
+                }
+            }
+        }
+
+        graph
+    }
+}
+
+fn add_node<'a>(
+    tree: &'a Tree,
+    graph: &mut DiGraph<TreeNodeRef<'a>, EdgeKind>,
+    spans: &mut HashMap<u64, NodeIndex>,
+) -> NodeIndex {
+    match tree {
+        Tree::Event(event) => graph.add_node(TreeNodeRef::Event(event)),
+        Tree::Span(span) => {
+            let index = graph.add_node(TreeNodeRef::Span(span));
+            spans.insert(span.id(), index);
+
+            for node in span.nodes() {
+                let child = add_node(node, graph, spans);
+                graph.add_edge(index, child, EdgeKind::Child);
+            }
+
+            index
+        }
+    }
+}
@@ -1,26 +1,44 @@
 //! The core tree structure of `tracing-forest`.
 //!
 //! This module provides methods used for log inspection when using [`capture`].
-//! It consists of three types: [`Tree`], [`Span`], and [`Event`].
+//! It consists of three types: [`Tree`], [`Span`], and [`Event`], along with
+//! [`Forest`] for collections of trees.
 //!
 //! [`capture`]: crate::runtime::capture
+use crate::printer::Pretty;
 use crate::tag::Tag;
 #[cfg(feature = "chrono")]
 use chrono::{DateTime, Utc};
+#[cfg(feature = "regex")]
+use regex::Regex;
 #[cfg(feature = "serde")]
-use serde::Serialize;
-use std::time::Duration;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::slice;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::OnceLock;
+use std::time::{Duration, Instant};
 use thiserror::Error;
 use tracing::Level;
 #[cfg(feature = "uuid")]
 use uuid::Uuid;
 
+#[cfg(feature = "serde")]
+mod de;
 mod field;
+mod forest;
+#[cfg(feature = "petgraph")]
+mod graph;
 #[cfg(feature = "serde")]
 mod ser;
 
 pub use field::Field;
-pub(crate) use field::FieldSet;
+#[cfg(feature = "valuable")]
+pub use field::FieldValue;
+pub(crate) use field::{FieldSet, Primitive};
+pub use forest::Forest;
+#[cfg(feature = "petgraph")]
+pub use graph::{EdgeKind, TreeNodeRef};
 
 /// A node in the log tree, consisting of either a [`Span`] or an [`Event`].
 ///
@@ -28,11 +46,26 @@ pub(crate) use field::FieldSet;
 /// the [`event`] and [`span`] methods provide a more ergonomic way to access the
 /// inner types in unit tests when combined with the [`capture`] function.
 ///
+/// Events emitted outside of any span aren't grouped under a synthetic span.
+/// Each one becomes a root-level `Tree::Event` of its own, and is processed
+/// as soon as it's emitted instead of waiting for a span to close.
+///
+/// When serialized, each root tree carries a [`SCHEMA_VERSION`] alongside the
+/// node. Trees written with an older version are migrated when they're
+/// deserialized, filling in data they don't have with defaults, and trees
+/// written with a newer version fail to deserialize.
+/// Since tag icons aren't serialized, deserialized tags use the icon of their
+/// level.
+///
+/// Span names, field keys, and tags are `&'static str`s, so deserializing a
+/// tree leaks each distinct one for the rest of the process. This is cheap for
+/// trees recorded by the same program, but deserializing trees from arbitrary
+/// sources fails once 65536 distinct strings have been leaked.
+///
 /// [`event`]: Tree::event
 /// [`span`]: Tree::span
 /// [`capture`]: crate::runtime::capture
 #[derive(Clone, Debug)]
-#[cfg_attr(feature = "serde", derive(Serialize))]
 pub enum Tree {
     /// An [`Event`] leaf node.
     Event(Event),
@@ -49,6 +82,11 @@ pub struct Event {
     #[cfg_attr(feature = "serde", serde(flatten))]
     pub(crate) shared: Shared,
 
+    /// The ID of the span the event occurred in.
+    #[cfg(feature = "uuid")]
+    #[cfg_attr(feature = "serde", serde(rename = "parent_id"))]
+    pub(crate) span_uuid: Option<Uuid>,
+
     /// The message associated with the event.
     pub(crate) message: Option<String>,
 
@@ -58,40 +96,262 @@ pub struct Event {
     /// Key-value data.
     #[cfg_attr(feature = "serde", serde(serialize_with = "ser::fields"))]
     pub(crate) fields: FieldSet,
+
+    /// Whether the event was marked with the `forest.hidden` field.
+    #[cfg_attr(feature = "serde", serde(skip_serializing_if = "std::ops::Not::not"))]
+    pub(crate) hidden: bool,
 }
 
 /// An internal node in the log tree carrying information about a Tracing span.
+///
+/// When serialized, durations are written as integer nanoseconds. See
+/// [`Tree::with_duration_format`] for other formats.
 #[derive(Clone, Debug)]
-#[cfg_attr(feature = "serde", derive(Serialize))]
 pub struct Span {
     /// Shared fields between events and spans.
-    #[cfg_attr(feature = "serde", serde(flatten))]
     pub(crate) shared: Shared,
 
+    /// The ID of the span's parent span.
+    #[cfg(feature = "uuid")]
+    pub(crate) parent_uuid: Option<Uuid>,
+
+    /// An identifier unique among all spans in the process.
+    pub(crate) id: u64,
+
+    /// The identifiers of spans that this span follows from.
+    pub(crate) follows_from: Vec<u64>,
+
     /// The name of the span.
     pub(crate) name: &'static str,
 
+    /// The name that the span is displayed with, if it was derived by
+    /// [`ForestLayer::span_name_fn`] and differs from `name`.
+    ///
+    /// [`ForestLayer::span_name_fn`]: crate::layer::ForestLayer::span_name_fn
+    pub(crate) display_name: Option<String>,
+
+    /// The tag that the span was collected with.
+    pub(crate) tag: Option<Tag>,
+
+    /// The fields that the span was created with or later recorded.
+    pub(crate) fields: FieldSet,
+
+    /// The status of the span, as set by the span's status field.
+    pub(crate) status: SpanStatus,
+
+    /// Whether the span was marked as created by `#[instrument]`.
+    pub(crate) instrumented: bool,
+
+    /// The color that the span is displayed with, as set by the span's
+    /// `forest.color` field.
+    pub(crate) color: Option<SpanColor>,
+
+    /// Whether the span is a snapshot of a span that's still open.
+    pub(crate) partial: bool,
+
+    /// Whether the tree containing the span was sampled.
+    pub(crate) sampled: bool,
+
+    /// When the span was closed.
+    #[cfg(feature = "chrono")]
+    pub(crate) end_timestamp: DateTime<Utc>,
+
     /// The total duration the span was open for.
-    #[cfg_attr(
-        feature = "serde",
-        serde(rename = "nanos_total", serialize_with = "ser::nanos")
-    )]
     pub(crate) total_duration: Duration,
 
     /// The total duration inner spans were open for.
-    #[cfg_attr(
-        feature = "serde",
-        serde(rename = "nanos_nested", serialize_with = "ser::nanos")
-    )]
     pub(crate) inner_duration: Duration,
 
+    /// The total duration between exiting the span and entering it again.
+    pub(crate) idle_duration: Duration,
+
+    /// Counts of the span's own activity.
+    pub(crate) activity: SpanActivity,
+
+    /// The number of direct child events that were dropped by a level filter.
+    pub(crate) filtered_event_count: u64,
+
     /// Events and spans collected while the span was open.
     pub(crate) nodes: Vec<Tree>,
 }
 
+/// A 64-bit FNV-1a hasher, used by [`Tree::structural_hash`] because its
+/// output doesn't change between Rust versions like `DefaultHasher`'s can.
+struct Fnv(u64);
+
+impl Fnv {
+    const OFFSET_BASIS: u64 = 0xcbf2_9ce4_8422_2325;
+    const PRIME: u64 = 0x0000_0100_0000_01b3;
+
+    fn new() -> Self {
+        Fnv(Fnv::OFFSET_BASIS)
+    }
+
+    fn write(&mut self, bytes: &[u8]) {
+        for &byte in bytes {
+            self.0 ^= u64::from(byte);
+            self.0 = self.0.wrapping_mul(Fnv::PRIME);
+        }
+    }
+
+    fn write_u8(&mut self, n: u8) {
+        self.write(&[n]);
+    }
+
+    fn write_u64(&mut self, n: u64) {
+        self.write(&n.to_le_bytes());
+    }
+
+    /// Writes a length-prefixed string, so that adjacent strings can't run
+    /// into each other.
+    fn write_str(&mut self, s: &str) {
+        self.write_u64(s.len() as u64);
+        self.write(s.as_bytes());
+    }
+}
+
+/// Counts of the direct activity of a [`Span`], returned by [`Span::activity`].
+#[derive(Clone, Copy, Debug, Default, Hash, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct SpanActivity {
+    /// The number of times the span was entered.
+    pub enters: u64,
+
+    /// The number of times the span was exited.
+    pub exits: u64,
+
+    /// The number of events recorded directly within the span.
+    pub events: u64,
+}
+
+/// The status of a [`Span`], modeled after OpenTelemetry's span status codes.
+///
+/// The status is set by recording a value of `"ok"` or `"error"` to the span's
+/// status field, which is `otel.status_code` by default. Any other value leaves
+/// the status unchanged.
+///
+/// See [`ForestLayer::status_field`] for details.
+///
+/// [`ForestLayer::status_field`]: crate::ForestLayer::status_field
+#[derive(Clone, Copy, Debug, Default, Hash, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "lowercase"))]
+pub enum SpanStatus {
+    /// No status was recorded.
+    #[default]
+    Unset,
+
+    /// The operation completed successfully.
+    Ok,
+
+    /// The operation contains an error.
+    Error,
+}
+
+impl SpanStatus {
+    pub(crate) fn parse(value: &str) -> Option<Self> {
+        if value.eq_ignore_ascii_case("unset") {
+            Some(SpanStatus::Unset)
+        } else if value.eq_ignore_ascii_case("ok") {
+            Some(SpanStatus::Ok)
+        } else if value.eq_ignore_ascii_case("error") {
+            Some(SpanStatus::Error)
+        } else {
+            None
+        }
+    }
+}
+
+/// A color that a [`Span`] is displayed with, set by recording a color name to
+/// the span's `forest.color` field.
+///
+/// Names are matched case-insensitively, and `purple` is accepted as an alias
+/// for `magenta`. Unrecognized names are ignored, in which case the span is
+/// displayed with the default colors.
+///
+/// # Examples
+///
+/// ```
+/// # use tracing::info_span;
+/// info_span!("request", forest.color = "magenta");
+/// ```
+#[derive(Clone, Copy, Debug, Hash, PartialEq, Eq)]
+pub enum SpanColor {
+    /// Black.
+    Black,
+    /// Red.
+    Red,
+    /// Green.
+    Green,
+    /// Yellow.
+    Yellow,
+    /// Blue.
+    Blue,
+    /// Magenta, also known as purple.
+    Magenta,
+    /// Cyan.
+    Cyan,
+    /// White.
+    White,
+}
+
+impl SpanColor {
+    pub(crate) fn parse(value: &str) -> Option<Self> {
+        const NAMES: [(&str, SpanColor); 9] = [
+            ("black", SpanColor::Black),
+            ("red", SpanColor::Red),
+            ("green", SpanColor::Green),
+            ("yellow", SpanColor::Yellow),
+            ("blue", SpanColor::Blue),
+            ("magenta", SpanColor::Magenta),
+            ("purple", SpanColor::Magenta),
+            ("cyan", SpanColor::Cyan),
+            ("white", SpanColor::White),
+        ];
+
+        NAMES
+            .iter()
+            .find(|(name, _)| value.eq_ignore_ascii_case(name))
+            .map(|&(_, color)| color)
+    }
+
+    #[cfg(feature = "serde")]
+    pub(crate) fn name(self) -> &'static str {
+        match self {
+            SpanColor::Black => "black",
+            SpanColor::Red => "red",
+            SpanColor::Green => "green",
+            SpanColor::Yellow => "yellow",
+            SpanColor::Blue => "blue",
+            SpanColor::Magenta => "magenta",
+            SpanColor::Cyan => "cyan",
+            SpanColor::White => "white",
+        }
+    }
+}
+
+/// The data shared by events and spans: their level, and their [`Uuid`] and
+/// timestamp with the `uuid` and `chrono` features.
+///
+/// This is only needed to build trees from sources other than `tracing`, such
+/// as when importing external logs, with [`Event::new`] and [`Span::new`].
+///
+/// # Examples
+///
+/// ```
+/// use tracing::Level;
+/// use tracing_forest::tree::{Event, Field, Shared, Tree};
+///
+/// let mut event = Event::new(Shared::new(Level::WARN));
+/// event.set_message(Some("imported".to_string()));
+/// event.push_field(Field::new("source", "syslog".to_string()));
+///
+/// let lines = Tree::Event(event).to_pretty_lines(false);
+/// assert!(lines[0].ends_with("WARN     🚧 [warn]: imported | source: syslog"));
+/// ```
 #[derive(Clone, Debug)]
 #[cfg_attr(feature = "serde", derive(Serialize))]
-pub(crate) struct Shared {
+pub struct Shared {
     /// The ID of the event or span.
     #[cfg(feature = "uuid")]
     pub(crate) uuid: Uuid,
@@ -104,6 +364,100 @@ pub(crate) struct Shared {
     /// The level the event or span occurred at.
     #[cfg_attr(feature = "serde", serde(serialize_with = "ser::level"))]
     pub(crate) level: Level,
+
+    /// When the event or span was recorded, relative to the first time
+    /// anything was recorded in the process.
+    #[cfg_attr(
+        feature = "serde",
+        serde(rename = "recorded_at_nanos", serialize_with = "ser::nanos")
+    )]
+    pub(crate) recorded_at: Duration,
+
+    /// The Tokio task that the event occurred in or that the span was opened
+    /// in.
+    #[cfg(feature = "tokio")]
+    #[cfg_attr(feature = "serde", serde(skip))]
+    pub(crate) task_id: Option<tokio::task::Id>,
+}
+
+/// Returns the time elapsed since the epoch that [`Shared::recorded_at`] is
+/// relative to, which is the first time this is called.
+pub(crate) fn since_epoch() -> Duration {
+    static EPOCH: OnceLock<Instant> = OnceLock::new();
+    EPOCH.get_or_init(Instant::now).elapsed()
+}
+
+/// The version of the serialized representation of a [`Tree`].
+///
+/// This is written as the `schema_version` key of every serialized root tree,
+/// and is bumped whenever the serialized representation changes so that
+/// readers can detect and migrate old formats. Deserialization accepts every
+/// version since 2.
+#[cfg(feature = "serde")]
+pub const SCHEMA_VERSION: u32 = 6;
+
+/// The unit that span durations are serialized in, set by
+/// [`Tree::with_duration_format`].
+#[cfg(feature = "serde")]
+#[derive(Clone, Copy, Debug, Default, Hash, PartialEq, Eq)]
+pub enum DurationFormat {
+    /// Durations are integer nanoseconds, with keys like `nanos_total`.
+    ///
+    /// This is the default.
+    #[default]
+    Nanos,
+
+    /// Durations are fractional milliseconds, with keys like `millis_total`.
+    Millis,
+
+    /// Durations are fractional seconds, with keys like `seconds_total`.
+    Seconds,
+}
+
+/// A [`Tree`] that serializes span durations in a chosen [`DurationFormat`],
+/// returned by [`Tree::with_duration_format`].
+#[cfg(feature = "serde")]
+#[derive(Clone, Copy, Debug)]
+pub struct WithDurationFormat<'a> {
+    pub(crate) tree: &'a Tree,
+    pub(crate) format: DurationFormat,
+}
+
+/// A row in the flattened representation of a [`Tree`], returned by
+/// [`Tree::to_rows`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct TreeRow<'a> {
+    /// The number of spans enclosing the node, where the root has a depth of 0.
+    pub depth: usize,
+
+    /// Whether the node is a span or an event.
+    pub kind: TreeKind,
+
+    /// The name of a span, or the message of an event.
+    ///
+    /// This is empty for events without a message.
+    pub name_or_message: &'a str,
+
+    /// The level of the node.
+    pub level: Level,
+
+    /// The total duration of a span, or `None` for events.
+    pub duration: Option<Duration>,
+
+    /// The fields of an event, formatted as `key: value` pairs joined by `, `.
+    ///
+    /// This is empty for spans.
+    pub fields: String,
+}
+
+/// The kind of node that a [`TreeRow`] represents.
+#[derive(Clone, Copy, Debug, Hash, PartialEq, Eq)]
+pub enum TreeKind {
+    /// The row represents an [`Event`].
+    Event,
+
+    /// The row represents a [`Span`].
+    Span,
 }
 
 /// Error returned by [`Tree::event`][event].
@@ -121,6 +475,13 @@ pub struct ExpectedEventError(());
 pub struct ExpectedSpanError(());
 
 impl Tree {
+    /// Returns `true` if the tree is a [hidden] event.
+    ///
+    /// [hidden]: Event::is_hidden
+    pub(crate) fn is_hidden(&self) -> bool {
+        matches!(self, Tree::Event(event) if event.hidden)
+    }
+
     /// Returns a reference to the inner [`Event`] if the tree is an event.
     ///
     /// # Errors
@@ -132,11 +493,11 @@ impl Tree {
     /// Inspecting a `Tree` returned from [`capture`]:
     /// ```
     /// use tracing::{info, info_span};
-    /// use tracing_forest::tree::{Tree, Event};
+    /// use tracing_forest::tree::{Event, Forest, Tree};
     ///
     /// #[tokio::main]
     /// async fn main() -> Result<(), Box<dyn std::error::Error>> {
-    ///     let logs: Vec<Tree> = tracing_forest::capture()
+    ///     let logs: Forest = tracing_forest::capture()
     ///         .build()
     ///         .on(async {
     ///             info!("some information");
@@ -171,11 +532,11 @@ impl Tree {
     /// Inspecting a `Tree` returned from [`capture`]:
     /// ```
     /// use tracing::{info, info_span};
-    /// use tracing_forest::tree::{Tree, Span};
+    /// use tracing_forest::tree::{Forest, Span, Tree};
     ///
     /// #[tokio::main]
     /// async fn main() -> Result<(), Box<dyn std::error::Error>> {
-    ///     let logs: Vec<Tree> = tracing_forest::capture()
+    ///     let logs: Forest = tracing_forest::capture()
     ///         .build()
     ///         .on(async {
     ///             info_span!("my_span").in_scope(|| {
@@ -199,15 +560,483 @@ impl Tree {
             Tree::Span(span) => Ok(span),
         }
     }
+
+    /// Returns `true` if the tree is an [`Event`].
+    pub fn is_event(&self) -> bool {
+        matches!(self, Tree::Event(_))
+    }
+
+    /// Returns `true` if the tree is a [`Span`].
+    pub fn is_span(&self) -> bool {
+        matches!(self, Tree::Span(_))
+    }
+
+    /// Returns the name of the span at the root of the tree, or `None` if the
+    /// tree is an event.
+    pub fn root_name(&self) -> Option<&str> {
+        match self {
+            Tree::Event(_) => None,
+            Tree::Span(span) => Some(span.name()),
+        }
+    }
+
+    /// Renders the tree with the [`Pretty`] formatter, returning each line
+    /// without its trailing newline.
+    ///
+    /// Lines are colored if `ansi` is `true`, which requires the `ansi`
+    /// feature. Wrapping is disabled. For anything else, use
+    /// [`Pretty::fmt_lines`] with a configured formatter instead.
+    pub fn to_pretty_lines(&self, ansi: bool) -> Vec<String> {
+        Pretty::new().ansi(ansi).wrap(None).fmt_lines(self)
+    }
+
+    /// Flattens the tree into rows for tabular display.
+    ///
+    /// Rows are in pre-order, so indenting each row by its [`depth`] reconstructs
+    /// the tree.
+    ///
+    /// [`depth`]: TreeRow::depth
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use tracing::{info, info_span};
+    /// # #[tokio::main(flavor = "current_thread")]
+    /// # async fn main() {
+    /// let logs = tracing_forest::capture()
+    ///     .build()
+    ///     .on(async {
+    ///         info_span!("my_span").in_scope(|| {
+    ///             info!(answer = 42, "inside the span");
+    ///         });
+    ///     })
+    ///     .await;
+    ///
+    /// for row in logs[0].to_rows() {
+    ///     println!("{:indent$}{} {}", "", row.name_or_message, row.fields, indent = 2 * row.depth);
+    /// }
+    /// # }
+    /// ```
+    pub fn to_rows(&self) -> Vec<TreeRow<'_>> {
+        fn push_rows<'a>(tree: &'a Tree, depth: usize, rows: &mut Vec<TreeRow<'a>>) {
+            match tree {
+                Tree::Event(event) => {
+                    let fields = event
+                        .fields()
+                        .iter()
+                        .map(|field| format!("{}: {}", field.key(), field.value()))
+                        .collect::<Vec<_>>()
+                        .join(", ");
+
+                    rows.push(TreeRow {
+                        depth,
+                        kind: TreeKind::Event,
+                        name_or_message: event.message().unwrap_or(""),
+                        level: event.level(),
+                        duration: None,
+                        fields,
+                    });
+                }
+                Tree::Span(span) => {
+                    rows.push(TreeRow {
+                        depth,
+                        kind: TreeKind::Span,
+                        name_or_message: span.name(),
+                        level: span.level(),
+                        duration: Some(span.total_duration()),
+                        fields: String::new(),
+                    });
+
+                    for node in span.nodes() {
+                        push_rows(node, depth + 1, rows);
+                    }
+                }
+            }
+        }
+
+        let mut rows = Vec::new();
+        push_rows(self, 0, &mut rows);
+        rows
+    }
+
+    /// Returns the number of events in the tree that match `pred`, including
+    /// the tree itself if it's an event.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use tracing::{error, info, info_span, Level};
+    /// # #[tokio::main(flavor = "current_thread")]
+    /// # async fn main() {
+    /// let logs = tracing_forest::capture()
+    ///     .build()
+    ///     .on(async {
+    ///         info_span!("request").in_scope(|| {
+    ///             info!("started");
+    ///             error!("failed");
+    ///         });
+    ///     })
+    ///     .await;
+    ///
+    /// assert!(logs[0].count_events(|event| event.level() == Level::ERROR) == 1);
+    /// # }
+    /// ```
+    pub fn count_events(&self, pred: impl Fn(&Event) -> bool) -> usize {
+        fn count(tree: &Tree, pred: &dyn Fn(&Event) -> bool) -> usize {
+            match tree {
+                Tree::Event(event) => pred(event) as usize,
+                Tree::Span(span) => span.nodes.iter().map(|node| count(node, pred)).sum(),
+            }
+        }
+
+        count(self, &pred)
+    }
+
+    /// Returns a copy of the tree where adjacent sibling spans with the same
+    /// name are merged into one, which summarizes loops that open a span on
+    /// each iteration.
+    ///
+    /// A merged span keeps the identity and fields of the first span, and its
+    /// durations and [`activity`] are the sums of the merged spans. Its
+    /// children are the children of each merged span in order, which are
+    /// merged the same way. Its status is the most severe of the merged
+    /// spans, and it ends when the last span ended. Only adjacent spans are
+    /// merged, so siblings with the same name separated by another node stay
+    /// separate, and the order of the remaining nodes is preserved.
+    ///
+    /// [`activity`]: Span::activity
+    pub fn coalesce_sibling_spans(&self) -> Tree {
+        fn coalesce(span: &mut Span) {
+            let mut nodes: Vec<Tree> = Vec::with_capacity(span.nodes.len());
+            for node in std::mem::take(&mut span.nodes) {
+                match (nodes.last_mut(), node) {
+                    (Some(Tree::Span(last)), Tree::Span(mut next)) if last.name == next.name => {
+                        last.merge(&mut next);
+                    }
+                    (_, node) => nodes.push(node),
+                }
+            }
+
+            for node in &mut nodes {
+                if let Tree::Span(span) = node {
+                    coalesce(span);
+                }
+            }
+            span.nodes = nodes;
+        }
+
+        let mut tree = self.clone();
+        if let Tree::Span(span) = &mut tree {
+            coalesce(span);
+        }
+        tree
+    }
+
+    /// Returns a fingerprint of the tree's structure, for deduplicating trees
+    /// that took the same code path.
+    ///
+    /// The hash covers the names and levels of spans, and the messages, levels,
+    /// and field keys of events, recursively and in order. Uuids, timestamps,
+    /// durations, and field values are ignored. The hash is computed with
+    /// 64-bit FNV-1a, so it's stable across processes, platforms, and builds.
+    pub fn structural_hash(&self) -> u64 {
+        fn write(tree: &Tree, hasher: &mut Fnv) {
+            match tree {
+                Tree::Event(event) => {
+                    hasher.write_u8(b'E');
+                    hasher.write_str(event.level().as_str());
+                    match event.message() {
+                        Some(message) => {
+                            hasher.write_u8(1);
+                            hasher.write_str(message);
+                        }
+                        None => hasher.write_u8(0),
+                    }
+                    hasher.write_u64(event.fields().len() as u64);
+                    for field in event.fields() {
+                        hasher.write_str(field.key());
+                    }
+                }
+                Tree::Span(span) => {
+                    hasher.write_u8(b'S');
+                    hasher.write_str(span.level().as_str());
+                    hasher.write_str(span.name());
+                    hasher.write_u64(span.nodes().len() as u64);
+                    for node in span.nodes() {
+                        write(node, hasher);
+                    }
+                }
+            }
+        }
+
+        let mut hasher = Fnv::new();
+        write(self, &mut hasher);
+        hasher.0
+    }
+
+    /// Returns the names of the spans from the root of the tree down to the
+    /// first node in pre-order that matches `pred`, or `None` if no node does.
+    ///
+    /// If the matching node is a span, its own name is last in the path. If
+    /// the tree itself is an event that matches, the path is empty.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use tracing::{info, info_span};
+    /// # #[tokio::main(flavor = "current_thread")]
+    /// # async fn main() {
+    /// let logs = tracing_forest::capture()
+    ///     .build()
+    ///     .on(async {
+    ///         info_span!("outer").in_scope(|| {
+    ///             info_span!("inner").in_scope(|| {
+    ///                 info!("leaf");
+    ///             });
+    ///         });
+    ///     })
+    ///     .await;
+    ///
+    /// let path = logs[0].path_to(|node| {
+    ///     node.event().is_ok_and(|event| event.message() == Some("leaf"))
+    /// });
+    ///
+    /// assert!(path.unwrap().join(" → ") == "outer → inner");
+    /// # }
+    /// ```
+    pub fn path_to(&self, pred: impl Fn(&Tree) -> bool) -> Option<Vec<&'static str>> {
+        fn find(tree: &Tree, pred: &dyn Fn(&Tree) -> bool, path: &mut Vec<&'static str>) -> bool {
+            if let Tree::Span(span) = tree {
+                path.push(span.name);
+            }
+
+            if pred(tree) {
+                return true;
+            }
+
+            if let Tree::Span(span) = tree {
+                if span.nodes.iter().any(|node| find(node, pred, path)) {
+                    return true;
+                }
+                path.pop();
+            }
+
+            false
+        }
+
+        let mut path = Vec::new();
+        find(self, &pred, &mut path).then_some(path)
+    }
+
+    /// Returns the number of spans in the tree that match `pred`, including
+    /// the tree itself if it's a span.
+    pub fn count_spans(&self, pred: impl Fn(&Span) -> bool) -> usize {
+        fn count(tree: &Tree, pred: &dyn Fn(&Span) -> bool) -> usize {
+            match tree {
+                Tree::Event(_) => 0,
+                Tree::Span(span) => {
+                    pred(span) as usize
+                        + span
+                            .nodes
+                            .iter()
+                            .map(|node| count(node, pred))
+                            .sum::<usize>()
+                }
+            }
+        }
+
+        count(self, &pred)
+    }
+
+    /// Returns a wrapper that serializes the tree with span durations in the
+    /// given [`DurationFormat`].
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use tracing::info;
+    /// use tracing_forest::tree::{DurationFormat, Tree};
+    ///
+    /// # #[tokio::main(flavor = "current_thread")]
+    /// # async fn main() {
+    /// tracing_forest::worker_task()
+    ///     .map_receiver(|receiver| {
+    ///         receiver.formatter(|tree: &Tree| {
+    ///             serde_json::to_string(&tree.with_duration_format(DurationFormat::Seconds))
+    ///         })
+    ///     })
+    ///     .build()
+    ///     .on(async {
+    ///         info!("durations in seconds");
+    ///     })
+    ///     .await
+    /// # }
+    /// ```
+    #[cfg(feature = "serde")]
+    pub fn with_duration_format(&self, format: DurationFormat) -> WithDurationFormat<'_> {
+        WithDurationFormat { tree: self, format }
+    }
+
+    /// Applies `f` to each node in the tree in pre-order, allowing the tree to
+    /// be transformed in place.
+    ///
+    /// The callback is applied to a span before its children are visited, so
+    /// children added or removed by the callback are reflected in the walk.
+    ///
+    /// # Examples
+    ///
+    /// Redacting all event fields named `password`:
+    /// ```
+    /// # use tracing::info;
+    /// # use tracing_forest::tree::Tree;
+    /// # #[tokio::main(flavor = "current_thread")]
+    /// # async fn main() {
+    /// let mut logs = tracing_forest::capture()
+    ///     .build()
+    ///     .on(async {
+    ///         info!(password = "hunter2", "logging in");
+    ///     })
+    ///     .await;
+    ///
+    /// for tree in logs.iter_mut() {
+    ///     tree.walk_mut(|node| {
+    ///         if let Tree::Event(event) = node {
+    ///             event.retain_fields(|field| field.key() != "password");
+    ///         }
+    ///     });
+    /// }
+    ///
+    /// assert!(logs[0].event().unwrap().fields().is_empty());
+    /// # }
+    /// ```
+    pub fn walk_mut(&mut self, mut f: impl FnMut(&mut Tree)) {
+        self.walk_mut_inner(&mut f);
+    }
+
+    fn walk_mut_inner(&mut self, f: &mut dyn FnMut(&mut Tree)) {
+        f(self);
+
+        if let Tree::Span(span) = self {
+            for node in span.nodes.iter_mut() {
+                node.walk_mut_inner(f);
+            }
+        }
+    }
+}
+
+impl Shared {
+    /// Returns a new `Shared` at the given level.
+    ///
+    /// With the `uuid` feature, the [`Uuid`] is nil until set with
+    /// [`with_uuid`]. With the `chrono` feature, the timestamp is the current
+    /// time until set with [`with_timestamp`].
+    ///
+    /// [`with_uuid`]: Shared::with_uuid
+    /// [`with_timestamp`]: Shared::with_timestamp
+    pub fn new(level: Level) -> Self {
+        Shared {
+            #[cfg(feature = "uuid")]
+            uuid: Uuid::nil(),
+            #[cfg(feature = "chrono")]
+            timestamp: Utc::now(),
+            level,
+            recorded_at: since_epoch(),
+            #[cfg(feature = "tokio")]
+            task_id: None,
+        }
+    }
+
+    /// Set the [`Uuid`] of the event or span.
+    #[cfg(feature = "uuid")]
+    pub fn with_uuid(mut self, uuid: Uuid) -> Self {
+        self.uuid = uuid;
+        self
+    }
+
+    /// Set when the event occurred or when the span opened.
+    #[cfg(feature = "chrono")]
+    pub fn with_timestamp(mut self, timestamp: DateTime<Utc>) -> Self {
+        self.timestamp = timestamp;
+        self
+    }
+
+    /// Returns the [`Uuid`] of the event or span.
+    #[cfg(feature = "uuid")]
+    pub fn uuid(&self) -> Uuid {
+        self.uuid
+    }
+
+    /// Returns when the event occurred or when the span opened.
+    #[cfg(feature = "chrono")]
+    pub fn timestamp(&self) -> DateTime<Utc> {
+        self.timestamp
+    }
+
+    /// Returns the level of the event or span.
+    pub fn level(&self) -> Level {
+        self.level
+    }
+
+    /// Returns when the event or span was recorded, as an offset from the
+    /// first time anything was recorded in the process.
+    ///
+    /// Unlike the timestamp, this comes from a monotonic clock, so it's a
+    /// stable ordering key that's immune to clock adjustments. Offsets are
+    /// serialized as `recorded_at_nanos`, but they're only comparable between
+    /// trees recorded by the same run of a process, since each run has its own
+    /// epoch. Trees serialized before schema version 5 don't have an offset,
+    /// and are deserialized with an offset of zero.
+    pub fn recorded_at(&self) -> Duration {
+        self.recorded_at
+    }
+
+    /// Returns the [`Id`] of the Tokio task that the event occurred in or
+    /// that the span was opened in, or `None` if it happened outside of a
+    /// task.
+    ///
+    /// Like [`recorded_at`], task ids are only meaningful within a single
+    /// run of a process. Unlike it, they aren't serialized, so deserialized
+    /// trees don't have them. Trees built with [`Shared::new`] don't have them
+    /// either.
+    ///
+    /// [`Id`]: tokio::task::Id
+    /// [`recorded_at`]: Shared::recorded_at
+    #[cfg(feature = "tokio")]
+    pub fn task_id(&self) -> Option<tokio::task::Id> {
+        self.task_id
+    }
 }
 
 impl Event {
+    /// Returns a new `Event` without a message, tag, or fields.
+    ///
+    /// This is only needed to build trees from sources other than `tracing`.
+    /// See [`Shared`] for an example.
+    pub fn new(shared: Shared) -> Self {
+        Event {
+            shared,
+            #[cfg(feature = "uuid")]
+            span_uuid: None,
+            message: None,
+            tag: None,
+            fields: FieldSet::default(),
+            hidden: false,
+        }
+    }
+
     /// Returns the event's [`Uuid`].
     #[cfg(feature = "uuid")]
     pub fn uuid(&self) -> Uuid {
         self.shared.uuid
     }
 
+    /// Returns the [`Uuid`] of the span that the event occurred in, or `None`
+    /// if it occurred outside of any span.
+    #[cfg(feature = "uuid")]
+    pub fn span_uuid(&self) -> Option<Uuid> {
+        self.span_uuid
+    }
+
     /// Returns the [`DateTime`] that the event occurred at.
     #[cfg(feature = "chrono")]
     pub fn timestamp(&self) -> DateTime<Utc> {
@@ -219,11 +1048,65 @@ impl Event {
         self.shared.level
     }
 
+    /// Returns when the event was recorded, as an offset from the first time
+    /// anything was recorded in the process.
+    ///
+    /// See [`Shared::recorded_at`] for details.
+    pub fn recorded_at(&self) -> Duration {
+        self.shared.recorded_at
+    }
+
+    /// Returns the [`Id`] of the Tokio task that the event occurred in, or `None` if it
+    /// happened outside of a task.
+    ///
+    /// See [`Shared::task_id`] for details.
+    ///
+    /// [`Id`]: tokio::task::Id
+    #[cfg(feature = "tokio")]
+    pub fn task_id(&self) -> Option<tokio::task::Id> {
+        self.shared.task_id
+    }
+
+    /// Returns a single character representing the event's level, one of
+    /// `T`, `D`, `I`, `W`, or `E`.
+    ///
+    /// See [`level_char`] for details.
+    ///
+    /// [`level_char`]: crate::printer::level_char
+    pub fn level_char(&self) -> char {
+        crate::printer::level_char(self.shared.level)
+    }
+
     /// Returns the event's message, if there is one.
+    ///
+    /// Following Tracing's convention, the message is the field named `message`.
+    /// This means that `info!("hello")` and `info!(message = "hello")` produce
+    /// the same message, and the `message` field doesn't appear in [`fields`].
+    /// Values recorded with `?` are stored using their `Debug` representation.
+    ///
+    /// [`fields`]: Event::fields
     pub fn message(&self) -> Option<&str> {
         self.message.as_deref()
     }
 
+    /// Returns `true` if the event has a message containing `needle`.
+    ///
+    /// Matching is case-sensitive. An event without a message never matches.
+    pub fn message_contains(&self, needle: &str) -> bool {
+        self.message()
+            .is_some_and(|message| message.contains(needle))
+    }
+
+    /// Returns `true` if the event has a message matched by `re`.
+    ///
+    /// Like [`Regex::is_match`], the match may occur anywhere in the message
+    /// unless the pattern is anchored with `^` or `$`. An event without a
+    /// message never matches.
+    #[cfg(feature = "regex")]
+    pub fn message_matches(&self, re: &Regex) -> bool {
+        self.message().is_some_and(|message| re.is_match(message))
+    }
+
     /// Returns the event's [`Tag`], if there is one.
     pub fn tag(&self) -> Option<Tag> {
         self.tag
@@ -233,15 +1116,99 @@ impl Event {
     pub fn fields(&self) -> &[Field] {
         &self.fields
     }
+
+    /// Returns `true` if the event is hidden from formatted output.
+    ///
+    /// Events are hidden by setting the reserved `forest.hidden` field to
+    /// `true`, which isn't recorded in [`fields`]:
+    /// ```
+    /// tracing::info!(forest.hidden = true, "only visible to captures");
+    /// ```
+    /// Hidden events are still collected into trees, so they can be inspected
+    /// through [`capture`] or a custom [`Processor`], but [`Pretty`],
+    /// [`Logfmt`], [`EsBulk`], and [`SummaryLine`] skip them. Serialized trees
+    /// keep them, marked with `"hidden": true`.
+    ///
+    /// [`fields`]: Event::fields
+    /// [`capture`]: crate::runtime::capture
+    /// [`Processor`]: crate::Processor
+    /// [`Pretty`]: crate::printer::Pretty
+    /// [`Logfmt`]: crate::printer::Logfmt
+    /// [`EsBulk`]: crate::printer::EsBulk
+    /// [`SummaryLine`]: crate::printer::SummaryLine
+    pub fn is_hidden(&self) -> bool {
+        self.hidden
+    }
+
+    /// Set the event's message.
+    pub fn set_message(&mut self, message: Option<String>) {
+        self.message = message;
+    }
+
+    /// Set the event's [`Tag`].
+    pub fn set_tag(&mut self, tag: Option<Tag>) {
+        self.tag = tag;
+    }
+
+    /// Appends a field to the event.
+    pub fn push_field(&mut self, field: Field) {
+        self.fields.push(field);
+    }
+
+    /// Retains only the fields specified by the predicate.
+    pub fn retain_fields(&mut self, mut f: impl FnMut(&Field) -> bool) {
+        self.fields.retain(|field| f(field));
+    }
+}
+
+impl Drop for Span {
+    fn drop(&mut self) {
+        // Dropping children recursively can overflow the stack for very deep
+        // trees, so descendants are moved onto an explicit stack instead, and
+        // each span is dropped once it has no children left.
+        if self.nodes.iter().all(Tree::is_event) {
+            return;
+        }
+
+        let mut stack = std::mem::take(&mut self.nodes);
+        while let Some(node) = stack.pop() {
+            if let Tree::Span(mut span) = node {
+                stack.append(&mut span.nodes);
+            }
+        }
+    }
 }
 
 impl Span {
-    pub(crate) fn new(shared: Shared, name: &'static str) -> Self {
+    /// Returns a new `Span` without any children, whose durations are zero.
+    ///
+    /// This is only needed to build trees from sources other than `tracing`.
+    /// Children can be added with [`push_node`](Span::push_node).
+    pub fn new(shared: Shared, name: &'static str) -> Self {
+        static NEXT_ID: AtomicU64 = AtomicU64::new(1);
+
         Span {
+            #[cfg(feature = "chrono")]
+            end_timestamp: shared.timestamp,
             shared,
+            #[cfg(feature = "uuid")]
+            parent_uuid: None,
+            id: NEXT_ID.fetch_add(1, Ordering::Relaxed),
+            follows_from: Vec::new(),
             name,
+            display_name: None,
+            tag: None,
+            fields: FieldSet::default(),
+            status: SpanStatus::Unset,
+            instrumented: false,
+            color: None,
+            partial: false,
+            sampled: true,
             total_duration: Duration::ZERO,
             inner_duration: Duration::ZERO,
+            idle_duration: Duration::ZERO,
+            activity: SpanActivity::default(),
+            filtered_event_count: 0,
             nodes: Vec::new(),
         }
     }
@@ -252,23 +1219,195 @@ impl Span {
         self.shared.uuid
     }
 
+    /// Returns the [`Uuid`] of the span's parent span, or `None` if the span
+    /// is the root of its tree.
+    #[cfg(feature = "uuid")]
+    pub fn parent_uuid(&self) -> Option<Uuid> {
+        self.parent_uuid
+    }
+
     /// Returns the [`DateTime`] that the span occurred at.
     #[cfg(feature = "chrono")]
     pub fn timestamp(&self) -> DateTime<Utc> {
         self.shared.timestamp
     }
 
+    /// Returns the [`DateTime`] that the span was closed at.
+    ///
+    /// This is recorded when the span closes rather than derived from
+    /// [`total_duration`], which only counts the time the span was entered.
+    ///
+    /// [`total_duration`]: Span::total_duration
+    #[cfg(feature = "chrono")]
+    pub fn end_timestamp(&self) -> DateTime<Utc> {
+        self.end_timestamp
+    }
+
+    /// Returns the [`DateTime`]s that the span was opened and closed at.
+    #[cfg(feature = "chrono")]
+    pub fn interval(&self) -> (DateTime<Utc>, DateTime<Utc>) {
+        (self.shared.timestamp, self.end_timestamp)
+    }
+
     /// Returns the span's [`Level`].
     pub fn level(&self) -> Level {
         self.shared.level
     }
 
+    /// Returns when the span was recorded, as an offset from the first time
+    /// anything was recorded in the process.
+    ///
+    /// See [`Shared::recorded_at`] for details.
+    pub fn recorded_at(&self) -> Duration {
+        self.shared.recorded_at
+    }
+
+    /// Returns the [`Id`] of the Tokio task that the span was opened in, or `None` if it
+    /// happened outside of a task.
+    ///
+    /// See [`Shared::task_id`] for details.
+    ///
+    /// [`Id`]: tokio::task::Id
+    #[cfg(feature = "tokio")]
+    pub fn task_id(&self) -> Option<tokio::task::Id> {
+        self.shared.task_id
+    }
+
+    /// Returns an identifier that is unique among all spans recorded in the
+    /// process.
+    ///
+    /// Unlike the span's `Uuid`, this isn't inherited by child spans, and
+    /// unlike a Tracing span [`Id`], it's never reused. Deserialized spans keep
+    /// the identifier they were recorded with, so identifiers are only unique
+    /// among spans recorded by the same process.
+    ///
+    /// [`Id`]: tracing::span::Id
+    pub fn id(&self) -> u64 {
+        self.id
+    }
+
+    /// Returns the [`id`]s of the spans that this span follows from, as set
+    /// by [`tracing::Span::follows_from`].
+    ///
+    /// [`id`]: Span::id
+    pub fn follows_from(&self) -> &[u64] {
+        &self.follows_from
+    }
+
     /// Returns the span's name.
     pub fn name(&self) -> &str {
         self.name
     }
 
+    /// Set the span's name.
+    pub fn set_name(&mut self, name: &'static str) {
+        self.name = name;
+    }
+
+    /// Returns the name that the span is displayed with.
+    ///
+    /// This is the name derived by [`ForestLayer::span_name_fn`] if one was
+    /// set, and the span's [`name`] otherwise.
+    ///
+    /// [`ForestLayer::span_name_fn`]: crate::layer::ForestLayer::span_name_fn
+    /// [`name`]: Span::name
+    pub fn display_name(&self) -> &str {
+        self.display_name.as_deref().unwrap_or(self.name)
+    }
+
+    /// Returns the span's [`Tag`], as parsed by [`TagParser::parse_span`].
+    ///
+    /// [`TagParser::parse_span`]: crate::tag::TagParser::parse_span
+    pub fn tag(&self) -> Option<Tag> {
+        self.tag
+    }
+
+    /// Returns the fields that the span was created with or later recorded.
+    ///
+    /// Fields recorded again after the span was created replace their
+    /// earlier value, keeping their position. Values are stored using their
+    /// `Debug` representation. Fields that configure the layer, such as
+    /// `forest.color` or the status field, aren't included.
+    pub fn fields(&self) -> &[Field] {
+        &self.fields
+    }
+
+    /// Returns the span's [`SpanStatus`].
+    pub fn status(&self) -> SpanStatus {
+        self.status
+    }
+
+    /// Returns `true` if the span was created by the [`#[instrument]`] macro.
+    ///
+    /// `tracing` doesn't mark spans created by `#[instrument]` in their
+    /// metadata, so they can't be told apart from hand-written spans
+    /// automatically. Instead, spans are marked by setting the reserved
+    /// `forest.instrumented` field to `true` when the span is created, which
+    /// `#[instrument]` can do through its `fields` argument:
+    /// ```
+    /// #[tracing::instrument(fields(forest.instrumented = true))]
+    /// fn handle_request() {
+    ///     // ...
+    /// }
+    /// ```
+    ///
+    /// [`#[instrument]`]: tracing::instrument
+    pub fn is_instrumented(&self) -> bool {
+        self.instrumented
+    }
+
+    /// Returns the color that the span is displayed with, if its
+    /// `forest.color` field was set to a recognized [`SpanColor`].
+    pub fn color(&self) -> Option<SpanColor> {
+        self.color
+    }
+
+    /// Returns `true` if the tree containing the span was sampled, as decided
+    /// by its root span according to the [sample rate].
+    ///
+    /// Spans that weren't sampled have no children.
+    ///
+    /// [sample rate]: crate::ForestLayer::sample_rate
+    pub fn sampled(&self) -> bool {
+        self.sampled
+    }
+
+    /// Returns `true` if the span is a partial snapshot of a span that was
+    /// still open, as emitted when a [flush threshold] is set.
+    ///
+    /// [flush threshold]: crate::ForestLayer::flush_threshold
+    pub fn is_partial(&self) -> bool {
+        self.partial
+    }
+
+    /// Returns the number of events that occurred directly in the span but
+    /// were dropped by [`ForestLayer::min_event_level`] or
+    /// [`Forest::filter_level`].
+    ///
+    /// A nonzero count means the span is hiding activity. Events in nested
+    /// spans are counted by those spans instead.
+    ///
+    /// [`ForestLayer::min_event_level`]: crate::layer::ForestLayer::min_event_level
+    pub fn filtered_count(&self) -> u64 {
+        self.filtered_event_count
+    }
+
+    /// Appends a child tree to the span.
+    pub fn push_node(&mut self, node: Tree) {
+        self.nodes.push(node);
+    }
+
     /// Returns the span's child trees.
+    ///
+    /// Children are ordered according to the layer's [`ChildOrder`], and
+    /// events are always ordered by when they occurred. An event is recorded in
+    /// a span as long as the span hasn't started closing, so events emitted
+    /// while the last handle to a span is being dropped, such as from the
+    /// `Drop` impl of a guard that owns the span, are the span's last children.
+    /// Events that refer to a span which has already started closing are
+    /// treated as if they occurred outside of any span.
+    ///
+    /// [`ChildOrder`]: crate::layer::ChildOrder
     pub fn nodes(&self) -> &[Tree] {
         &self.nodes
     }
@@ -287,8 +1426,259 @@ impl Span {
         self.inner_duration
     }
 
+    /// Returns the total duration between exiting this span and entering it
+    /// again.
+    ///
+    /// If the span was used to instrument a `Future`, this is the time the
+    /// `Future` spent waiting to be polled again after yielding, such as while
+    /// sleeping. Together with [`total_duration`], this mirrors the busy and
+    /// idle times reported by `tokio-console`.
+    ///
+    /// [`total_duration`]: Span::total_duration
+    pub fn idle_duration(&self) -> Duration {
+        self.idle_duration
+    }
+
+    /// Returns counts of how many times the span was entered and exited, and
+    /// how many events were recorded directly within it.
+    ///
+    /// For a span instrumenting a `Future`, the number of enters is the number
+    /// of times the `Future` was polled.
+    pub fn activity(&self) -> SpanActivity {
+        self.activity
+    }
+
     /// Returns the duration this span was entered, but not in any child spans.
     pub fn base_duration(&self) -> Duration {
         self.total_duration - self.inner_duration
     }
+
+    /// Returns the most severe [`Level`] of the span or anything within it.
+    pub fn max_level(&self) -> Level {
+        // `Level`s compare by verbosity, so the most severe is the smallest.
+        self.nodes
+            .iter()
+            .map(|node| match node {
+                Tree::Event(event) => event.level(),
+                Tree::Span(span) => span.max_level(),
+            })
+            .fold(self.level(), Level::min)
+    }
+
+    /// Returns `true` if the span or anything within it is at the `ERROR` level.
+    pub fn is_error(&self) -> bool {
+        self.max_level() == Level::ERROR
+    }
+
+    /// Returns the mean [`total_duration`] of the span's direct child spans,
+    /// or zero if it has none.
+    ///
+    /// [`total_duration`]: Span::total_duration
+    pub fn mean_child_duration(&self) -> Duration {
+        let (count, sum) = self
+            .child_spans()
+            .fold((0, Duration::ZERO), |(count, sum), span| {
+                (count + 1, sum + span.total_duration())
+            });
+
+        match count {
+            0 => Duration::ZERO,
+            count => sum / count,
+        }
+    }
+
+    /// Returns the direct child spans whose [`total_duration`] exceeds `factor`
+    /// times the [`mean_child_duration`].
+    ///
+    /// This is useful for spotting which of many sibling operations was
+    /// unusually slow. Since outliers need something to be compared against,
+    /// spans with fewer than two child spans never have any.
+    ///
+    /// [`total_duration`]: Span::total_duration
+    /// [`mean_child_duration`]: Span::mean_child_duration
+    pub fn slow_children(&self, factor: f64) -> Vec<&Span> {
+        if self.child_spans().nth(1).is_none() {
+            return Vec::new();
+        }
+
+        let threshold = factor * self.mean_child_duration().as_secs_f64();
+
+        self.child_spans()
+            .filter(|span| span.total_duration().as_secs_f64() > threshold)
+            .collect()
+    }
+
+    /// Returns the depth of the first node within the span in pre-order that
+    /// matches `pred`, or `None` if no node does.
+    ///
+    /// The span's direct children are at depth 1, their children at depth 2,
+    /// and so on.
+    pub fn depth_of(&self, pred: impl Fn(&Tree) -> bool) -> Option<usize> {
+        fn find(span: &Span, pred: &dyn Fn(&Tree) -> bool, depth: usize) -> Option<usize> {
+            span.nodes.iter().find_map(|node| {
+                if pred(node) {
+                    return Some(depth);
+                }
+                match node {
+                    Tree::Span(span) => find(span, pred, depth + 1),
+                    Tree::Event(_) => None,
+                }
+            })
+        }
+
+        find(self, &pred, 1)
+    }
+
+    /// Returns the summed [`base_duration`] of the span and all of its
+    /// descendant spans, grouped by name.
+    ///
+    /// Spans sharing a name are accumulated together regardless of their
+    /// depth, so recursive or repeated operations each produce one figure.
+    ///
+    /// [`base_duration`]: Span::base_duration
+    pub fn duration_by_name(&self) -> HashMap<&'static str, Duration> {
+        fn accumulate(span: &Span, durations: &mut HashMap<&'static str, Duration>) {
+            *durations.entry(span.name).or_default() += span.base_duration();
+            for child in span.child_spans() {
+                accumulate(child, durations);
+            }
+        }
+
+        let mut durations = HashMap::new();
+        accumulate(self, &mut durations);
+        durations
+    }
+
+    /// Returns the number of events in the span, including those in
+    /// descendant spans.
+    pub fn total_events(&self) -> usize {
+        self.nodes
+            .iter()
+            .map(|node| match node {
+                Tree::Event(_) => 1,
+                Tree::Span(span) => span.total_events(),
+            })
+            .sum()
+    }
+
+    /// Returns how many events in the span, including those in descendant
+    /// spans, have a field with each key.
+    ///
+    /// An event with several fields sharing a key counts once for that key.
+    pub fn field_frequency(&self) -> HashMap<&str, usize> {
+        fn accumulate<'a>(span: &'a Span, counts: &mut HashMap<&'a str, usize>) {
+            for node in &span.nodes {
+                match node {
+                    Tree::Event(event) => {
+                        for (i, field) in event.fields.iter().enumerate() {
+                            let key = field.key();
+                            if event.fields[..i].iter().all(|prev| prev.key() != key) {
+                                *counts.entry(key).or_default() += 1;
+                            }
+                        }
+                    }
+                    Tree::Span(span) => accumulate(span, counts),
+                }
+            }
+        }
+
+        let mut counts = HashMap::new();
+        accumulate(self, &mut counts);
+        counts
+    }
+
+    /// Merges `other`, a later sibling with the same name, into the span.
+    fn merge(&mut self, other: &mut Span) {
+        fn severity(status: SpanStatus) -> u8 {
+            match status {
+                SpanStatus::Unset => 0,
+                SpanStatus::Ok => 1,
+                SpanStatus::Error => 2,
+            }
+        }
+
+        self.total_duration += other.total_duration;
+        self.inner_duration += other.inner_duration;
+        self.idle_duration += other.idle_duration;
+        self.activity.enters += other.activity.enters;
+        self.activity.exits += other.activity.exits;
+        self.activity.events += other.activity.events;
+        self.filtered_event_count += other.filtered_event_count;
+        if severity(other.status) > severity(self.status) {
+            self.status = other.status;
+        }
+        #[cfg(feature = "chrono")]
+        {
+            self.end_timestamp = self.end_timestamp.max(other.end_timestamp);
+        }
+        self.nodes.append(&mut other.nodes);
+    }
+
+    /// Returns an iterator over the span's direct children that are spans.
+    pub fn child_spans(&self) -> impl Iterator<Item = &Span> {
+        self.nodes.iter().filter_map(|node| match node {
+            Tree::Span(span) => Some(span),
+            Tree::Event(_) => None,
+        })
+    }
+
+    /// Returns an iterator over the span's direct children that are events.
+    pub fn child_events(&self) -> impl Iterator<Item = &Event> {
+        self.nodes.iter().filter_map(|node| match node {
+            Tree::Event(event) => Some(event),
+            Tree::Span(_) => None,
+        })
+    }
+}
+
+/// Iterates over the span's direct children, like [`Span::nodes`].
+impl<'a> IntoIterator for &'a Span {
+    type Item = &'a Tree;
+    type IntoIter = slice::Iter<'a, Tree>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.nodes.iter()
+    }
+}
+
+/// Iterates over the tree's direct children. Events have none.
+impl<'a> IntoIterator for &'a Tree {
+    type Item = &'a Tree;
+    type IntoIter = slice::Iter<'a, Tree>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        match self {
+            Tree::Event(_) => [].iter(),
+            Tree::Span(span) => span.nodes.iter(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_drop_deep_tree() {
+        let shared = Shared {
+            #[cfg(feature = "uuid")]
+            uuid: Uuid::nil(),
+            #[cfg(feature = "chrono")]
+            timestamp: Utc::now(),
+            level: Level::INFO,
+            recorded_at: since_epoch(),
+            #[cfg(feature = "tokio")]
+            task_id: None,
+        };
+
+        let mut tree = Tree::Span(Span::new(shared.clone(), "leaf"));
+        for _ in 0..100_000 {
+            let mut parent = Span::new(shared.clone(), "parent");
+            parent.nodes.push(tree);
+            tree = Tree::Span(parent);
+        }
+
+        // This would overflow the stack if children were dropped recursively.
+        drop(tree);
+    }
 }
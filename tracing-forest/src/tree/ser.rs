@@ -1,7 +1,12 @@
-use crate::tree::FieldSet;
+#[cfg(feature = "valuable")]
+use crate::tree::FieldValue;
+use crate::tree::{
+    DurationFormat, FieldSet, Span, SpanColor, Tree, WithDurationFormat, SCHEMA_VERSION,
+};
 #[cfg(feature = "chrono")]
 use chrono::{DateTime, Utc};
-use serde::ser::{SerializeMap, Serializer};
+use serde::ser::{Serialize, SerializeMap, SerializeSeq, Serializer};
+use std::convert::TryFrom;
 use std::time::Duration;
 use tracing::Level;
 
@@ -10,18 +15,70 @@ pub(super) fn level<S: Serializer>(level: &Level, serializer: S) -> Result<S::Ok
     serializer.serialize_str(level.as_str())
 }
 
-pub(super) fn nanos<S: Serializer>(duration: &Duration, serializer: S) -> Result<S::Ok, S::Error> {
-    serializer.serialize_u128(duration.as_nanos())
-}
-
 pub(super) fn fields<S: Serializer>(fields: &FieldSet, serializer: S) -> Result<S::Ok, S::Error> {
     let mut model = serializer.serialize_map(Some(fields.len()))?;
     for field in fields {
+        #[cfg(feature = "valuable")]
+        if let Some(structured) = field.structured_value() {
+            model.serialize_entry(field.key(), structured)?;
+            continue;
+        }
+
         model.serialize_entry(field.key(), field.value())?;
     }
     model.end()
 }
 
+/// Serializes a [`FieldSet`] as a map, for serializers that are written by
+/// hand rather than derived.
+struct Fields<'a>(&'a FieldSet);
+
+impl Serialize for Fields<'_> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        fields(self.0, serializer)
+    }
+}
+
+#[cfg(feature = "valuable")]
+impl Serialize for FieldValue {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        match self {
+            FieldValue::Unit => serializer.serialize_unit(),
+            FieldValue::Bool(value) => serializer.serialize_bool(*value),
+            FieldValue::I64(value) => serializer.serialize_i64(*value),
+            FieldValue::U64(value) => serializer.serialize_u64(*value),
+            FieldValue::I128(value) => serializer.serialize_i128(*value),
+            FieldValue::U128(value) => serializer.serialize_u128(*value),
+            FieldValue::F64(value) => serializer.serialize_f64(*value),
+            FieldValue::String(value) => serializer.serialize_str(value),
+            FieldValue::List(values) => {
+                let mut seq = serializer.serialize_seq(Some(values.len()))?;
+                for value in values {
+                    seq.serialize_element(value)?;
+                }
+                seq.end()
+            }
+            FieldValue::Map(entries) => {
+                let mut map = serializer.serialize_map(Some(entries.len()))?;
+                for (key, value) in entries {
+                    map.serialize_entry(key, value)?;
+                }
+                map.end()
+            }
+        }
+    }
+}
+
+/// Serializes a [`Duration`] as a whole number of nanoseconds, saturating at
+/// `u64::MAX`.
+pub(super) fn nanos<S: Serializer>(duration: &Duration, serializer: S) -> Result<S::Ok, S::Error> {
+    serializer.serialize_u64(saturating_nanos(*duration))
+}
+
+fn saturating_nanos(duration: Duration) -> u64 {
+    u64::try_from(duration.as_nanos()).unwrap_or(u64::MAX)
+}
+
 #[cfg(feature = "chrono")]
 pub(super) fn timestamp<S: Serializer>(
     timestamp: &DateTime<Utc>,
@@ -29,3 +86,203 @@ pub(super) fn timestamp<S: Serializer>(
 ) -> Result<S::Ok, S::Error> {
     serializer.serialize_str(&timestamp.to_rfc3339())
 }
+
+impl DurationFormat {
+    fn keys(self) -> [&'static str; 3] {
+        match self {
+            DurationFormat::Nanos => ["nanos_total", "nanos_nested", "nanos_idle"],
+            DurationFormat::Millis => ["millis_total", "millis_nested", "millis_idle"],
+            DurationFormat::Seconds => ["seconds_total", "seconds_nested", "seconds_idle"],
+        }
+    }
+
+    fn serialize_entry<M: SerializeMap>(
+        self,
+        map: &mut M,
+        key: &'static str,
+        duration: Duration,
+    ) -> Result<(), M::Error> {
+        match self {
+            DurationFormat::Nanos => map.serialize_entry(key, &duration.as_nanos()),
+            DurationFormat::Millis => map.serialize_entry(key, &(duration.as_secs_f64() * 1000.0)),
+            DurationFormat::Seconds => map.serialize_entry(key, &duration.as_secs_f64()),
+        }
+    }
+}
+
+impl Serialize for Span {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        SpanWithDurationFormat {
+            span: self,
+            format: DurationFormat::Nanos,
+        }
+        .serialize(serializer)
+    }
+}
+
+impl Serialize for Tree {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        self.with_duration_format(DurationFormat::Nanos)
+            .serialize(serializer)
+    }
+}
+
+impl Serialize for WithDurationFormat<'_> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut model = serializer.serialize_map(Some(2))?;
+        model.serialize_entry("schema_version", &SCHEMA_VERSION)?;
+        match self.tree {
+            Tree::Event(event) => model.serialize_entry("Event", event)?,
+            Tree::Span(span) => model.serialize_entry(
+                "Span",
+                &SpanWithDurationFormat {
+                    span,
+                    format: self.format,
+                },
+            )?,
+        }
+        model.end()
+    }
+}
+
+/// A node nested within a span, which doesn't repeat the schema version.
+struct NodeWithDurationFormat<'a> {
+    tree: &'a Tree,
+    format: DurationFormat,
+}
+
+impl Serialize for NodeWithDurationFormat<'_> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        match self.tree {
+            Tree::Event(event) => serializer.serialize_newtype_variant("Tree", 0, "Event", event),
+            Tree::Span(span) => serializer.serialize_newtype_variant(
+                "Tree",
+                1,
+                "Span",
+                &SpanWithDurationFormat {
+                    span,
+                    format: self.format,
+                },
+            ),
+        }
+    }
+}
+
+struct SpanWithDurationFormat<'a> {
+    span: &'a Span,
+    format: DurationFormat,
+}
+
+impl Serialize for SpanWithDurationFormat<'_> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let span = self.span;
+        let mut model = serializer.serialize_map(None)?;
+
+        #[cfg(feature = "uuid")]
+        model.serialize_entry("uuid", &span.shared.uuid)?;
+        #[cfg(feature = "uuid")]
+        model.serialize_entry("parent_id", &span.parent_uuid)?;
+        #[cfg(feature = "chrono")]
+        model.serialize_entry("timestamp", &span.shared.timestamp.to_rfc3339())?;
+        model.serialize_entry("level", span.shared.level.as_str())?;
+        model.serialize_entry(
+            "recorded_at_nanos",
+            &saturating_nanos(span.shared.recorded_at),
+        )?;
+        model.serialize_entry("id", &span.id)?;
+        model.serialize_entry("follows_from", &span.follows_from)?;
+        model.serialize_entry("name", span.name)?;
+        model.serialize_entry("display_name", &span.display_name)?;
+        model.serialize_entry("tag", &span.tag)?;
+        model.serialize_entry("fields", &Fields(&span.fields))?;
+        model.serialize_entry("status", &span.status)?;
+        model.serialize_entry("instrumented", &span.instrumented)?;
+        model.serialize_entry("color", &span.color.map(SpanColor::name))?;
+        model.serialize_entry("partial", &span.partial)?;
+        model.serialize_entry("sampled", &span.sampled)?;
+        #[cfg(feature = "chrono")]
+        model.serialize_entry("end_timestamp", &span.end_timestamp.to_rfc3339())?;
+
+        let [total, nested, idle] = self.format.keys();
+        self.format
+            .serialize_entry(&mut model, total, span.total_duration)?;
+        self.format
+            .serialize_entry(&mut model, nested, span.inner_duration)?;
+        self.format
+            .serialize_entry(&mut model, idle, span.idle_duration)?;
+
+        model.serialize_entry("activity", &span.activity)?;
+        model.serialize_entry("filtered_event_count", &span.filtered_event_count)?;
+        model.serialize_entry(
+            "nodes",
+            &NodesWithDurationFormat {
+                nodes: &span.nodes,
+                format: self.format,
+            },
+        )?;
+        model.end()
+    }
+}
+
+struct NodesWithDurationFormat<'a> {
+    nodes: &'a [Tree],
+    format: DurationFormat,
+}
+
+impl Serialize for NodesWithDurationFormat<'_> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut seq = serializer.serialize_seq(Some(self.nodes.len()))?;
+        for tree in self.nodes {
+            seq.serialize_element(&NodeWithDurationFormat {
+                tree,
+                format: self.format,
+            })?;
+        }
+        seq.end()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tree::{since_epoch, Shared};
+
+    fn span() -> Tree {
+        let shared = Shared {
+            #[cfg(feature = "uuid")]
+            uuid: uuid::Uuid::nil(),
+            #[cfg(feature = "chrono")]
+            timestamp: chrono::Utc::now(),
+            level: Level::INFO,
+            recorded_at: since_epoch(),
+            #[cfg(feature = "tokio")]
+            task_id: None,
+        };
+        let mut span = Span::new(shared, "span");
+        span.total_duration = Duration::from_millis(1500);
+        span.inner_duration = Duration::from_millis(500);
+        Tree::Span(span)
+    }
+
+    #[test]
+    fn test_duration_format_seconds() {
+        let tree = span();
+        let json =
+            serde_json::to_value(tree.with_duration_format(DurationFormat::Seconds)).unwrap();
+
+        assert!(json["Span"]["seconds_total"] == 1.5);
+        assert!(json["Span"]["seconds_nested"] == 0.5);
+        assert!(json["Span"].get("nanos_total").is_none());
+    }
+
+    #[test]
+    fn test_duration_format_default_is_nanos() {
+        let tree = span();
+        let json = serde_json::to_value(&tree).unwrap();
+
+        assert!(json["Span"]["nanos_total"] == 1_500_000_000u64);
+        assert!(
+            json == serde_json::to_value(tree.with_duration_format(DurationFormat::Nanos)).unwrap()
+        );
+    }
+}
@@ -0,0 +1,46 @@
+#![cfg(feature = "tokio")]
+use tokio::sync::broadcast::error::RecvError;
+use tracing_forest::processor::BroadcastProcessor;
+use tracing_forest::{traits::*, util::*};
+use tracing_subscriber::Registry;
+
+#[tokio::test]
+async fn test_broadcast_to_all_subscribers() -> Result<(), Box<dyn std::error::Error>> {
+    let processor = BroadcastProcessor::new(16);
+    let mut first = processor.subscribe();
+    let mut second = processor.subscribe();
+
+    let subscriber = Registry::default().with(ForestLayer::from(processor));
+    tracing::subscriber::with_default(subscriber, || {
+        info!("published");
+    });
+
+    let from_first = first.recv().await?;
+    let from_second = second.recv().await?;
+
+    assert!(from_first.event()?.message() == Some("published"));
+    assert!(from_second.event()?.message() == Some("published"));
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_broadcast_lagged_subscriber() -> Result<(), Box<dyn std::error::Error>> {
+    let processor = BroadcastProcessor::new(2);
+    let mut slow = processor.subscribe();
+
+    let subscriber = Registry::default().with(ForestLayer::from(processor));
+    tracing::subscriber::with_default(subscriber, || {
+        info!("first");
+        info!("second");
+        info!("third");
+    });
+
+    assert!(matches!(slow.recv().await, Err(RecvError::Lagged(1))));
+
+    // The oldest tree was dropped, and the rest are still received in order.
+    assert!(slow.recv().await?.event()?.message() == Some("second"));
+    assert!(slow.recv().await?.event()?.message() == Some("third"));
+
+    Ok(())
+}
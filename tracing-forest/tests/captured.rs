@@ -71,3 +71,1189 @@ async fn try_get_wrong_tree_variant() -> Result<(), Box<dyn Error>> {
 
     Ok(())
 }
+
+#[tokio::test]
+async fn test_validating_processor() {
+    use tracing_forest::processor::ValidatingProcessor;
+    use tracing_forest::PrettyPrinter;
+    use tracing_subscriber::Registry;
+
+    let layer = ForestLayer::from(ValidatingProcessor::new(PrettyPrinter::new()));
+    let _guard = tracing::subscriber::set_default(Registry::default().with(layer));
+
+    info_span!("outer").in_scope(|| {
+        let detached = info_span!("detached");
+        info_span!("inner").in_scope(|| {
+            info!("nested event");
+        });
+        detached.in_scope(|| {});
+    });
+}
+
+#[test]
+fn test_validating_processor_with_omitted_children_and_snapshots() {
+    use std::time::Duration;
+    use tracing_forest::processor::{Sink, ValidatingProcessor};
+    use tracing_subscriber::Registry;
+
+    let layer = ForestLayer::from(ValidatingProcessor::new(Sink))
+        .max_children(1)
+        .flush_threshold(Duration::ZERO);
+    let _guard = tracing::subscriber::set_default(Registry::default().with(layer));
+
+    info_span!("request").in_scope(|| {
+        for _ in 0..3 {
+            info_span!("query").in_scope(|| {
+                std::thread::sleep(Duration::from_millis(1));
+                info!("row");
+            });
+        }
+    });
+}
+
+#[tokio::test]
+async fn test_explicit_message_field() -> Result<(), Box<dyn Error>> {
+    #[derive(Debug)]
+    #[allow(dead_code)]
+    struct Value(u32);
+
+    let logs = tracing_forest::capture()
+        .build()
+        .on(async {
+            info!("x");
+            info!(message = "x");
+            info!(message = %"x");
+            info!(message = ?Value(7));
+        })
+        .await;
+
+    assert!(logs.len() == 4);
+
+    for tree in &logs[..3] {
+        let event = tree.event()?;
+        assert!(event.message() == Some("x"));
+        assert!(event.fields().is_empty());
+    }
+
+    let debug = logs[3].event()?;
+    assert!(debug.message() == Some("Value(7)"));
+    assert!(debug.fields().is_empty());
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_also_print() -> Result<(), Box<dyn Error>> {
+    use tracing_forest::printer::MakeBuffer;
+    use tracing_forest::Printer;
+
+    let buffer = MakeBuffer::new();
+
+    let logs = tracing_forest::capture()
+        .also_print(Printer::new().writer(buffer.clone()))
+        .build()
+        .on(async {
+            info!("first event");
+            info_span!("my_span").in_scope(|| {
+                info!("inside the span");
+            });
+        })
+        .await;
+
+    let rendered = buffer.contents();
+    let lines: Vec<&str> = rendered.lines().collect();
+
+    assert!(logs.len() == 2);
+    assert!(lines.len() == 3);
+
+    assert!(lines[0].contains(logs[0].event()?.message().unwrap()));
+
+    let span = logs[1].span()?;
+    assert!(lines[1].contains(span.name()));
+    assert!(lines[2].contains(span.nodes()[0].event()?.message().unwrap()));
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_idle_duration() -> Result<(), Box<dyn Error>> {
+    let logs = tracing_forest::capture()
+        .build()
+        .on(async {
+            async {
+                info!("before sleeping");
+                tokio::time::sleep(Duration::from_millis(50)).await;
+                info!("after sleeping");
+            }
+            .instrument(info_span!("sleepy"))
+            .await;
+
+            info_span!("busy").in_scope(|| {});
+        })
+        .await;
+
+    assert!(logs.len() == 2);
+
+    let sleepy = logs[0].span()?;
+    assert!(sleepy.idle_duration() >= Duration::from_millis(50));
+    assert!(sleepy.total_duration() < sleepy.idle_duration());
+
+    let busy = logs[1].span()?;
+    assert!(busy.idle_duration() == Duration::ZERO);
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_span_activity() -> Result<(), Box<dyn Error>> {
+    let logs = tracing_forest::capture()
+        .build()
+        .on(async {
+            let span = info_span!("twice");
+            span.in_scope(|| {
+                info!("only event");
+                info_span!("child").in_scope(|| {
+                    info!("not a direct event");
+                });
+            });
+            span.in_scope(|| {});
+        })
+        .await;
+
+    let activity = logs[0].span()?.activity();
+    assert!(activity.enters == 2);
+    assert!(activity.exits == 2);
+    assert!(activity.events == 1);
+
+    Ok(())
+}
+
+#[cfg(feature = "chrono")]
+#[tokio::test]
+async fn test_span_end_timestamp() -> Result<(), Box<dyn Error>> {
+    let logs = tracing_forest::capture()
+        .build()
+        .on(async {
+            async {
+                tokio::time::sleep(Duration::from_millis(5)).await;
+            }
+            .instrument(info_span!("sleeper"))
+            .await;
+        })
+        .await;
+
+    let span = logs[0].span()?;
+    assert!(span.end_timestamp() >= span.timestamp());
+
+    let (start, end) = span.interval();
+    assert!(start == span.timestamp());
+    assert!(end == span.end_timestamp());
+    // The wall-clock interval includes time spent sleeping, unlike the total
+    // duration which only counts time spent polling.
+    assert!((end - start).to_std()? >= Duration::from_millis(5));
+
+    Ok(())
+}
+
+#[tokio::test]
+#[allow(clippy::result_large_err)]
+async fn test_processor_map() -> Result<(), Box<dyn Error>> {
+    use std::sync::{Arc, Mutex};
+    use tracing_forest::processor::{self, Processor};
+    use tracing_forest::tree::Tree;
+    use tracing_subscriber::Registry;
+
+    let trees = Arc::new(Mutex::new(Vec::new()));
+    let downstream = {
+        let trees = trees.clone();
+        processor::from_fn(move |tree| {
+            trees.lock().unwrap().push(tree);
+            Ok(())
+        })
+    };
+
+    let processor = downstream.map(|mut tree: Tree| {
+        tree.walk_mut(|node| {
+            if let Tree::Event(event) = node {
+                event.retain_fields(|_| false);
+            }
+        });
+        tree
+    });
+
+    let subscriber = Registry::default().with(ForestLayer::from(processor));
+    tracing::subscriber::with_default(subscriber, || {
+        info!(secret = "hunter2", "root");
+        info_span!("span").in_scope(|| {
+            info!(answer = 42, "nested");
+        });
+    });
+
+    let trees = trees.lock().unwrap();
+    assert!(trees.len() == 2);
+
+    let root = trees[0].event()?;
+    assert!(root.message() == Some("root"));
+    assert!(root.fields().is_empty());
+
+    let nested = trees[1].span()?.nodes()[0].event()?;
+    assert!(nested.message() == Some("nested"));
+    assert!(nested.fields().is_empty());
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_max_field_len() -> Result<(), Box<dyn Error>> {
+    use tracing_subscriber::Registry;
+
+    // A multi-byte character straddles the 100 byte boundary.
+    let long = format!("{}é{}", "a".repeat(99), "b".repeat(4899));
+    assert!(long.len() == 5000);
+
+    let logs = tracing_forest::capture()
+        .build_with(|layer| Registry::default().with(layer.max_field_len(100)))
+        .on(async {
+            info!(blob = %long, short = "ok", "big");
+        })
+        .await;
+
+    let fields = logs[0].event()?.fields();
+
+    let blob = fields[0].value();
+    assert!(blob == format!("{}…(+4901 bytes)", "a".repeat(99)));
+    assert!(std::str::from_utf8(blob.as_bytes()).is_ok());
+
+    assert!(fields[1].value() == "\"ok\"");
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_lazy_fields() -> Result<(), Box<dyn Error>> {
+    use tracing_subscriber::Registry;
+
+    let record = || {
+        info!(
+            id = 7u64,
+            delta = -1i64,
+            big = u128::MAX,
+            ratio = 0.5,
+            ok = true,
+            name = "x",
+            "lazy"
+        );
+    };
+
+    let eager = tracing_forest::capture()
+        .build()
+        .on(async { record() })
+        .await;
+    let lazy = tracing_forest::capture()
+        .build_with(|layer| Registry::default().with(layer.lazy_fields(true)))
+        .on(async { record() })
+        .await;
+
+    let eager = eager[0].event()?.fields();
+    let lazy = lazy[0].event()?.fields();
+    assert!(lazy == eager);
+
+    let values: Vec<_> = lazy.iter().map(|field| field.value()).collect();
+    let big = u128::MAX.to_string();
+    assert!(values == ["7", "-1", big.as_str(), "0.5", "true", "\"x\""]);
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_span_fields_skip_reserved_keys() -> Result<(), Box<dyn Error>> {
+    use tracing_subscriber::Registry;
+
+    let logs = tracing_forest::capture()
+        .build_with(|layer| Registry::default().with(layer.max_field_len(4)))
+        .on(async {
+            let span = info_span!(
+                "request",
+                forest.color = "red",
+                otel.status_code = "ERROR",
+                path = "/login",
+                body = tracing::field::Empty,
+            );
+            span.record("body", "payload");
+            span.record("otel.status_code", "OK");
+            span.in_scope(|| info!("inside"));
+        })
+        .await;
+
+    let span = logs[0].span()?;
+    assert!(span.status() == tracing_forest::tree::SpanStatus::Ok);
+
+    let fields = span.fields();
+    assert!(fields.len() == 2);
+    assert!(fields[0].key() == "path");
+    assert!(fields[0].value() == "\"/lo…(+4 bytes)");
+    assert!(fields[1].key() == "body");
+    assert!(fields[1].value() == "\"pay…(+5 bytes)");
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_capture_panics() -> Result<(), Box<dyn Error>> {
+    let logs = tracing_forest::capture()
+        .capture_panics(true)
+        .build()
+        .on(async {
+            info_span!("risky").in_scope(|| {
+                let result = std::panic::catch_unwind(|| panic!("boom"));
+                assert!(result.is_err());
+            });
+        })
+        .await;
+
+    assert!(logs.len() == 1);
+
+    let risky = logs[0].span()?;
+    assert!(risky.nodes().len() == 1);
+
+    let event = risky.nodes()[0].event()?;
+    assert!(event.level() == Level::ERROR);
+    assert!(event.tag().unwrap().to_string() == "panic");
+    assert!(event.message() == Some("boom"));
+    assert!(event.fields()[0].key() == "location");
+    assert!(event.fields()[0].value().contains("captured.rs"));
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_root_level_events() -> Result<(), Box<dyn Error>> {
+    let logs = tracing_forest::capture()
+        .build()
+        .on(async {
+            info!("no span");
+            info_span!("some span").in_scope(|| {
+                info!("in span");
+            });
+            info!("no span again");
+        })
+        .await;
+
+    assert!(logs.len() == 3);
+    assert!(
+        matches!(&logs[0], tracing_forest::tree::Tree::Event(event) if event.message() == Some("no span"))
+    );
+    assert!(logs[1].span()?.nodes().len() == 1);
+    assert!(logs[2].event()?.message() == Some("no span again"));
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_with_metadata() -> Result<(), Box<dyn Error>> {
+    let result = tracing_forest::capture()
+        .with_metadata("test", "test_with_metadata")
+        .with_metadata("scenario", String::from("two entries"))
+        .build()
+        .on(async {
+            info!("hello");
+        })
+        .await;
+
+    assert!(result.trees.len() == 1);
+    assert!(result.trees[0].event()?.message() == Some("hello"));
+
+    assert!(result.meta.len() == 2);
+    assert!(result.meta.get("test") == Some("test_with_metadata"));
+    assert!(result.meta.get("scenario") == Some("two entries"));
+    assert!(result.meta.get("missing").is_none());
+
+    let entries: Vec<_> = result.meta.iter().collect();
+    assert!(entries == [("test", "test_with_metadata"), ("scenario", "two entries")]);
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_max_children() -> Result<(), Box<dyn Error>> {
+    use tracing_subscriber::Registry;
+
+    let logs = tracing_forest::capture()
+        .build_with(|layer| Registry::default().with(layer.max_children(3)))
+        .on(async {
+            info_span!("parent").in_scope(|| {
+                info_span!("child").in_scope(|| {
+                    for i in 0..5 {
+                        info!("grandchild {}", i);
+                    }
+                });
+                for i in 0..9 {
+                    info!("child {}", i);
+                }
+            });
+        })
+        .await;
+
+    let parent = logs[0].span()?;
+    assert!(parent.nodes().len() == 4);
+    assert!(parent.nodes()[2].event()?.message() == Some("child 1"));
+    assert!(parent.nodes()[3].event()?.message() == Some("…(+7 children omitted)"));
+    assert!(parent.activity().events == 9);
+
+    let child = parent.nodes()[0].span()?;
+    assert!(child.nodes().len() == 4);
+    assert!(child.nodes()[3].event()?.message() == Some("…(+2 children omitted)"));
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_span_children_capacity() -> Result<(), Box<dyn Error>> {
+    use tracing_subscriber::Registry;
+
+    let logs = tracing_forest::capture()
+        .build_with(|layer| Registry::default().with(layer.span_children_capacity(16)))
+        .on(async {
+            info_span!("wide").in_scope(|| {
+                for i in 0..1000 {
+                    info!("child {}", i);
+                }
+                info_span!("narrow").in_scope(|| {
+                    info!("only child");
+                });
+            });
+        })
+        .await;
+
+    let wide = logs[0].span()?;
+    assert!(wide.nodes().len() == 1001);
+    for (i, node) in wide.nodes()[..1000].iter().enumerate() {
+        assert!(node.event()?.message() == Some(format!("child {}", i).as_str()));
+    }
+    assert!(wide.activity().events == 1000);
+
+    let narrow = wide.nodes()[1000].span()?;
+    assert!(narrow.nodes().len() == 1);
+    assert!(narrow.nodes()[0].event()?.message() == Some("only child"));
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_filtered_count() -> Result<(), Box<dyn Error>> {
+    use tracing::Level;
+    use tracing_subscriber::Registry;
+
+    let emit = || async {
+        info_span!("request").in_scope(|| {
+            debug!("headers parsed");
+            info!("received");
+            trace!("body read");
+            debug_span!("query").in_scope(|| {
+                debug!("planned");
+                warn!("slow");
+            });
+        });
+        debug!("outside");
+    };
+
+    let logs = tracing_forest::capture()
+        .build_with(|layer| Registry::default().with(layer.min_event_level(Level::INFO)))
+        .on(emit())
+        .await;
+
+    assert!(logs.len() == 1);
+    let request = logs[0].span()?;
+    assert!(request.filtered_count() == 2);
+    assert!(request.nodes().len() == 2);
+    assert!(request.nodes()[0].event()?.message() == Some("received"));
+
+    let query = request.nodes()[1].span()?;
+    assert!(query.filtered_count() == 1);
+    assert!(query.nodes()[0].event()?.message() == Some("slow"));
+
+    // Pruning a captured forest counts the removed events the same way.
+    let logs = tracing_forest::capture().build().on(emit()).await;
+    assert!(logs[0].span()?.filtered_count() == 0);
+
+    let logs = logs.filter_level(Level::INFO);
+    assert!(logs.len() == 1);
+    assert!(logs[0].span()?.filtered_count() == 2);
+    assert!(logs[0].span()?.nodes()[1].span()?.filtered_count() == 1);
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_open_spans() -> Result<(), Box<dyn Error>> {
+    use tracing_forest::layer::OpenSpanTracker;
+    use tracing_subscriber::Registry;
+
+    let tracker = OpenSpanTracker::new();
+
+    let logs = tracing_forest::capture()
+        .build_with({
+            let tracker = tracker.clone();
+            |layer| Registry::default().with(layer.track_open_spans(tracker))
+        })
+        .on(async {
+            info_span!("done").in_scope(|| {});
+            let _created = info_span!("never entered");
+
+            let stuck = info_span!("stuck", lock = "db", owner = tracing::field::Empty);
+            let _entered = stuck.enter();
+            stuck.record("owner", 7);
+
+            let open = tracker.open_spans();
+            assert!(open.len() == 1);
+            assert!(open[0].name() == "stuck");
+            assert!(open[0].level() == Level::INFO);
+            assert!(open[0].fields()[0].key() == "lock");
+            assert!(open[0].fields()[0].value() == "\"db\"");
+            assert!(open[0].fields()[1].key() == "owner");
+            assert!(open[0].fields()[1].value() == "7");
+        })
+        .await;
+
+    assert!(tracker.open_spans().is_empty());
+    assert!(logs.spans().count() == 3);
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_on_tree() -> Result<(), Box<dyn Error>> {
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::Arc;
+    use tracing_subscriber::Registry;
+
+    let trees = Arc::new(AtomicUsize::new(0));
+    let spans = Arc::new(AtomicUsize::new(0));
+
+    let logs = tracing_forest::capture()
+        .build_with(|layer| {
+            let (trees, spans) = (trees.clone(), spans.clone());
+            Registry::default().with(
+                layer
+                    .on_tree(move |_| {
+                        trees.fetch_add(1, Ordering::Relaxed);
+                    })
+                    .on_tree(move |tree| {
+                        if tree.is_span() {
+                            spans.fetch_add(1, Ordering::Relaxed);
+                        }
+                    }),
+            )
+        })
+        .on(async {
+            info_span!("first").in_scope(|| {
+                info_span!("nested").in_scope(|| info!("inside"));
+            });
+            info_span!("second").in_scope(|| {});
+        })
+        .await;
+
+    // Nested spans aren't root trees, and the processor still sees every tree.
+    assert!(trees.load(Ordering::Relaxed) == 2);
+    assert!(spans.load(Ordering::Relaxed) == 2);
+    assert!(logs.len() == 2);
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_flush_threshold() -> Result<(), Box<dyn Error>> {
+    use tracing_subscriber::Registry;
+
+    let logs = tracing_forest::capture()
+        .build_with(|layer| {
+            Registry::default().with(layer.flush_threshold(Duration::from_millis(20)))
+        })
+        .on(async {
+            info_span!("long_running").in_scope(|| {
+                info!("early");
+                std::thread::sleep(Duration::from_millis(30));
+                info_span!("step").in_scope(|| {
+                    info!("late");
+                });
+            });
+        })
+        .await;
+
+    assert!(logs.len() == 2);
+
+    let partial = logs[0].span()?;
+    assert!(partial.is_partial());
+    assert!(partial.nodes().len() == 2);
+    let step = partial.nodes()[1].span()?;
+    assert!(step.is_partial());
+    assert!(step.nodes()[0].event()?.message() == Some("late"));
+
+    let complete = logs[1].span()?;
+    assert!(!complete.is_partial());
+    assert!(complete.nodes().len() == 2);
+    assert!(!complete.nodes()[1].span()?.is_partial());
+    assert!(partial.uuid() == complete.uuid());
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_capture_limit() -> Result<(), Box<dyn Error>> {
+    let result = tracing_forest::capture()
+        .limit(3)
+        .build()
+        .on(async {
+            for i in 0..10 {
+                info_span!("root", i).in_scope(|| {
+                    info!("inside {}", i);
+                });
+                tokio::task::yield_now().await;
+            }
+        })
+        .await;
+
+    assert!(result.trees.len() == 3);
+    assert!(result.dropped == 7);
+    assert!(result.meta.is_empty());
+
+    for (tree, i) in result.trees.iter().zip(7..) {
+        let span = tree.span()?;
+        assert!(span.name() == "root");
+        assert!(span.nodes()[0].event()?.message() == Some(format!("inside {}", i).as_str()));
+    }
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_capture_limit_is_enforced_by_the_sender() -> Result<(), Box<dyn Error>> {
+    use tracing_forest::printer::MakeBuffer;
+    use tracing_forest::Printer;
+
+    let buffer = MakeBuffer::new();
+    let result = tracing_forest::capture()
+        .also_print(Printer::new().writer(buffer.clone()))
+        .limit(2)
+        .build()
+        .on(async {
+            // The future never yields, so trees can't be collected by polling.
+            for i in 0..100 {
+                info!("event {}", i);
+            }
+            // Trees are handled as soon as they're sent.
+            assert!(buffer.contents().contains("event 99"));
+        })
+        .await;
+
+    assert!(result.trees.len() == 2);
+    assert!(result.dropped == 98);
+    assert!(result.trees[1].event()?.message() == Some("event 99"));
+
+    Ok(())
+}
+
+#[tokio::test]
+#[allow(clippy::result_large_err)]
+async fn test_field_match_processor() -> Result<(), Box<dyn Error>> {
+    use std::sync::{Arc, Mutex};
+    use tracing_forest::processor::{self, FieldMatchProcessor};
+    use tracing_subscriber::Registry;
+
+    let trees = Arc::new(Mutex::new(Vec::new()));
+    let downstream = {
+        let trees = trees.clone();
+        processor::from_fn(move |tree| {
+            trees.lock().unwrap().push(tree);
+            Ok(())
+        })
+    };
+
+    let processor = FieldMatchProcessor::new(downstream, "request_id", "abc");
+    let subscriber = Registry::default().with(ForestLayer::from(processor));
+    tracing::subscriber::with_default(subscriber, || {
+        info_span!("first").in_scope(|| {
+            info!(request_id = "xyz", "other request");
+        });
+        info_span!("second").in_scope(|| {
+            info_span!("nested").in_scope(|| {
+                info!(request_id = "abc", "target request");
+            });
+        });
+        info!(request_id = "abcd", "similar request");
+    });
+
+    let trees = trees.lock().unwrap();
+    assert!(trees.len() == 1);
+    assert!(trees[0].span()?.name() == "second");
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_sampling_decision_is_inherited() -> Result<(), Box<dyn Error>> {
+    use tracing_subscriber::Registry;
+
+    fn workload() {
+        info_span!("root").in_scope(|| {
+            info!("direct");
+            info_span!("child").in_scope(|| {
+                info_span!("grandchild").in_scope(|| {
+                    info!("nested");
+                });
+            });
+        });
+    }
+
+    let logs = tracing_forest::capture()
+        .build_with(|layer| Registry::default().with(layer.sample_rate(0.0)))
+        .on(async {
+            workload();
+            info!("root event");
+        })
+        .await;
+
+    assert!(logs.len() == 1);
+    let root = logs[0].span()?;
+    assert!(!root.sampled());
+    assert!(root.nodes().is_empty());
+    assert!(root.activity().events == 1);
+
+    let logs = tracing_forest::capture()
+        .build_with(|layer| Registry::default().with(layer.sample_rate(0.5)))
+        .on(async {
+            for _ in 0..50 {
+                workload();
+            }
+        })
+        .await;
+
+    assert!(logs.len() == 50);
+    for tree in &logs {
+        let root = tree.span()?;
+        if root.sampled() {
+            assert!(root.nodes().len() == 2);
+            let child = root.nodes()[1].span()?;
+            assert!(child.sampled());
+            assert!(child.nodes()[0].span()?.sampled());
+        } else {
+            assert!(root.nodes().is_empty());
+        }
+    }
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_forest_combinators() -> Result<(), Box<dyn Error>> {
+    use tracing_forest::tree::{Forest, Tree};
+
+    let logs: Forest = tracing_forest::capture()
+        .build()
+        .on(async {
+            info_span!("request").in_scope(|| {
+                debug!("details");
+                info_span!("db").in_scope(|| {
+                    warn!("slow query");
+                });
+            });
+            trace!("noise");
+            info!("done");
+        })
+        .await;
+
+    // Derefs to a slice like the previous `Vec<Tree>`.
+    assert!(logs.len() == 3);
+    assert!(logs[0].span()?.name() == "request");
+
+    assert!(logs.events().count() == 4);
+    assert!(logs.spans().count() == 2);
+    assert!(logs.nodes().count() == 6);
+
+    let spans_only: Forest = logs.iter().filter(|tree| tree.is_span()).cloned().collect();
+    assert!(spans_only.len() == 1);
+
+    let filtered = logs.clone().filter_level(Level::INFO);
+    assert!(filtered.len() == 2);
+    assert!(filtered.events().count() == 2);
+    assert!(filtered.events().all(|event| event.level() <= Level::INFO));
+
+    let rendered = filtered.render();
+    assert!(rendered.lines().count() == 4);
+    assert!(rendered.contains("slow query"));
+
+    let trees: Vec<Tree> = logs.into();
+    assert!(trees.len() == 3);
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_span_name_fn() -> Result<(), Box<dyn Error>> {
+    use tracing_subscriber::Registry;
+
+    let logs = tracing_forest::capture()
+        .build_with(|layer| {
+            Registry::default().with(layer.span_name_fn(|span, fields| {
+                match fields.iter().find(|field| field.key() == "route") {
+                    Some(route) => format!("{} {}", span.name(), route.value()).into(),
+                    None => span.name().into(),
+                }
+            }))
+        })
+        .on(async {
+            let request = info_span!("request", route = tracing::field::Empty);
+            request.record("route", "/users");
+            request.in_scope(|| {
+                info_span!("db").in_scope(|| info!("query"));
+            });
+        })
+        .await;
+
+    assert!(logs.len() == 1);
+
+    let request = logs[0].span()?;
+    assert!(request.name() == "request");
+    assert!(request.display_name() == "request \"/users\"");
+    assert!(request.display_name() != request.name());
+
+    let db = request.nodes()[0].span()?;
+    assert!(db.display_name() == db.name());
+
+    let rendered = logs.render();
+    assert!(rendered.contains("request \"/users\""));
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_discarded_events_are_not_visited() -> Result<(), Box<dyn Error>> {
+    use std::fmt;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use tracing_subscriber::Registry;
+
+    /// Counts how many times it's formatted, which is how field values are
+    /// turned into `Field`s.
+    struct Counted(&'static AtomicUsize);
+
+    impl fmt::Debug for Counted {
+        fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+            self.0.fetch_add(1, Ordering::Relaxed);
+            f.write_str("counted")
+        }
+    }
+
+    static FILTERED: AtomicUsize = AtomicUsize::new(0);
+    let logs = tracing_forest::capture()
+        .build_on(|subscriber| subscriber.with(LevelFilter::INFO))
+        .on(async {
+            trace!(value = ?Counted(&FILTERED), "filtered");
+            info_span!("request").in_scope(|| {
+                trace!(value = ?Counted(&FILTERED), "filtered");
+            });
+        })
+        .await;
+
+    assert!(FILTERED.load(Ordering::Relaxed) == 0);
+    assert!(logs.len() == 1);
+    assert!(logs[0].span()?.nodes().is_empty());
+
+    static UNSAMPLED: AtomicUsize = AtomicUsize::new(0);
+    let logs = tracing_forest::capture()
+        .build_with(|layer| Registry::default().with(layer.sample_rate(0.0)))
+        .on(async {
+            info!(value = ?Counted(&UNSAMPLED), "unsampled");
+            info_span!("request").in_scope(|| {
+                info!(value = ?Counted(&UNSAMPLED), "unsampled");
+            });
+        })
+        .await;
+
+    assert!(UNSAMPLED.load(Ordering::Relaxed) == 0);
+    assert!(logs.len() == 1);
+    assert!(logs[0].span()?.activity().events == 1);
+
+    static OMITTED: AtomicUsize = AtomicUsize::new(0);
+    let logs = tracing_forest::capture()
+        .build_with(|layer| Registry::default().with(layer.max_children(1)))
+        .on(async {
+            info_span!("request").in_scope(|| {
+                info!(value = ?Counted(&OMITTED), "kept");
+                info!(value = ?Counted(&OMITTED), "omitted");
+            });
+        })
+        .await;
+
+    assert!(OMITTED.load(Ordering::Relaxed) == 1);
+    let request = logs[0].span()?;
+    assert!(request.activity().events == 2);
+    assert!(request.nodes()[1].event()?.message() == Some("…(+1 children omitted)"));
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_capture_with_capacity() -> Result<(), Box<dyn Error>> {
+    let logs = tracing_forest::capture()
+        .with_capacity(4)
+        .build()
+        .on(async {
+            for i in 0..10 {
+                info_span!("root", i).in_scope(|| {
+                    info!("inside {}", i);
+                });
+            }
+        })
+        .await;
+
+    // More trees than the initial capacity are still collected, in order.
+    assert!(logs.len() == 10);
+    for (tree, i) in logs.iter().zip(0..) {
+        let span = tree.span()?;
+        assert!(span.name() == "root");
+        assert!(span.nodes()[0].event()?.message() == Some(format!("inside {}", i).as_str()));
+    }
+
+    let result = tracing_forest::capture()
+        .with_capacity(16)
+        .limit(2)
+        .build()
+        .on(async {
+            for i in 0..5 {
+                info!("event {}", i);
+            }
+        })
+        .await;
+
+    assert!(result.trees.len() == 2);
+    assert!(result.dropped == 3);
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_group_by_root_name() -> Result<(), Box<dyn Error>> {
+    let logs = tracing_forest::capture()
+        .build()
+        .on(async {
+            for i in 0..3 {
+                info_span!("get_user", i).in_scope(|| info!("fetched"));
+            }
+            info_span!("create_user").in_scope(|| info!("created"));
+            info!("standalone");
+            info_span!("get_user", i = 3).in_scope(|| {});
+        })
+        .await;
+
+    let groups = logs.group_by_root_name();
+    assert!(groups.len() == 3);
+
+    let get_user = &groups[&Some("get_user")];
+    assert!(get_user.len() == 4);
+    assert!(get_user
+        .iter()
+        .all(|tree| tree.root_name() == Some("get_user")));
+    assert!(get_user[3].span()?.nodes().is_empty());
+
+    let create_user = &groups[&Some("create_user")];
+    assert!(create_user.len() == 1);
+    assert!(create_user[0].span()?.nodes()[0].event()?.message() == Some("created"));
+
+    let events = &groups[&None];
+    assert!(events.len() == 1);
+    assert!(events[0].event()?.message() == Some("standalone"));
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_pretty_diff() -> Result<(), Box<dyn Error>> {
+    use tracing_forest::test::pretty_diff;
+
+    let run = |status: &'static str| {
+        tracing_forest::capture().build().on(async move {
+            info_span!("request").in_scope(|| {
+                info!("received");
+                info_span!("query").in_scope(|| {
+                    info!(status, "finished");
+                });
+                info!("responded");
+            });
+        })
+    };
+
+    let expected = run("ok").await;
+    let actual = run("failed").await;
+
+    // Durations, uuids, and timestamps differ between runs but aren't compared.
+    assert!(pretty_diff(&expected, &run("ok").await).is_empty());
+
+    let diff = pretty_diff(&expected, &actual);
+    let lines: Vec<&str> = diff.lines().collect();
+    assert!(lines[..3] == ["--- expected", "+++ actual", "@@ -1,5 +1,5 @@"]);
+    assert!(lines[3] == " INFO     request");
+    assert!(lines[6] == r#"-INFO     │  ┕━ ｉ [info]: finished | status: "ok""#);
+    assert!(lines[7] == r#"+INFO     │  ┕━ ｉ [info]: finished | status: "failed""#);
+    assert!(lines[8] == " INFO     ┕━ ｉ [info]: responded");
+    assert!(lines.len() == 9);
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_hidden_events() -> Result<(), Box<dyn Error>> {
+    let logs = tracing_forest::capture()
+        .build()
+        .on(async {
+            info_span!("request").in_scope(|| {
+                info!("visible");
+                info!(forest.hidden = true, attempt = 3, "hidden");
+            });
+            info!(forest.hidden = true, "hidden root");
+        })
+        .await;
+
+    // Both events are captured.
+    assert!(logs.len() == 2);
+    let span = logs[0].span()?;
+    assert!(span.nodes().len() == 2);
+
+    let visible = span.nodes()[0].event()?;
+    assert!(!visible.is_hidden());
+
+    let hidden = span.nodes()[1].event()?;
+    assert!(hidden.is_hidden());
+    assert!(hidden.message() == Some("hidden"));
+    assert!(hidden.fields().len() == 1);
+    assert!(hidden.fields()[0].key() == "attempt");
+    assert!(logs[1].event()?.is_hidden());
+
+    // Only the visible one is rendered, and it's drawn as the last child.
+    let rendered = logs.render();
+    let lines: Vec<&str> = rendered.lines().collect();
+    assert!(lines.len() == 2);
+    assert!(lines[1].ends_with("┕━ ｉ [info]: visible"));
+    assert!(!rendered.contains("hidden"));
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_task_ids() -> Result<(), Box<dyn Error>> {
+    use tracing::instrument::WithSubscriber;
+    use tracing_forest::printer::Pretty;
+
+    let logs = tracing_forest::capture()
+        .build()
+        .on(async {
+            info!("outside a task");
+            let first = tokio::spawn(
+                async { info_span!("first").in_scope(|| info!("in first")) }
+                    .with_current_subscriber(),
+            );
+            let second = tokio::spawn(
+                async { info_span!("second").in_scope(|| info!("in second")) }
+                    .with_current_subscriber(),
+            );
+            first.await.unwrap();
+            second.await.unwrap();
+        })
+        .await;
+
+    assert!(logs.len() == 3);
+    assert!(logs[0].event()?.task_id().is_none());
+
+    let first = logs
+        .spans()
+        .find(|span| span.name() == "first")
+        .ok_or("first")?;
+    let second = logs
+        .spans()
+        .find(|span| span.name() == "second")
+        .ok_or("second")?;
+    let first_id = first.task_id().ok_or("first has no task id")?;
+    let second_id = second.task_id().ok_or("second has no task id")?;
+    assert!(first_id != second_id);
+    assert!(first.nodes()[0].event()?.task_id() == Some(first_id));
+
+    let lines = Pretty::new()
+        .ansi(false)
+        .show_task_id(true)
+        .fmt_lines(first.nodes().first().ok_or("no event")?);
+    assert!(lines[0].contains(&format!("task {:<5} INFO", first_id)));
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_forest_filter() -> Result<(), Box<dyn Error>> {
+    let logs = tracing_forest::capture()
+        .build()
+        .on(async {
+            info_span!("request").in_scope(|| {
+                info!("received");
+                info_span!("query").in_scope(|| {
+                    info!(slow = true, "finished");
+                    info!("cached");
+                });
+                info_span!("render").in_scope(|| info!("rendered"));
+            });
+            info_span!("unrelated").in_scope(|| info!("noise"));
+            info!(slow = true, "standalone");
+        })
+        .await;
+
+    let slow = logs.filter(|tree| {
+        tree.event()
+            .is_ok_and(|event| event.fields().iter().any(|field| field.key() == "slow"))
+    });
+
+    assert!(slow.len() == 2);
+
+    // The matching event keeps its ancestors, but not its unmatched siblings.
+    let request = slow[0].span()?;
+    assert!(request.name() == "request");
+    assert!(request.nodes().len() == 1);
+    let query = request.nodes()[0].span()?;
+    assert!(query.name() == "query");
+    assert!(query.nodes().len() == 1);
+    assert!(query.nodes()[0].event()?.message() == Some("finished"));
+
+    assert!(slow[1].event()?.message() == Some("standalone"));
+
+    // The original forest is unchanged.
+    assert!(logs.len() == 3);
+    assert!(logs.events().count() == 6);
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_attribute_follows_from_sibling_origins() -> Result<(), Box<dyn Error>> {
+    let logs = tracing_forest::capture()
+        .build()
+        .on(async {
+            info_span!("root").in_scope(|| {
+                let first = info_span!("first");
+                let second = info_span!("second");
+                let first_worker = info_span!("first_worker");
+                first_worker.follows_from(&first);
+                first_worker.in_scope(|| {});
+                let second_worker = info_span!("second_worker");
+                second_worker.follows_from(&second);
+                second_worker.in_scope(|| {});
+            });
+        })
+        .await;
+
+    let id_of = |name: &str| logs.spans().find(|span| span.name() == name).unwrap().id();
+    let duration_of = |name: &str| {
+        logs.spans()
+            .find(|span| span.name() == name)
+            .unwrap()
+            .total_duration()
+    };
+
+    // Both origins are children of the same root, but are attributed
+    // separately.
+    let attributed = logs.attribute_follows_from();
+    assert!(attributed.len() == 2);
+    assert!(attributed[&id_of("first")] == duration_of("first_worker"));
+    assert!(attributed[&id_of("second")] == duration_of("second_worker"));
+
+    Ok(())
+}
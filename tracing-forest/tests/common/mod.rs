@@ -0,0 +1,16 @@
+use tracing_forest::printer::{Formatter, MakeBuffer};
+use tracing_forest::{ForestLayer, Printer};
+use tracing_subscriber::{layer::SubscriberExt, Registry};
+
+/// Runs `f` with a subscriber that formats each tree with `formatter`, and
+/// returns everything that was written.
+pub fn render<F>(formatter: F, f: impl FnOnce()) -> String
+where
+    F: 'static + Formatter + Send + Sync,
+{
+    let buffer = MakeBuffer::new();
+    let printer = Printer::new().formatter(formatter).writer(buffer.clone());
+    let subscriber = Registry::default().with(ForestLayer::from(printer));
+    tracing::subscriber::with_default(subscriber, f);
+    buffer.contents()
+}
@@ -0,0 +1,27 @@
+//! This test sets the `RUST_LOG` environment variable, so it's in its own
+//! compilation unit to avoid affecting other tests.
+#![cfg(all(feature = "tokio", feature = "env-filter"))]
+use tracing_forest::util::*;
+
+#[tokio::test]
+async fn test_build_filtered_env_filter() -> Result<(), Box<dyn std::error::Error>> {
+    std::env::set_var("RUST_LOG", "warn");
+
+    let logs = tracing_forest::capture()
+        .build_filtered(EnvFilter::from_default_env())
+        .on(async {
+            info!("filtered out");
+            warn!("kept");
+            info_span!("filtered span").in_scope(|| {
+                info!("also filtered out");
+                error!("kept in root");
+            });
+        })
+        .await;
+
+    assert!(logs.len() == 2);
+    assert!(logs[0].event()?.message() == Some("kept"));
+    assert!(logs[1].event()?.message() == Some("kept in root"));
+
+    Ok(())
+}
@@ -0,0 +1,83 @@
+use tracing_forest::printer::{EsBulk, MakeBuffer};
+use tracing_forest::{traits::*, util::*, Printer};
+use tracing_subscriber::Registry;
+
+mod common;
+use common::render;
+
+fn log() {
+    info_span!("Outer Span").in_scope(|| {
+        info!("first");
+        info_span!("inner").in_scope(|| {
+            warn!(answer = 42, "second \"quoted\"");
+        });
+    });
+    info!("standalone");
+}
+
+#[test]
+fn test_bulk_lines() {
+    let rendered = render(EsBulk::new(), log);
+    let lines: Vec<&str> = rendered.lines().collect();
+
+    assert!(lines.len() == 6);
+    assert!(lines.len().is_multiple_of(2));
+
+    for pair in lines.chunks(2) {
+        assert!(pair[0] == r#"{"index":{"_index":"logs"}}"#);
+        let document: serde_json::Value = serde_json::from_str(pair[1]).unwrap();
+        assert!(document.get("index").is_none());
+        assert!(document["level"].is_string());
+    }
+
+    let second: serde_json::Value = serde_json::from_str(lines[3]).unwrap();
+    assert!(second["message"] == "second \"quoted\"");
+    assert!(second["level"] == "WARN");
+    assert!(second["span"] == serde_json::json!(["Outer Span", "inner"]));
+    assert!(second["fields"]["answer"] == "42");
+
+    let standalone: serde_json::Value = serde_json::from_str(lines[5]).unwrap();
+    assert!(standalone["span"] == serde_json::json!([]));
+}
+
+#[test]
+fn test_index_from_root_span() {
+    let rendered = render(
+        EsBulk::new().index("fallback").index_from_root_span(true),
+        log,
+    );
+    let lines: Vec<&str> = rendered.lines().collect();
+
+    assert!(lines[0] == r#"{"index":{"_index":"outer_span"}}"#);
+    assert!(lines[2] == r#"{"index":{"_index":"outer_span"}}"#);
+    assert!(lines[4] == r#"{"index":{"_index":"fallback"}}"#);
+}
+
+#[test]
+fn test_invalid_index_names() {
+    let es_bulk = EsBulk::new().index("fallback").index_from_root_span(true);
+    let rendered = render(es_bulk.clone(), || {
+        info_span!("_internal").in_scope(|| info!("a"));
+        info_span!("-+Mixed").in_scope(|| info!("b"));
+        info_span!("..").in_scope(|| info!("c"));
+        info_span!("__").in_scope(|| info!("d"));
+    });
+    let lines: Vec<&str> = rendered.lines().collect();
+
+    assert!(lines[0] == r#"{"index":{"_index":"internal"}}"#);
+    assert!(lines[2] == r#"{"index":{"_index":"mixed"}}"#);
+    assert!(lines[4] == r#"{"index":{"_index":"fallback"}}"#);
+    assert!(lines[6] == r#"{"index":{"_index":"fallback"}}"#);
+
+    let buffer = MakeBuffer::new();
+    let printer = Printer::new().formatter(es_bulk).writer(buffer.clone());
+    let layer = ForestLayer::from(printer).span_name_fn(|_, _| "É".repeat(200).into());
+    tracing::subscriber::with_default(Registry::default().with(layer), || {
+        info_span!("long").in_scope(|| info!("e"));
+    });
+
+    let rendered = buffer.contents();
+    let action: serde_json::Value = serde_json::from_str(rendered.lines().next().unwrap()).unwrap();
+    let index = action["index"]["_index"].as_str().unwrap();
+    assert!(index == "é".repeat(127));
+}
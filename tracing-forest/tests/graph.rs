@@ -0,0 +1,47 @@
+#![cfg(all(feature = "tokio", feature = "petgraph"))]
+use tracing_forest::tree::{EdgeKind, TreeNodeRef};
+use tracing_forest::util::*;
+
+#[tokio::test]
+async fn test_to_digraph() -> Result<(), Box<dyn std::error::Error>> {
+    let logs = tracing_forest::capture()
+        .build()
+        .on(async {
+            info_span!("root").in_scope(|| {
+                info!("first");
+                let producer = info_span!("producer");
+                producer.in_scope(|| {
+                    info!("produce");
+                });
+                let consumer = info_span!("consumer");
+                consumer.follows_from(&producer);
+                consumer.in_scope(|| {
+                    info!("consume");
+                });
+            });
+        })
+        .await;
+
+    let graph = logs[0].to_digraph();
+
+    // root, first, producer, produce, consumer, consume
+    assert!(graph.node_count() == 6);
+    assert!(graph.edge_count() == 6);
+
+    let child_edges = graph
+        .edge_weights()
+        .filter(|kind| **kind == EdgeKind::Child)
+        .count();
+    assert!(child_edges == 5);
+
+    let follows = graph
+        .edge_indices()
+        .find(|&edge| graph[edge] == EdgeKind::FollowsFrom)
+        .expect("a follows_from edge");
+    let (origin, follower) = graph.edge_endpoints(follows).unwrap();
+
+    assert!(matches!(graph[origin], TreeNodeRef::Span(span) if span.name() == "producer"));
+    assert!(matches!(graph[follower], TreeNodeRef::Span(span) if span.name() == "consumer"));
+
+    Ok(())
+}
@@ -0,0 +1,109 @@
+use tracing_forest::printer::{EscapePolicy, Logfmt};
+use tracing_forest::util::*;
+
+mod common;
+use common::render;
+
+fn log() {
+    info_span!("outer").in_scope(|| {
+        info!("first");
+        info_span!("inner").in_scope(|| {
+            warn!(answer = 42, "second");
+        });
+    });
+}
+
+#[test]
+fn test_span_path() {
+    let rendered = render(Logfmt::new(), log);
+    let lines: Vec<&str> = rendered.lines().collect();
+
+    assert!(lines.len() == 2);
+    assert!(lines[0].contains(" msg=first span=outer"));
+    assert!(lines[1].contains(" msg=second span=outer/inner answer=42"));
+    assert!(lines[1].contains("level=WARN"));
+}
+
+#[test]
+fn test_span_events() {
+    let plain = render(Logfmt::new(), log);
+    let with_spans = render(Logfmt::new().span_events(true), log);
+
+    let plain_lines: Vec<&str> = plain.lines().collect();
+    let lines: Vec<&str> = with_spans.lines().collect();
+
+    // Two spans, each contributing an open and a close line.
+    assert!(lines.len() == plain_lines.len() + 4);
+
+    let opens: Vec<&str> = lines
+        .iter()
+        .copied()
+        .filter(|line| line.contains("msg=span_open"))
+        .collect();
+    let closes: Vec<&str> = lines
+        .iter()
+        .copied()
+        .filter(|line| line.contains("msg=span_close"))
+        .collect();
+
+    assert!(opens.len() == 2);
+    assert!(closes.len() == 2);
+    assert!(closes.iter().all(|line| line.contains(" duration_ns=")));
+    assert!(opens.iter().all(|line| !line.contains("duration_ns")));
+
+    // Lifecycle lines are interleaved with child events in order.
+    assert!(lines[0].contains("msg=span_open span=outer"));
+    assert!(lines[1].contains("msg=first"));
+    assert!(lines[2].contains("msg=span_open span=outer/inner"));
+    assert!(lines[3].contains("msg=second"));
+    assert!(lines[4].contains("msg=span_close span=outer/inner"));
+    assert!(lines[5].contains("msg=span_close span=outer"));
+}
+
+#[test]
+fn test_quoting() {
+    let rendered = render(Logfmt::new(), || {
+        info!(text = %"two words", debug = ?"already quoted", "hello world");
+    });
+
+    assert!(rendered.contains(r#"msg="hello world""#));
+    assert!(rendered.contains(r#"text="two words""#));
+    assert!(rendered.contains(r#"debug="already quoted""#));
+}
+
+#[test]
+fn test_newline_stays_on_one_line() {
+    let rendered = render(Logfmt::new(), || {
+        info!(text = %"first\nsecond", "multi\nline");
+        info!("after");
+    });
+
+    assert!(rendered.lines().count() == 2);
+    assert!(rendered.contains(r#"msg="multi\nline""#));
+    assert!(rendered.contains(r#"text="first\nsecond""#));
+}
+
+#[test]
+fn test_escape_policies() {
+    let event = || info!(text = %"it's\nhere", "hello");
+
+    let shell = render(Logfmt::new().escape(EscapePolicy::Shell), event);
+    assert!(shell.lines().count() == 1);
+    assert!(shell.contains(r#"text='it'\''s'$'\n''here'"#));
+
+    let verbatim = render(Logfmt::new().escape(EscapePolicy::None), event);
+    assert!(verbatim.lines().count() == 2);
+}
+
+#[test]
+fn test_quoted_display_value_is_escaped() {
+    let rendered = render(Logfmt::new(), || {
+        info!(user = %"\"evil\nlevel=ERROR msg=forged\"", "login");
+        info!(name = ?"debug \"quoted\"", "debug");
+    });
+
+    let lines: Vec<&str> = rendered.lines().collect();
+    assert!(lines.len() == 2);
+    assert!(lines[0].ends_with(r#"user="\"evil\nlevel=ERROR msg=forged\"""#));
+    assert!(lines[1].ends_with(r#"name="debug \"quoted\"""#));
+}
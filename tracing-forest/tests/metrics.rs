@@ -0,0 +1,61 @@
+#![cfg(feature = "metrics")]
+use metrics_util::debugging::{DebugValue, DebuggingRecorder};
+use metrics_util::MetricKind;
+use tracing_forest::processor::MetricsProcessor;
+use tracing_forest::{traits::*, util::*};
+use tracing_subscriber::Registry;
+
+#[test]
+fn test_metrics_processor() {
+    let recorder = DebuggingRecorder::new();
+    let snapshotter = recorder.snapshotter();
+
+    metrics::with_local_recorder(&recorder, || {
+        let subscriber = Registry::default().with(ForestLayer::from(MetricsProcessor::new()));
+
+        tracing::subscriber::with_default(subscriber, || {
+            info_span!("outer").in_scope(|| {
+                info!("first");
+                info_span!("inner").in_scope(|| {
+                    warn!("second");
+                });
+            });
+            info!("root");
+        });
+    });
+
+    let metrics: Vec<_> = snapshotter
+        .snapshot()
+        .into_vec()
+        .into_iter()
+        .map(|(key, _, _, value)| {
+            let kind = key.kind();
+            let key = key.key();
+            let label = key
+                .labels()
+                .next()
+                .map(|l| (l.key().to_owned(), l.value().to_owned()));
+            (kind, key.name().to_owned(), label, value)
+        })
+        .collect();
+
+    let find = |name: &str, label: (&str, &str)| {
+        metrics
+            .iter()
+            .find(|(_, n, l, _)| {
+                n == name && l.as_ref().map(|(k, v)| (k.as_str(), v.as_str())) == Some(label)
+            })
+            .map(|(kind, _, _, value)| (*kind, value))
+            .unwrap_or_else(|| panic!("missing metric `{}` with label {:?}", name, label))
+    };
+
+    assert!(find("events", ("level", "INFO")) == (MetricKind::Counter, &DebugValue::Counter(2)));
+    assert!(find("events", ("level", "WARN")) == (MetricKind::Counter, &DebugValue::Counter(1)));
+
+    for span in ["outer", "inner"] {
+        match find("span.duration", ("span", span)) {
+            (MetricKind::Histogram, DebugValue::Histogram(values)) => assert!(values.len() == 1),
+            other => panic!("unexpected metric {:?}", other),
+        }
+    }
+}
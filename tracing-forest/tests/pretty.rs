@@ -0,0 +1,657 @@
+use tracing_forest::printer::{strip_ansi, FieldSort, MakeBuffer, Pretty, TimestampFormat};
+use tracing_forest::{traits::*, util::*, Printer};
+use tracing_subscriber::Registry;
+
+mod common;
+use common::render;
+
+#[test]
+fn test_footer() {
+    let rendered = render(Pretty::new().footer(true), || {
+        info_span!("first").in_scope(|| {
+            info!("hello");
+            info_span!("inner").in_scope(|| {
+                error!("uh oh");
+                warn!("careful");
+            });
+        });
+        info!("standalone");
+    });
+
+    let footers: Vec<&str> = rendered
+        .lines()
+        .filter(|line| line.starts_with("└─ "))
+        .collect();
+
+    assert!(footers.len() == 2);
+    assert!(footers[0].starts_with("└─ 3 events, 1 error, "));
+    assert!(footers[1] == "└─ 1 event, 0 errors");
+
+    let lines: Vec<&str> = rendered.lines().collect();
+    assert!(lines[5] == footers[0]);
+    assert!(lines[7] == footers[1]);
+}
+
+#[test]
+fn test_no_footer_by_default() {
+    let rendered = render(Pretty::new(), || {
+        info_span!("first").in_scope(|| {
+            info!("hello");
+        });
+    });
+
+    assert!(rendered.lines().count() == 2);
+    assert!(!rendered.contains("└─"));
+}
+
+#[test]
+fn test_newline_marker_by_default() {
+    let rendered = render(Pretty::new(), || {
+        info!(text = %"first\nsecond", "multi\nline");
+    });
+
+    assert!(rendered.lines().count() == 1);
+    assert!(rendered.contains("multi⏎line | text: first⏎second"));
+}
+
+#[test]
+fn test_control_characters_escaped_by_default() {
+    let rendered = render(Pretty::new(), || {
+        info_span!("request").in_scope(|| {
+            info!(input = %"ring\x07\x1b[2Jcleared\ttab", "user\rsaid");
+        });
+    });
+
+    assert!(!rendered.contains('\x07'));
+    assert!(!rendered.contains('\x1b'));
+    assert!(!rendered.contains('\r'));
+    assert!(rendered.contains(r"user\x0dsaid | input: ring\x07\x1b[2Jcleared	tab"));
+}
+
+#[test]
+fn test_separator() {
+    let buffer = MakeBuffer::new();
+    let printer = Printer::new().separator("---\n").writer(buffer.clone());
+    let subscriber = Registry::default().with(ForestLayer::from(printer));
+    tracing::subscriber::with_default(subscriber, || {
+        info!("first");
+        info!("second");
+    });
+
+    let rendered = buffer.contents();
+    let lines: Vec<&str> = rendered.lines().collect();
+
+    assert!(rendered.matches("---").count() == 1);
+    assert!(lines.len() == 3);
+    assert!(lines[0].ends_with("first"));
+    assert!(lines[1] == "---");
+    assert!(lines[2].ends_with("second"));
+}
+
+fn column_of(line: &str, pattern: &str) -> usize {
+    line[..line.find(pattern).unwrap()].chars().count()
+}
+
+#[test]
+fn test_wrap_fields() {
+    // Wide enough for two fields per line after the uuid and timestamp.
+    const WIDTH: usize = 170;
+
+    let rendered = render(Pretty::new().wrap(Some(WIDTH)), || {
+        info_span!("my_span").in_scope(|| {
+            info!(
+                first = "aaaaaaaaaaaaaaaaaaaa",
+                second = "bbbbbbbbbbbbbbbbbbbb",
+                third = "cccccccccccccccccccc",
+                fourth = "dddddddddddddddddddd",
+                fifth = "eeeeeeeeeeeeeeeeeeee",
+                "many fields"
+            );
+            info!("after");
+        });
+    });
+
+    let lines: Vec<&str> = rendered.lines().collect();
+
+    // The span, the wrapped event over multiple lines, and the last event.
+    assert!(lines.len() > 3);
+    // One column per char, plus one for the double-width `ｉ` icon.
+    assert!(lines
+        .iter()
+        .all(|line| line.chars().count() + line.matches('ｉ').count() <= WIDTH));
+
+    let event = lines[1];
+    let continuations = &lines[2..lines.len() - 1];
+    assert!(continuations.len() >= 2);
+
+    // Fields on continuation lines are aligned under the message, and the
+    // connector to the next sibling is preserved.
+    let message_column = column_of(event, "many fields") + 1;
+    for line in continuations {
+        assert!(line.trim_start().starts_with('│'));
+        assert!(column_of(line, "| ") == message_column);
+    }
+
+    assert!(lines.last().unwrap().contains("┕━ "));
+    assert!(rendered.contains("fifth: \"eeeeeeeeeeeeeeeeeeee\""));
+}
+
+#[test]
+fn test_wrap_fields_under_wide_icon() {
+    const WIDTH: usize = 80;
+
+    let rendered = render(Pretty::new().ansi(false).wrap(Some(WIDTH)), || {
+        warn!(
+            first = "aaaaaaaaaaaaaaaaaaaa",
+            second = "bbbbbbbbbbbbbbbbbbbb",
+            third = "cccccccccccccccccccc",
+            "many fields"
+        );
+    });
+
+    let lines: Vec<&str> = rendered.lines().collect();
+    assert!(lines.len() > 1);
+
+    // The `🚧` icon is one char but two columns wide.
+    let message_column = column_of(lines[0], "many fields") + 1;
+    for line in &lines[1..] {
+        assert!(column_of(line, "| ") == message_column);
+    }
+}
+
+#[test]
+fn test_no_wrap() {
+    let rendered = render(Pretty::new().wrap(None), || {
+        info!(
+            first = "aaaaaaaaaaaaaaaaaaaa",
+            second = "bbbbbbbbbbbbbbbbbbbb",
+            third = "cccccccccccccccccccc",
+            "many fields"
+        );
+    });
+
+    assert!(rendered.lines().count() == 1);
+}
+
+#[test]
+fn test_no_ansi_when_not_a_terminal() {
+    let log = || {
+        info_span!("failing", otel.status_code = "error").in_scope(|| {
+            error!("oh no");
+        });
+    };
+
+    let rendered = render(Pretty::new(), log);
+    assert!(!rendered.contains("\x1b["));
+
+    let buffer = MakeBuffer::new();
+    let printer = Printer::new().ansi(true).writer(buffer.clone());
+    let subscriber = Registry::default().with(ForestLayer::from(printer));
+    tracing::subscriber::with_default(subscriber, log);
+
+    let colored = buffer.contents();
+    assert!(colored.contains("\x1b["));
+
+    let stripped = strip_ansi(&colored);
+    assert!(!stripped.contains('\x1b'));
+    assert!(stripped.lines().count() == rendered.lines().count());
+    assert!(stripped
+        .lines()
+        .last()
+        .unwrap()
+        .ends_with("ERROR    ┕━ 🚨 [error]: oh no"));
+}
+
+#[test]
+fn test_ansi_disabled_on_formatter() {
+    let buffer = MakeBuffer::new();
+    let printer = Printer::new()
+        .formatter(Pretty::new().ansi(false))
+        .ansi(true)
+        .writer(buffer.clone());
+    let subscriber = Registry::default().with(ForestLayer::from(printer));
+    tracing::subscriber::with_default(subscriber, || {
+        info!("plain");
+    });
+
+    assert!(!buffer.contents().contains('\x1b'));
+}
+
+#[test]
+fn test_inherit_span_tag() {
+    use tracing::span::Attributes;
+    use tracing_forest::tag::{Tag, TagParser};
+
+    struct HttpSpans;
+
+    impl TagParser for HttpSpans {
+        fn parse(&self, _event: &Event) -> Option<Tag> {
+            None
+        }
+
+        fn parse_span(&self, attrs: &Attributes) -> Option<Tag> {
+            match attrs.metadata().name() {
+                "request" => Some(
+                    Tag::builder()
+                        .prefix("net")
+                        .suffix("http")
+                        .icon('🌐')
+                        .build(),
+                ),
+                _ => None,
+            }
+        }
+    }
+
+    let log = || {
+        info_span!("request").in_scope(|| {
+            info!("received");
+            info_span!("handler").in_scope(|| {
+                warn!("slow");
+            });
+        });
+        info!("outside");
+    };
+
+    let render_tagged = |pretty: Pretty| {
+        let buffer = MakeBuffer::new();
+        let printer = Printer::new().formatter(pretty).writer(buffer.clone());
+        let layer = ForestLayer::new(printer, HttpSpans);
+        tracing::subscriber::with_default(Registry::default().with(layer), log);
+        buffer.contents()
+    };
+
+    let inherited = render_tagged(Pretty::new().inherit_span_tag(true));
+    let lines: Vec<&str> = inherited.lines().collect();
+    assert!(lines[1].ends_with("ｉ [net.http.info]: received"));
+    assert!(lines[3].ends_with("🚧 [net.http.warn]: slow"));
+    assert!(lines[4].ends_with("ｉ [info]: outside"));
+
+    let plain = render_tagged(Pretty::new());
+    assert!(plain
+        .lines()
+        .nth(1)
+        .unwrap()
+        .ends_with("ｉ [info]: received"));
+}
+
+#[test]
+fn test_ascii_icon_set() {
+    use tracing_forest::printer::IconSet;
+
+    let rendered = render(Pretty::new().icon_set(IconSet::Ascii), || {
+        trace!("a");
+        debug!("b");
+        info!("c");
+        warn!("d");
+        error!("e");
+    });
+
+    let lines: Vec<&str> = rendered.lines().collect();
+    assert!(lines[0].ends_with("[T] [trace]: a"));
+    assert!(lines[1].ends_with("[D] [debug]: b"));
+    assert!(lines[2].ends_with("[I] [info]: c"));
+    assert!(lines[3].ends_with("[W] [warn]: d"));
+    assert!(lines[4].ends_with("[E] [error]: e"));
+}
+
+#[test]
+fn test_icon_set_keeps_custom_icons() {
+    use tracing_forest::printer::IconSet;
+    use tracing_forest::tag::Tag;
+
+    fn tag(event: &Event) -> Option<Tag> {
+        match event.metadata().target() {
+            // A custom tag that happens to use the default icon for `WARN`.
+            "deploy" => Some(Tag::builder().suffix("deploy").icon('🚧').build()),
+            "security" => Some(Tag::builder().prefix("security").level(Level::WARN).build()),
+            _ => None,
+        }
+    }
+
+    let buffer = MakeBuffer::new();
+    let pretty = Pretty::new().icon_set(IconSet::Ascii);
+    let printer = Printer::new().formatter(pretty).writer(buffer.clone());
+    let layer = ForestLayer::new(printer, tag);
+    tracing::subscriber::with_default(Registry::default().with(layer), || {
+        info!(target: "deploy", "rolling out");
+        warn!(target: "security", "token reused");
+    });
+
+    let contents = buffer.contents();
+    let lines: Vec<&str> = contents.lines().collect();
+    assert!(lines[0].ends_with("🚧 [deploy]: rolling out"));
+    assert!(lines[1].ends_with("[W] [security.warn]: token reused"));
+}
+
+#[test]
+fn test_sort_fields() {
+    let record = || {
+        info!(zebra = 1, apple = 2, mango = 3, "fruit");
+    };
+
+    let insertion = render(Pretty::new().ansi(false).wrap(None), record);
+    assert!(insertion.contains("fruit | zebra: 1 | apple: 2 | mango: 3"));
+
+    let alphabetical = render(
+        Pretty::new()
+            .ansi(false)
+            .wrap(None)
+            .sort_fields(FieldSort::Alphabetical),
+        record,
+    );
+    assert!(alphabetical.contains("fruit | apple: 2 | mango: 3 | zebra: 1"));
+}
+
+#[test]
+fn test_timestamp_format() {
+    let record = || {
+        info!("hello");
+    };
+
+    // Each line starts with the uuid, then the timestamp, then the level.
+    let unix = render(
+        Pretty::new()
+            .ansi(false)
+            .timestamp_format(TimestampFormat::UnixNanos),
+        record,
+    );
+    let tokens: Vec<&str> = unix.split_whitespace().collect();
+    assert!(tokens[1].parse::<i64>().is_ok());
+    assert!(tokens[2] == "INFO");
+
+    let nanos = render(
+        Pretty::new()
+            .ansi(false)
+            .timestamp_format(TimestampFormat::Rfc3339Nanos),
+        record,
+    );
+    let timestamp = nanos.split_whitespace().nth(1).unwrap();
+    let fraction = timestamp.split('.').nth(1).unwrap();
+    assert!(fraction.split('+').next().unwrap().len() == 9);
+
+    let none = render(
+        Pretty::new()
+            .ansi(false)
+            .timestamp_format(TimestampFormat::None),
+        record,
+    );
+    assert!(none == "00000000-0000-0000-0000-000000000000 INFO     ｉ [info]: hello\n");
+}
+
+#[test]
+fn test_errors_only() {
+    let buffer = MakeBuffer::new();
+    let printer = Printer::new().writer(buffer.clone()).errors_only(true);
+    let subscriber = Registry::default().with(ForestLayer::from(printer));
+    tracing::subscriber::with_default(subscriber, || {
+        info_span!("clean").in_scope(|| {
+            info!("all good");
+            warn!("just a warning");
+        });
+        info_span!("failing").in_scope(|| {
+            info_span!("inner").in_scope(|| {
+                error!("something broke");
+            });
+        });
+        info!("standalone");
+    });
+
+    let rendered = buffer.contents();
+    assert!(rendered.contains("failing"));
+    assert!(rendered.contains("something broke"));
+    assert!(!rendered.contains("clean"));
+    assert!(!rendered.contains("standalone"));
+
+    let buffer = MakeBuffer::new();
+    let printer = Printer::new().writer(buffer.clone()).threshold(Level::WARN);
+    let subscriber = Registry::default().with(ForestLayer::from(printer));
+    tracing::subscriber::with_default(subscriber, || {
+        info_span!("clean").in_scope(|| warn!("just a warning"));
+        info!("standalone");
+    });
+
+    let rendered = buffer.contents();
+    assert!(rendered.contains("clean"));
+    assert!(!rendered.contains("standalone"));
+
+    let buffer = MakeBuffer::new();
+    let printer = Printer::new()
+        .writer(buffer.clone())
+        .threshold(Level::WARN)
+        .errors_only(false);
+    let subscriber = Registry::default().with(ForestLayer::from(printer));
+    tracing::subscriber::with_default(subscriber, || {
+        info_span!("clean").in_scope(|| warn!("just a warning"));
+        info!("standalone");
+    });
+
+    let rendered = buffer.contents();
+    assert!(rendered.contains("clean"));
+    assert!(!rendered.contains("standalone"));
+}
+
+#[test]
+fn test_span_color() {
+    const MAGENTA: &str = "\x1b[35m";
+
+    let log = || {
+        info_span!("request", forest.color = "magenta").in_scope(|| {
+            info!("colored");
+        });
+        info_span!("other", forest.color = "chartreuse").in_scope(|| {
+            info!("default colors");
+        });
+    };
+
+    let buffer = MakeBuffer::new();
+    let printer = Printer::new().ansi(true).writer(buffer.clone());
+    let subscriber = Registry::default().with(ForestLayer::from(printer));
+    tracing::subscriber::with_default(subscriber, log);
+
+    let colored = buffer.contents();
+    let lines: Vec<&str> = colored.lines().collect();
+    assert!(lines.len() == 4);
+
+    assert!(lines[0].contains(&format!("{}request", MAGENTA)));
+    assert!(lines[1].contains(&format!("{}ｉ [info]: ", MAGENTA)));
+
+    // Unrecognized colors fall back to the default colors.
+    assert!(!lines[2].contains(MAGENTA));
+    assert!(!lines[3].contains(MAGENTA));
+    assert!(strip_ansi(lines[2]).contains("other [ "));
+
+    // Colors don't change the layout.
+    assert!(strip_ansi(lines[1]).ends_with("┕━ ｉ [info]: colored"));
+}
+
+#[test]
+fn test_ditto_unchanged_fields() {
+    let record = || {
+        info_span!("loop").in_scope(|| {
+            info!(job = "sync", iteration = 1, "retrying");
+            info!(job = "sync", iteration = 2, "retrying");
+            info_span!("backoff").in_scope(|| {});
+            info!(job = "sync", iteration = 3, "retrying");
+        });
+    };
+
+    let rendered = render(
+        Pretty::new()
+            .ansi(false)
+            .wrap(None)
+            .ditto_unchanged_fields(true),
+        record,
+    );
+    let lines: Vec<&str> = rendered.lines().collect();
+
+    assert!(lines[1].ends_with("retrying | job: \"sync\" | iteration: 1"));
+    assert!(lines[2].ends_with("retrying | job: 〃 | iteration: 2"));
+    // Only the immediately preceding sibling is compared against.
+    assert!(lines[4].ends_with("retrying | job: \"sync\" | iteration: 3"));
+
+    let plain = render(Pretty::new().ansi(false).wrap(None), record);
+    assert!(!plain.contains('〃'));
+}
+
+#[test]
+fn test_max_message_len() {
+    let record = || {
+        info!("{}", "a".repeat(100));
+        info!("short");
+        info!("日本語のメッセージ");
+    };
+
+    let rendered = render(Pretty::new().ansi(false).max_message_len(10), record);
+    let lines: Vec<&str> = rendered.lines().collect();
+
+    assert!(lines[0].ends_with(&format!("[info]: {}…", "a".repeat(9))));
+    assert!(lines[1].ends_with("[info]: short"));
+    // Each CJK character is two columns wide, so only four fit before the
+    // ellipsis.
+    assert!(lines[2].ends_with("[info]: 日本語の…"));
+}
+
+#[test]
+fn test_max_message_len_counts_escapes() {
+    let record = || {
+        info!("ab\ncd\x07efghijk");
+        info!("e\u{301}e\u{301}e\u{301}e\u{301}e\u{301}e\u{301}");
+    };
+
+    let rendered = render(Pretty::new().ansi(false).max_message_len(10), record);
+    let lines: Vec<&str> = rendered.lines().collect();
+
+    // `⏎` and `\x07` count towards the width once the message is escaped.
+    assert!(lines[0].ends_with(r"[info]: ab⏎cd\x07…"));
+    // Combining accents don't take up a column.
+    assert!(lines[1].ends_with("[info]: e\u{301}e\u{301}e\u{301}e\u{301}e\u{301}e\u{301}"));
+}
+
+#[test]
+fn test_show_span_fields() {
+    let record = || {
+        info_span!(
+            "request",
+            user_id = 7,
+            path = "/login",
+            status = tracing::field::Empty
+        )
+        .in_scope(|| {
+            tracing::Span::current().record("status", 200);
+            info_span!("plain").in_scope(|| info!("inside"));
+        });
+    };
+
+    let rendered = render(Pretty::new().ansi(false).show_span_fields(true), record);
+    let lines: Vec<&str> = rendered.lines().collect();
+    assert!(lines[0].contains(r#"request{user_id=7 path="/login" status=200} [ "#));
+    assert!(lines[1].contains("┕━ plain [ "));
+
+    let rendered = render(Pretty::new().ansi(false), record);
+    assert!(rendered.lines().next().unwrap().contains("request [ "));
+}
+
+#[test]
+#[allow(clippy::result_large_err)]
+fn test_max_message_len_keeps_captured_message() {
+    use std::sync::{Arc, Mutex};
+    use tracing_forest::printer::Formatter;
+    use tracing_forest::processor;
+
+    let trees = Arc::new(Mutex::new(Vec::new()));
+    let processor = processor::from_fn({
+        let trees = trees.clone();
+        move |tree| {
+            trees.lock().unwrap().push(tree);
+            Ok(())
+        }
+    });
+    let subscriber = Registry::default().with(ForestLayer::from(processor));
+    tracing::subscriber::with_default(subscriber, || info!("{}", "x".repeat(50)));
+
+    let trees = trees.lock().unwrap();
+    let rendered = Pretty::new()
+        .ansi(false)
+        .max_message_len(20)
+        .fmt(&trees[0])
+        .unwrap();
+
+    assert!(rendered.ends_with(&format!("[info]: {}…\n", "x".repeat(19))));
+    assert!(trees[0].event().unwrap().message() == Some("x".repeat(50).as_str()));
+}
+
+#[test]
+fn test_split_streams() {
+    let (stdout, stderr) = (MakeBuffer::new(), MakeBuffer::new());
+    let printer = Printer::new()
+        .writer(stdout.clone())
+        .stderr_writer(stderr.clone());
+
+    tracing::subscriber::with_default(Registry::default().with(ForestLayer::from(printer)), || {
+        info_span!("healthy").in_scope(|| {
+            info!("all good");
+            debug!("details");
+        });
+        info_span!("failing").in_scope(|| {
+            info!("starting");
+            error!("crashed");
+        });
+        warn!("standalone warning");
+        info!("standalone info");
+    });
+
+    let (stdout, stderr) = (stdout.contents(), stderr.contents());
+    assert!(stdout.contains("healthy") && stdout.contains("all good"));
+    assert!(stdout.contains("standalone info"));
+    assert!(!stdout.contains("failing") && !stdout.contains("standalone warning"));
+
+    // The whole tree goes to stderr, including its less severe events.
+    assert!(stderr.contains("failing") && stderr.contains("starting"));
+    assert!(stderr.contains("crashed"));
+    assert!(stderr.contains("standalone warning"));
+    assert!(!stderr.contains("healthy") && !stderr.contains("standalone info"));
+}
+
+#[test]
+fn test_split_streams_disabled() {
+    let (stdout, stderr) = (MakeBuffer::new(), MakeBuffer::new());
+    let printer = Printer::new()
+        .writer(stdout.clone())
+        .stderr_writer(stderr.clone())
+        .split_streams(false);
+
+    tracing::subscriber::with_default(Registry::default().with(ForestLayer::from(printer)), || {
+        error!("crashed");
+    });
+
+    assert!(stdout.contents().contains("crashed"));
+    assert!(stderr.contents().is_empty());
+}
+
+#[test]
+fn test_header() {
+    let buffer = MakeBuffer::new();
+    let printer = Printer::new()
+        .formatter(Pretty::new().ansi(false))
+        .writer(buffer.clone())
+        .header(|tree| match tree.span() {
+            Ok(span) => format!("=== {} — {:?} ===", span.name(), span.total_duration()),
+            Err(_) => "=== event ===\n".to_string(),
+        });
+    let subscriber = Registry::default().with(ForestLayer::from(printer));
+    tracing::subscriber::with_default(subscriber, || {
+        info_span!("request").in_scope(|| info!("handled"));
+        info!("standalone");
+    });
+
+    let rendered = buffer.contents();
+    let lines: Vec<&str> = rendered.lines().collect();
+    assert!(lines.len() == 5);
+    assert!(lines[0].starts_with("=== request — "));
+    assert!(lines[0].ends_with(" ==="));
+    assert!(lines[1].contains("request [ "));
+    assert!(lines[2].ends_with("┕━ ｉ [info]: handled"));
+    assert!(lines[3] == "=== event ===");
+    assert!(lines[4].ends_with("ｉ [info]: standalone"));
+}
@@ -0,0 +1,106 @@
+use std::fmt;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use tracing::field::{Field, Visit};
+use tracing::{Event, Level, Subscriber};
+use tracing_forest::processor::ReemitProcessor;
+use tracing_forest::{traits::*, util::*};
+use tracing_subscriber::layer::{Context, Layer};
+use tracing_subscriber::Registry;
+
+#[derive(Debug, Default, PartialEq)]
+struct Reemitted {
+    level: Option<Level>,
+    message: String,
+    span: String,
+    fields: String,
+}
+
+/// A downstream layer that records the events re-emitted by tracing-forest.
+#[derive(Clone, Default)]
+struct Downstream(Arc<Mutex<Vec<Reemitted>>>);
+
+impl Visit for Reemitted {
+    fn record_debug(&mut self, field: &Field, value: &dyn fmt::Debug) {
+        let value = format!("{:?}", value);
+        match field.name() {
+            "message" => self.message = value,
+            "span" => self.span = value,
+            "fields" => self.fields = value,
+            _ => {}
+        }
+    }
+}
+
+impl<S: Subscriber> Layer<S> for Downstream {
+    fn on_event(&self, event: &Event<'_>, _ctx: Context<'_, S>) {
+        if event.metadata().target() != "tracing_forest::reemit" {
+            return;
+        }
+
+        let mut reemitted = Reemitted {
+            level: Some(*event.metadata().level()),
+            ..Reemitted::default()
+        };
+        event.record(&mut reemitted);
+        self.0.lock().unwrap().push(reemitted);
+    }
+}
+
+fn emit() {
+    info_span!("request").in_scope(|| {
+        info!(user = "ferris", attempt = 2, "received");
+        info_span!("query").in_scope(|| {
+            error!("failed");
+            info!(forest.hidden = true, "hidden");
+        });
+    });
+    warn!("standalone");
+}
+
+#[test]
+fn test_reemit_to_downstream() {
+    let downstream = Downstream::default();
+    let processor = ReemitProcessor::new(Registry::default().with(downstream.clone()));
+    let subscriber = Registry::default().with(ForestLayer::from(processor));
+
+    tracing::subscriber::with_default(subscriber, emit);
+
+    let reemitted = downstream.0.lock().unwrap();
+    assert!(reemitted.len() == 3);
+    assert!(
+        reemitted[0]
+            == Reemitted {
+                level: Some(Level::INFO),
+                message: "received".to_string(),
+                span: "request".to_string(),
+                fields: r#"user="ferris" attempt=2"#.to_string(),
+            }
+    );
+    assert!(reemitted[1].level == Some(Level::ERROR));
+    assert!(reemitted[1].message == "failed");
+    assert!(reemitted[1].span == "request/query");
+    assert!(reemitted[1].fields.is_empty());
+    assert!(reemitted[2].level == Some(Level::WARN));
+    assert!(reemitted[2].span.is_empty());
+}
+
+#[test]
+fn test_reemit_not_recollected() {
+    let downstream = Downstream::default();
+    let trees = Arc::new(AtomicUsize::new(0));
+    let forest = ForestLayer::from(tracing_forest::processor::Sink).on_tree({
+        let trees = trees.clone();
+        move |_tree| {
+            trees.fetch_add(1, Ordering::Relaxed);
+        }
+    });
+    let processor = ReemitProcessor::new(Registry::default().with(forest).with(downstream.clone()));
+    let subscriber = Registry::default().with(ForestLayer::from(processor));
+
+    tracing::subscriber::with_default(subscriber, emit);
+
+    // The downstream `ForestLayer` doesn't collect the re-emitted events.
+    assert!(downstream.0.lock().unwrap().len() == 3);
+    assert!(trees.load(Ordering::Relaxed) == 0);
+}
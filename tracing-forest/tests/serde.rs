@@ -0,0 +1,242 @@
+#![cfg(feature = "tokio")]
+use std::error::Error;
+use tracing_forest::tree::{Tree, SCHEMA_VERSION};
+use tracing_forest::util::*;
+use tracing_forest::Tag;
+
+fn admin_tag(event: &Event) -> Option<Tag> {
+    Some(
+        Tag::builder()
+            .prefix("admin")
+            .level(*event.metadata().level())
+            .build(),
+    )
+}
+
+#[tokio::test]
+async fn test_schema_version() -> Result<(), Box<dyn Error>> {
+    let logs = tracing_forest::capture()
+        .set_tag(admin_tag)
+        .build()
+        .on(async {
+            info_span!("outer").in_scope(|| {
+                info!(answer = 42, "hello");
+            });
+        })
+        .await;
+
+    let json = serde_json::to_value(&logs[0])?;
+    assert!(json["schema_version"] == SCHEMA_VERSION);
+
+    // Nested nodes don't repeat the version.
+    assert!(json["Span"]["nodes"][0].get("schema_version").is_none());
+
+    let tree: Tree = serde_json::from_value(json)?;
+    let span = tree.span()?;
+    assert!(span.name() == "outer");
+
+    let event = span.nodes()[0].event()?;
+    assert!(event.message() == Some("hello"));
+    assert!(event.tag() == logs[0].span()?.nodes()[0].event()?.tag());
+    assert!(event.fields()[0].key() == "answer");
+    assert!(event.fields()[0].value() == "42");
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_schema_version_mismatch() -> Result<(), Box<dyn Error>> {
+    let logs = tracing_forest::capture()
+        .build()
+        .on(async {
+            info!("hello");
+        })
+        .await;
+
+    let mut json = serde_json::to_value(&logs[0])?;
+    json["schema_version"] = (SCHEMA_VERSION + 1).into();
+
+    let err = serde_json::from_value::<Tree>(json.clone()).unwrap_err();
+    assert!(err.to_string().contains(&format!(
+        "unsupported schema version {}",
+        SCHEMA_VERSION + 1
+    )));
+
+    json.as_object_mut().unwrap().remove("schema_version");
+    let err = serde_json::from_value::<Tree>(json).unwrap_err();
+    assert!(err.to_string().contains("missing field `schema_version`"));
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_migrate_old_schema_version() -> Result<(), Box<dyn Error>> {
+    let logs = tracing_forest::capture()
+        .build()
+        .on(async {
+            info_span!("outer", path = "/").in_scope(|| info!("hello"));
+        })
+        .await;
+
+    // Version 2 didn't have span fields or hidden events.
+    let mut json = serde_json::to_value(&logs[0])?;
+    json["schema_version"] = 2.into();
+    let span = json["Span"].as_object_mut().unwrap();
+    span.remove("fields");
+    span["nodes"][0]["Event"]
+        .as_object_mut()
+        .unwrap()
+        .remove("hidden");
+
+    let tree: Tree = serde_json::from_value(json)?;
+    let span = tree.span()?;
+    assert!(span.fields().is_empty());
+    assert!(!span.nodes()[0].event()?.is_hidden());
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_recorded_at_round_trip() -> Result<(), Box<dyn Error>> {
+    let logs = tracing_forest::capture()
+        .build()
+        .on(async {
+            info_span!("outer").in_scope(|| info!("hello"));
+        })
+        .await;
+
+    let json = serde_json::to_value(&logs[0])?;
+    let tree: Tree = serde_json::from_value(json.clone())?;
+    let (span, original) = (tree.span()?, logs[0].span()?);
+    assert!(span.recorded_at() == original.recorded_at());
+    let event = span.nodes()[0].event()?;
+    assert!(event.recorded_at() == original.nodes()[0].event()?.recorded_at());
+
+    // Version 4 didn't have recorded offsets.
+    let mut json = json;
+    json["schema_version"] = 4.into();
+    let span = json["Span"].as_object_mut().unwrap();
+    span.remove("recorded_at_nanos");
+    let tree: Tree = serde_json::from_value(json)?;
+    assert!(tree.span()?.recorded_at() == std::time::Duration::ZERO);
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_print_deserialized_forest() -> Result<(), Box<dyn Error + Send + Sync>> {
+    use tracing_forest::printer::MakeBuffer;
+    use tracing_forest::Printer;
+
+    let logs = tracing_forest::capture()
+        .build()
+        .on(async {
+            info!("before");
+            info_span!("request").in_scope(|| {
+                warn!(code = 503, "upstream unavailable");
+            });
+        })
+        .await;
+
+    let json = serde_json::to_string(&logs)?;
+    let replayed: Vec<Tree> = serde_json::from_str(&json)?;
+
+    let buffer = MakeBuffer::new();
+    Printer::new()
+        .writer(buffer.clone())
+        .print_forest(&replayed)?;
+
+    let rendered = buffer.contents();
+    assert!(rendered.contains("before"));
+    assert!(rendered.contains("request"));
+    assert!(rendered.contains("upstream unavailable"));
+    assert!(rendered.contains("code: 503"));
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_forest_json_round_trip() -> Result<(), Box<dyn Error>> {
+    use tracing_forest::tree::Forest;
+
+    let logs = tracing_forest::capture()
+        .set_tag(admin_tag)
+        .build()
+        .on(async {
+            info!("before");
+            info_span!("request", path = "/login").in_scope(|| {
+                warn!(code = 503, "upstream unavailable");
+                info_span!("retry").in_scope(|| {
+                    info!("succeeded");
+                });
+            });
+        })
+        .await;
+
+    let compact = logs.to_json()?;
+    let pretty = logs.to_json_pretty()?;
+    assert!(!compact.contains('\n'));
+    assert!(pretty.contains('\n'));
+
+    for json in [&compact, &pretty] {
+        let replayed = Forest::from_json(json)?;
+        assert!(replayed.len() == logs.len());
+        assert!(replayed.to_json()? == compact);
+        assert!(replayed.render() == logs.render());
+        assert!(replayed[1].span()?.fields() == logs[1].span()?.fields());
+    }
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_forest_json_round_trip_span_metadata() -> Result<(), Box<dyn Error>> {
+    use std::borrow::Cow;
+    use tracing::Level;
+    use tracing_forest::tree::{Forest, Span};
+    use tracing_subscriber::{layer::SubscriberExt, Registry};
+
+    let logs = tracing_forest::capture()
+        .build_with(|layer| {
+            Registry::default().with(layer.min_event_level(Level::INFO).span_name_fn(
+                |span, fields| match fields.first() {
+                    Some(field) => Cow::Owned(format!("{} {}", span.name(), field.value())),
+                    None => Cow::Borrowed(span.name()),
+                },
+            ))
+        })
+        .on(async {
+            let origin = info_span!("origin", forest.color = "cyan", forest.instrumented = true);
+            origin.in_scope(|| debug!("filtered"));
+
+            let worker = info_span!("worker", route = "/jobs");
+            worker.follows_from(&origin);
+            worker.in_scope(|| info!("working"));
+            drop(worker);
+            drop(origin);
+        })
+        .await;
+
+    let replayed = Forest::from_json(&logs.to_json()?)?;
+    let spans = |forest: &Forest| -> Vec<Span> { forest.spans().cloned().collect() };
+    assert!(spans(&replayed).len() == 2);
+
+    for (original, replayed) in spans(&logs).iter().zip(&spans(&replayed)) {
+        assert!(replayed.id() == original.id());
+        assert!(replayed.follows_from() == original.follows_from());
+        assert!(replayed.display_name() == original.display_name());
+        assert!(replayed.is_instrumented() == original.is_instrumented());
+        assert!(replayed.color() == original.color());
+        assert!(replayed.sampled() == original.sampled());
+        assert!(replayed.filtered_count() == original.filtered_count());
+    }
+
+    let origin = logs.spans().find(|span| span.name() == "origin").unwrap();
+    let worker = logs.spans().find(|span| span.name() == "worker").unwrap();
+    assert!(origin.is_instrumented());
+    assert!(origin.filtered_count() == 1);
+    assert!(worker.display_name() == "worker \"/jobs\"");
+    assert!(worker.follows_from() == [origin.id()]);
+
+    Ok(())
+}
@@ -0,0 +1,127 @@
+#![cfg(feature = "serde")]
+use std::error::Error;
+use std::io::Read;
+use std::net::TcpListener;
+use std::thread;
+use std::time::{Duration, Instant};
+use tracing::Level;
+use tracing_forest::processor::{Processor, SocketProcessor};
+use tracing_forest::tree::{Event, Shared, Tree};
+use tracing_forest::{traits::*, util::*};
+use tracing_subscriber::Registry;
+
+fn read_frame(reader: &mut impl Read) -> std::io::Result<serde_json::Value> {
+    let mut len = [0; 4];
+    reader.read_exact(&mut len)?;
+    let mut json = vec![0; u32::from_be_bytes(len) as usize];
+    reader.read_exact(&mut json)?;
+    Ok(serde_json::from_slice(&json)?)
+}
+
+fn emit<P: Processor + Send + Sync>(processor: P) {
+    tracing::subscriber::with_default(
+        Registry::default().with(ForestLayer::from(processor)),
+        || {
+            info_span!("request").in_scope(|| {
+                info!(path = "/login", "received");
+            });
+            warn!("standalone");
+        },
+    );
+}
+
+#[test]
+fn test_socket_processor_tcp() -> Result<(), Box<dyn Error>> {
+    let listener = TcpListener::bind("127.0.0.1:0")?;
+    let addr = listener.local_addr()?;
+
+    let server = thread::spawn(move || -> std::io::Result<Vec<serde_json::Value>> {
+        let (mut stream, _) = listener.accept()?;
+        (0..2).map(|_| read_frame(&mut stream)).collect()
+    });
+
+    emit(SocketProcessor::tcp(
+        addr.to_string(),
+        serde_json::to_string::<Tree>,
+    ));
+
+    let frames = server.join().unwrap()?;
+    assert!(frames[0]["Span"]["name"] == "request");
+    assert!(frames[0]["Span"]["nodes"][0]["Event"]["message"] == "received");
+    assert!(frames[1]["Event"]["message"] == "standalone");
+    Ok(())
+}
+
+#[cfg(unix)]
+#[test]
+fn test_socket_processor_unix() -> Result<(), Box<dyn Error>> {
+    use std::os::unix::net::UnixListener;
+
+    let path = std::env::temp_dir().join(format!("tracing-forest-{}.sock", std::process::id()));
+    let _ = std::fs::remove_file(&path);
+    let listener = UnixListener::bind(&path)?;
+
+    let server = thread::spawn(move || -> std::io::Result<Vec<serde_json::Value>> {
+        let (mut stream, _) = listener.accept()?;
+        (0..2).map(|_| read_frame(&mut stream)).collect()
+    });
+
+    emit(SocketProcessor::unix(&path, serde_json::to_string::<Tree>));
+
+    let frames = server.join().unwrap()?;
+    std::fs::remove_file(&path)?;
+    assert!(frames[0]["Span"]["name"] == "request");
+    assert!(frames[1]["Event"]["level"] == "WARN");
+    Ok(())
+}
+
+#[test]
+#[allow(clippy::result_large_err)]
+fn test_socket_processor_unreachable_does_not_block() {
+    // Nothing listens on the address, so every connection attempt fails.
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let addr = listener.local_addr().unwrap();
+    drop(listener);
+
+    let processor = SocketProcessor::tcp(addr.to_string(), serde_json::to_string::<Tree>)
+        .capacity(1)
+        .reconnect_delay(Duration::from_millis(10));
+
+    let event = || Tree::Event(Event::new(Shared::new(Level::INFO)));
+    let results: Vec<_> = (0..3).map(|_| processor.process(event())).collect();
+
+    // The buffer holds one frame while the background thread retries another,
+    // so the rest are handed back instead of blocking.
+    let err = results.into_iter().find_map(Result::err).unwrap();
+    assert!(err.to_string() == "the socket buffer is full");
+    assert!(err.tree.event().is_ok());
+
+    drop(processor);
+}
+
+#[test]
+#[allow(clippy::result_large_err)]
+fn test_socket_processor_drop_has_deadline() -> Result<(), Box<dyn Error>> {
+    // The collector accepts the connection but never reads from it, so writes
+    // block once the socket buffers fill up.
+    let listener = TcpListener::bind("127.0.0.1:0")?;
+    let addr = listener.local_addr()?;
+    let server = thread::spawn(move || listener.accept().map(|(stream, _)| stream));
+
+    let processor = SocketProcessor::tcp(addr.to_string(), |_: &Tree| {
+        Ok::<_, std::convert::Infallible>("x".repeat(1 << 20))
+    })
+    .capacity(64)
+    .shutdown_timeout(Duration::from_millis(100));
+
+    let event = || Tree::Event(Event::new(Shared::new(Level::INFO)));
+    for _ in 0..64 {
+        let _ = processor.process(event());
+    }
+    let _stream = server.join().unwrap()?;
+
+    let start = Instant::now();
+    drop(processor);
+    assert!(start.elapsed() < Duration::from_secs(3));
+    Ok(())
+}
@@ -0,0 +1,47 @@
+#![cfg(feature = "tokio")]
+use tracing_forest::tree::SpanStatus;
+use tracing_forest::{traits::*, util::*};
+use tracing_subscriber::Registry;
+
+#[tokio::test]
+async fn test_status_field() -> Result<(), Box<dyn std::error::Error>> {
+    let logs = tracing_forest::capture()
+        .build()
+        .on(async {
+            info_span!("unset").in_scope(|| {});
+            info_span!("ok", otel.status_code = "ok").in_scope(|| {});
+            info_span!("error", otel.status_code = "error").in_scope(|| {});
+
+            let span = info_span!("recorded", otel.status_code = tracing::field::Empty);
+            span.in_scope(|| {
+                error!("something went wrong");
+            });
+            span.record("otel.status_code", "ERROR");
+        })
+        .await;
+
+    assert!(logs.len() == 4);
+    assert!(logs[0].span()?.status() == SpanStatus::Unset);
+    assert!(logs[1].span()?.status() == SpanStatus::Ok);
+    assert!(logs[2].span()?.status() == SpanStatus::Error);
+    assert!(logs[3].span()?.status() == SpanStatus::Error);
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_custom_status_field() -> Result<(), Box<dyn std::error::Error>> {
+    let logs = tracing_forest::capture()
+        .build_with(|layer| Registry::default().with(layer.status_field("status")))
+        .on(async {
+            info_span!("default", otel.status_code = "error").in_scope(|| {});
+            info_span!("custom", status = "error").in_scope(|| {});
+        })
+        .await;
+
+    assert!(logs.len() == 2);
+    assert!(logs[0].span()?.status() == SpanStatus::Unset);
+    assert!(logs[1].span()?.status() == SpanStatus::Error);
+
+    Ok(())
+}
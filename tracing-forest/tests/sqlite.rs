@@ -0,0 +1,69 @@
+#![cfg(feature = "rusqlite")]
+use rusqlite::Connection;
+use std::error::Error;
+use std::sync::Arc;
+use tracing_forest::processor::SqliteProcessor;
+use tracing_forest::{traits::*, util::*};
+use tracing_subscriber::Registry;
+
+fn count(connection: &Connection, sql: &str) -> rusqlite::Result<i64> {
+    connection.query_row(sql, [], |row| row.get(0))
+}
+
+#[test]
+fn test_sqlite_processor() -> Result<(), Box<dyn Error>> {
+    let processor = Arc::new(SqliteProcessor::new(Connection::open_in_memory()?)?);
+
+    let subscriber = Registry::default().with(ForestLayer::from(processor.clone()));
+    tracing::subscriber::with_default(subscriber, || {
+        info_span!("request").in_scope(|| {
+            info!(path = "/login", method = "POST", "received");
+            info_span!("query").in_scope(|| {
+                error!(table = "users", "timed out");
+                warn!("retrying");
+            });
+        });
+        error!("outside");
+    });
+
+    let connection = processor.connection();
+    assert!(count(&connection, "SELECT COUNT(*) FROM spans")? == 2);
+    assert!(count(&connection, "SELECT COUNT(*) FROM events")? == 4);
+    assert!(count(&connection, "SELECT COUNT(*) FROM fields")? == 3);
+
+    assert!(
+        count(
+            &connection,
+            "SELECT COUNT(*) FROM events WHERE span_id IS NULL"
+        )? == 1
+    );
+    assert!(
+        count(
+            &connection,
+            "SELECT COUNT(*) FROM spans child JOIN spans parent ON child.parent_id = parent.id
+             WHERE child.name = 'query' AND parent.name = 'request'"
+        )? == 1
+    );
+
+    let errors_under_request = count(
+        &connection,
+        "WITH RECURSIVE under(id) AS (
+             SELECT id FROM spans WHERE name = 'request'
+             UNION ALL
+             SELECT spans.id FROM spans JOIN under ON spans.parent_id = under.id
+         )
+         SELECT COUNT(*) FROM events
+         WHERE level = 'ERROR' AND span_id IN (SELECT id FROM under)",
+    )?;
+    assert!(errors_under_request == 1);
+
+    let value: String = connection.query_row(
+        "SELECT value FROM fields JOIN events ON fields.event_id = events.id
+         WHERE events.message = 'timed out' AND fields.key = 'table'",
+        [],
+        |row| row.get(0),
+    )?;
+    assert!(value == "\"users\"");
+
+    Ok(())
+}
@@ -0,0 +1,47 @@
+use tracing_forest::printer::SummaryLine;
+use tracing_forest::util::*;
+
+mod common;
+use common::render;
+
+#[test]
+fn test_one_line_per_root_tree() {
+    let rendered = render(SummaryLine::new(), || {
+        info_span!("request").in_scope(|| {
+            info!("started");
+            info_span!("db").in_scope(|| {
+                warn!("slow query");
+                error!("query failed");
+            });
+            error!("request failed");
+        });
+        info_span!("healthcheck").in_scope(|| {});
+        warn!("standalone");
+    });
+    let lines: Vec<&str> = rendered.lines().collect();
+
+    assert!(lines.len() == 3);
+
+    assert!(lines[0].starts_with("[INFO] request "));
+    assert!(lines[0].ends_with(" events=4 errors=2"));
+
+    assert!(lines[1].starts_with("[INFO] healthcheck "));
+    assert!(lines[1].ends_with(" events=0 errors=0"));
+
+    assert!(lines[2] == "[WARN] standalone events=1 errors=0");
+}
+
+#[test]
+fn test_newlines_are_escaped() {
+    let rendered = render(SummaryLine::new(), || {
+        info_span!("request").in_scope(|| {});
+        warn!("line one\nline two\r\n[ERROR] forged events=0 errors=0");
+    });
+    let lines: Vec<&str> = rendered.lines().collect();
+
+    assert!(lines.len() == 2);
+    assert!(
+        lines[1]
+            == r"[WARN] line one\nline two\r\n[ERROR] forged events=0 errors=0 events=1 errors=0"
+    );
+}
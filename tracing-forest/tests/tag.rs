@@ -54,3 +54,74 @@ async fn test_kanidm_tag() -> Result<(), Box<dyn std::error::Error>> {
 
     Ok(())
 }
+
+#[tokio::test]
+async fn test_group_by_tag() -> Result<(), Box<dyn std::error::Error>> {
+    use std::collections::HashMap;
+    use tracing_forest::tree::Event;
+
+    let logs = tracing_forest::capture()
+        .set_tag(kanidm_tag)
+        .build()
+        .on(async {
+            info!(target: "admin", "first admin info");
+            error!(target: "request", "the request timed out");
+            info!(target: "admin", "second admin info");
+            error!(target: "request", "the request failed");
+            warn!(target: "admin", "an admin warning");
+        })
+        .await;
+
+    let mut by_tag: HashMap<Tag, Vec<&Event>> = HashMap::new();
+    for event in logs.events() {
+        by_tag.entry(event.tag().unwrap()).or_default().push(event);
+    }
+
+    assert!(by_tag.len() == 3);
+
+    let admin_info = Tag::builder().prefix("admin").level(Level::INFO).build();
+    let messages: Vec<_> = by_tag[&admin_info].iter().map(|e| e.message()).collect();
+    assert!(messages == [Some("first admin info"), Some("second admin info")]);
+
+    let request_error = Tag::builder().prefix("request").level(Level::ERROR).build();
+    assert!(by_tag[&request_error].len() == 2);
+    assert!(admin_info != request_error);
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_finalize_tag() -> Result<(), Box<dyn std::error::Error>> {
+    use std::time::Duration;
+    use tracing_forest::traits::*;
+    use tracing_subscriber::Registry;
+
+    let logs = tracing_forest::capture()
+        .build_with(|layer| {
+            Registry::default().with(layer.finalize_tag(|span| {
+                (span.total_duration() > Duration::from_millis(100))
+                    .then(|| Tag::builder().prefix("slow").level(Level::WARN).build())
+            }))
+        })
+        .on(async {
+            info_span!("request").in_scope(|| {
+                info_span!("query").in_scope(|| {
+                    std::thread::sleep(Duration::from_millis(120));
+                });
+                info_span!("cache").in_scope(|| {});
+            });
+        })
+        .await;
+
+    let request = logs[0].span()?;
+    assert!(request.tag().and_then(|tag| tag.prefix()) == Some("slow"));
+    assert!(
+        request.nodes()[0]
+            .span()?
+            .tag()
+            .and_then(|tag| tag.prefix())
+            == Some("slow")
+    );
+    assert!(request.nodes()[1].span()?.tag().is_none());
+    Ok(())
+}
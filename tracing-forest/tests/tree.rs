@@ -0,0 +1,784 @@
+#![cfg(feature = "tokio")]
+use tracing_forest::layer::ChildOrder;
+use tracing_forest::tree::Tree;
+use tracing_forest::{traits::*, util::*};
+use tracing_subscriber::Registry;
+
+#[tokio::test]
+async fn test_walk_mut() -> Result<(), Box<dyn std::error::Error>> {
+    let mut logs = tracing_forest::capture()
+        .build()
+        .on(async {
+            info!("root event");
+            info_span!("outer").in_scope(|| {
+                info!("first");
+                info_span!("inner").in_scope(|| {
+                    info!("second");
+                });
+            });
+        })
+        .await;
+
+    for tree in logs.iter_mut() {
+        tree.walk_mut(|node| {
+            if let Tree::Event(event) = node {
+                let message = event.message().map(str::to_uppercase);
+                event.set_message(message);
+            }
+        });
+    }
+
+    assert!(logs.len() == 2);
+    assert!(logs[0].event()?.message() == Some("ROOT EVENT"));
+
+    let outer = logs[1].span()?;
+    assert!(outer.nodes()[0].event()?.message() == Some("FIRST"));
+
+    let inner = outer.nodes()[1].span()?;
+    assert!(inner.name() == "inner");
+    assert!(inner.nodes()[0].event()?.message() == Some("SECOND"));
+
+    Ok(())
+}
+
+fn out_of_order_children() {
+    info_span!("parent").in_scope(|| {
+        let first = info_span!("first");
+        let second = info_span!("second");
+        drop(second);
+        drop(first);
+    });
+}
+
+#[tokio::test]
+async fn test_child_order_close_time() -> Result<(), Box<dyn std::error::Error>> {
+    let logs = tracing_forest::capture()
+        .build()
+        .on(async { out_of_order_children() })
+        .await;
+
+    let parent = logs[0].span()?;
+    assert!(parent.nodes()[0].span()?.name() == "second");
+    assert!(parent.nodes()[1].span()?.name() == "first");
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_child_order_start_time() -> Result<(), Box<dyn std::error::Error>> {
+    let logs = tracing_forest::capture()
+        .build_with(|layer| Registry::default().with(layer.child_order(ChildOrder::StartTime)))
+        .on(async { out_of_order_children() })
+        .await;
+
+    let parent = logs[0].span()?;
+    assert!(parent.nodes()[0].span()?.name() == "first");
+    assert!(parent.nodes()[1].span()?.name() == "second");
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_to_rows() -> Result<(), Box<dyn std::error::Error>> {
+    use tracing_forest::tree::TreeKind;
+
+    let logs = tracing_forest::capture()
+        .build()
+        .on(async {
+            info_span!("outer").in_scope(|| {
+                info!(a = 1, b = "two", "first");
+                info_span!("inner").in_scope(|| {
+                    warn!("second");
+                });
+                info!("third");
+            });
+        })
+        .await;
+
+    let rows = logs[0].to_rows();
+    assert!(rows.len() == 5);
+
+    let depths: Vec<usize> = rows.iter().map(|row| row.depth).collect();
+    assert!(depths == [0, 1, 1, 2, 1]);
+
+    for pair in rows.windows(2) {
+        // A row can be at most one level deeper than the row before it, and
+        // only if the row before it is a span.
+        if pair[1].depth > pair[0].depth {
+            assert!(pair[1].depth == pair[0].depth + 1);
+            assert!(pair[0].kind == TreeKind::Span);
+        }
+    }
+
+    assert!(rows[0].name_or_message == "outer");
+    assert!(rows[0].duration.is_some());
+    assert!(rows[1].kind == TreeKind::Event);
+    assert!(rows[1].fields == "a: 1, b: \"two\"");
+    assert!(rows[1].duration.is_none());
+    assert!(rows[3].level == Level::WARN);
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_span_is_error() -> Result<(), Box<dyn std::error::Error>> {
+    let logs = tracing_forest::capture()
+        .build()
+        .on(async {
+            info_span!("outer").in_scope(|| {
+                info!("fine");
+                debug_span!("inner").in_scope(|| {
+                    error!("not fine");
+                });
+            });
+            info_span!("quiet").in_scope(|| {
+                warn!("careful");
+            });
+        })
+        .await;
+
+    let outer = logs[0].span()?;
+    assert!(outer.is_error());
+    assert!(outer.max_level() == Level::ERROR);
+
+    let quiet = logs[1].span()?;
+    assert!(!quiet.is_error());
+    assert!(quiet.max_level() == Level::WARN);
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_count_matching() -> Result<(), Box<dyn std::error::Error>> {
+    let logs = tracing_forest::capture()
+        .build()
+        .on(async {
+            error!("root error");
+            info_span!("outer").in_scope(|| {
+                error!("first");
+                info!("fine");
+                info_span!("inner").in_scope(|| {
+                    error!("second");
+                    warn!("careful");
+                });
+            });
+            info_span!("quiet").in_scope(|| {
+                info!("nothing to see");
+            });
+        })
+        .await;
+
+    let is_error = |event: &tracing_forest::tree::Event| event.level() == Level::ERROR;
+
+    let errors: usize = logs.iter().map(|tree| tree.count_events(is_error)).sum();
+    assert!(errors == 3);
+
+    assert!(logs[1].count_events(is_error) == 2);
+    assert!(logs[1].count_events(|_| true) == 4);
+    assert!(logs[1].count_spans(|_| true) == 2);
+    assert!(logs[1].count_spans(|span| span.name() == "inner") == 1);
+    assert!(logs[0].count_spans(|_| true) == 0);
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_slow_children() -> Result<(), Box<dyn std::error::Error>> {
+    use std::time::Duration;
+
+    let logs = tracing_forest::capture()
+        .build()
+        .on(async {
+            info_span!("batch").in_scope(|| {
+                for (name, millis) in [("a", 10), ("b", 10), ("c", 50), ("d", 10), ("e", 10)] {
+                    info_span!("task", name).in_scope(|| {
+                        std::thread::sleep(Duration::from_millis(millis));
+                    });
+                }
+                info!("done");
+            });
+            info_span!("single").in_scope(|| {
+                info_span!("only").in_scope(|| {
+                    std::thread::sleep(Duration::from_millis(10));
+                });
+            });
+        })
+        .await;
+
+    let batch = logs[0].span()?;
+    assert!(batch.mean_child_duration() >= Duration::from_millis(18));
+
+    let slow = batch.slow_children(2.0);
+    assert!(slow.len() == 1);
+    assert!(slow[0].total_duration() >= Duration::from_millis(50));
+
+    let single = logs[1].span()?;
+    assert!(single.mean_child_duration() >= Duration::from_millis(10));
+    assert!(single.slow_children(0.0).is_empty());
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_root_classification() {
+    let logs = tracing_forest::capture()
+        .build()
+        .on(async {
+            info_span!("root").in_scope(|| {
+                info!("inside");
+            });
+            info!("outside");
+        })
+        .await;
+
+    assert!(logs[0].is_span());
+    assert!(!logs[0].is_event());
+    assert!(logs[0].root_name() == Some("root"));
+
+    assert!(logs[1].is_event());
+    assert!(!logs[1].is_span());
+    assert!(logs[1].root_name().is_none());
+}
+
+#[tokio::test]
+async fn test_child_spans_and_events() -> Result<(), Box<dyn std::error::Error>> {
+    let logs = tracing_forest::capture()
+        .build()
+        .on(async {
+            info_span!("parent").in_scope(|| {
+                info!("first");
+                info_span!("child").in_scope(|| {
+                    info!("nested");
+                });
+                info!("second");
+            });
+        })
+        .await;
+
+    let parent = logs[0].span()?;
+    assert!(parent.child_spans().count() == 1);
+    assert!(parent.child_spans().next().unwrap().name() == "child");
+
+    let messages: Vec<_> = parent.child_events().map(|event| event.message()).collect();
+    assert!(messages == [Some("first"), Some("second")]);
+
+    Ok(())
+}
+
+#[tracing::instrument(fields(forest.instrumented = true))]
+fn instrumented_handler() {
+    info!("handling");
+}
+
+#[tokio::test]
+async fn test_is_instrumented() -> Result<(), Box<dyn std::error::Error>> {
+    let logs = tracing_forest::capture()
+        .build()
+        .on(async {
+            instrumented_handler();
+            info_span!("manual").in_scope(|| {
+                info!("by hand");
+            });
+            info_span!("opted_out", forest.instrumented = false).in_scope(|| {});
+        })
+        .await;
+
+    let handler = logs[0].span()?;
+    assert!(handler.name() == "instrumented_handler");
+    assert!(handler.is_instrumented());
+
+    assert!(!logs[1].span()?.is_instrumented());
+    assert!(!logs[2].span()?.is_instrumented());
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_level_char() -> Result<(), Box<dyn std::error::Error>> {
+    use tracing_forest::printer::level_abbrev;
+
+    let logs = tracing_forest::capture()
+        .build()
+        .on(async {
+            trace!("trace");
+            debug!("debug");
+            info!("info");
+            warn!("warn");
+            error!("error");
+        })
+        .await;
+
+    let chars = logs
+        .iter()
+        .map(|tree| tree.event().map(|event| event.level_char()))
+        .collect::<Result<String, _>>()?;
+    assert!(chars == "TDIWE");
+
+    let abbrevs: Vec<_> = logs
+        .iter()
+        .map(|tree| level_abbrev(tree.event().unwrap().level()))
+        .collect();
+    assert!(abbrevs == ["TRC", "DBG", "INF", "WRN", "ERR"]);
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_to_pretty_lines() -> Result<(), Box<dyn std::error::Error>> {
+    use tracing_forest::printer::{strip_ansi, Pretty};
+
+    fn count_nodes(tree: &Tree) -> usize {
+        match tree {
+            Tree::Event(_) => 1,
+            Tree::Span(span) => 1 + span.nodes().iter().map(count_nodes).sum::<usize>(),
+        }
+    }
+
+    let logs = tracing_forest::capture()
+        .build()
+        .on(async {
+            info_span!("outer").in_scope(|| {
+                info!("first");
+                info_span!("inner").in_scope(|| {
+                    info!("second");
+                    warn!("third");
+                });
+            });
+        })
+        .await;
+
+    let lines = logs[0].to_pretty_lines(false);
+    // Pretty output has no span-close lines, so every line is a node.
+    assert!(lines.len() == count_nodes(&logs[0]));
+    assert!(lines.len() == 5);
+    assert!(lines.iter().all(|line| !line.ends_with('\n')));
+    assert!(lines[0].contains("outer"));
+    assert!(lines[4].contains("third"));
+    assert!(!lines.iter().any(|line| line.contains('\u{1b}')));
+
+    let colored = logs[0].to_pretty_lines(true);
+    assert!(colored == Pretty::new().ansi(true).wrap(None).fmt_lines(&logs[0]));
+    assert!(colored.len() == lines.len());
+    #[cfg(feature = "ansi")]
+    assert!(colored[0].contains('\u{1b}'));
+    let stripped: Vec<String> = colored.iter().map(|line| strip_ansi(line)).collect();
+    assert!(stripped == lines);
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_path_to_and_depth_of() -> Result<(), Box<dyn std::error::Error>> {
+    let logs = tracing_forest::capture()
+        .build()
+        .on(async {
+            info_span!("outer").in_scope(|| {
+                info!("shallow");
+                info_span!("sibling").in_scope(|| {
+                    info!("unrelated");
+                });
+                info_span!("inner").in_scope(|| {
+                    info_span!("leaf").in_scope(|| {
+                        warn!("deep");
+                    });
+                });
+            });
+        })
+        .await;
+
+    let is_deep = |node: &Tree| {
+        node.event()
+            .is_ok_and(|event| event.message() == Some("deep"))
+    };
+
+    let path = logs[0].path_to(is_deep);
+    assert!(path == Some(vec!["outer", "inner", "leaf"]));
+    assert!(logs[0].span()?.depth_of(is_deep) == Some(3));
+
+    let is_inner = |node: &Tree| node.span().is_ok_and(|span| span.name() == "inner");
+    assert!(logs[0].path_to(is_inner) == Some(vec!["outer", "inner"]));
+    assert!(logs[0].span()?.depth_of(is_inner) == Some(1));
+
+    assert!(logs[0].path_to(|node| node.is_span()) == Some(vec!["outer"]));
+    assert!(logs[0].path_to(|_| false).is_none());
+    assert!(logs[0].span()?.depth_of(|_| false).is_none());
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_event_from_drop_is_last_child() -> Result<(), Box<dyn std::error::Error>> {
+    struct Guard(tracing::Span);
+
+    impl Drop for Guard {
+        fn drop(&mut self) {
+            info!(parent: &self.0, "guard dropped");
+        }
+    }
+
+    struct ScopeGuard;
+
+    impl Drop for ScopeGuard {
+        fn drop(&mut self) {
+            info!("scope guard dropped");
+        }
+    }
+
+    let logs = tracing_forest::capture()
+        .build()
+        .on(async {
+            info_span!("scoped").in_scope(|| {
+                let _guard = ScopeGuard;
+                info!("first");
+            });
+
+            // The guard holds the last handle to the span, so the span closes
+            // as the guard drops.
+            let guard = Guard(info_span!("owned"));
+            guard.0.in_scope(|| info!("first"));
+            drop(guard);
+        })
+        .await;
+
+    assert!(logs.len() == 2);
+
+    let scoped = logs[0].span()?;
+    assert!(scoped.nodes().len() == 2);
+    assert!(scoped.nodes()[1].event()?.message() == Some("scope guard dropped"));
+
+    let owned = logs[1].span()?;
+    assert!(owned.name() == "owned");
+    assert!(owned.nodes().len() == 2);
+    assert!(owned.nodes()[1].event()?.message() == Some("guard dropped"));
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_event_for_closing_span_is_root() -> Result<(), Box<dyn std::error::Error>> {
+    use tracing::span::Id;
+    use tracing::Subscriber;
+    use tracing_subscriber::layer::{Context, Layer};
+
+    // Layers added later see `on_close` after the `ForestLayer` has finished
+    // the span.
+    struct LogOnClose;
+
+    impl<S: Subscriber> Layer<S> for LogOnClose {
+        fn on_close(&self, id: Id, _ctx: Context<S>) {
+            info!(parent: id, "closing");
+        }
+    }
+
+    let logs = tracing_forest::capture()
+        .build_with(|layer| Registry::default().with(layer).with(LogOnClose))
+        .on(async {
+            info_span!("closed").in_scope(|| info!("inside"));
+        })
+        .await;
+
+    assert!(logs.len() == 2);
+    assert!(logs[0].span()?.nodes().len() == 1);
+    assert!(logs[1].event()?.message() == Some("closing"));
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_duration_by_name() -> Result<(), Box<dyn std::error::Error>> {
+    use std::time::Duration;
+
+    fn recurse(depth: usize) {
+        info_span!("recurse").in_scope(|| {
+            std::thread::sleep(Duration::from_millis(5));
+            if depth > 1 {
+                recurse(depth - 1);
+            }
+        });
+    }
+
+    let logs = tracing_forest::capture()
+        .build()
+        .on(async {
+            info_span!("root").in_scope(|| {
+                recurse(3);
+                info_span!("leaf").in_scope(|| recurse(1));
+            });
+        })
+        .await;
+
+    let root = logs[0].span()?;
+    let durations = root.duration_by_name();
+    assert!(durations.len() == 3);
+
+    // The four `recurse` spans at different depths are summed together.
+    let outer = root.nodes()[0].span()?;
+    let middle = outer.nodes()[0].span()?;
+    let inner = middle.nodes()[0].span()?;
+    let nested = root.nodes()[1].span()?.nodes()[0].span()?;
+    let expected = [outer, middle, inner, nested]
+        .iter()
+        .map(|span| span.base_duration())
+        .sum::<Duration>();
+
+    assert!(durations["recurse"] == expected);
+    assert!(durations["recurse"] >= Duration::from_millis(20));
+    assert!(durations["root"] == root.base_duration());
+    assert!(durations["leaf"] == root.nodes()[1].span()?.base_duration());
+
+    Ok(())
+}
+
+#[test]
+fn test_build_tree_from_shared() -> Result<(), Box<dyn std::error::Error>> {
+    use chrono::{TimeZone, Utc};
+    use tracing_forest::tree::{Event, Field, Shared, Span};
+    use uuid::Uuid;
+
+    let uuid = Uuid::new_v4();
+    let timestamp = Utc.timestamp_millis_opt(1_000).unwrap();
+    let shared = Shared::new(Level::ERROR)
+        .with_uuid(uuid)
+        .with_timestamp(timestamp);
+
+    assert!(shared.level() == Level::ERROR);
+    assert!(shared.uuid() == uuid);
+    assert!(shared.timestamp() == timestamp);
+
+    let mut event = Event::new(shared.clone());
+    event.set_message(Some("disk full".to_string()));
+    event.push_field(Field::new("host", "db-1".to_string()));
+
+    assert!(event.uuid() == uuid);
+    assert!(event.timestamp() == timestamp);
+    assert!(event.fields()[0].value() == "db-1");
+
+    let mut span = Span::new(Shared::new(Level::INFO).with_uuid(uuid), "import");
+    span.push_node(Tree::Event(event));
+    let tree = Tree::Span(span);
+
+    let lines = tree.to_pretty_lines(false);
+    assert!(lines.len() == 2);
+    assert!(lines[0].contains("INFO     import [ "));
+    assert!(lines[1].starts_with(&format!("{} {}", uuid, timestamp.to_rfc3339())));
+    assert!(lines[1].ends_with("ERROR    ┕━ 🚨 [error]: disk full | host: db-1"));
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_recorded_at_is_monotonic() -> Result<(), Box<dyn std::error::Error>> {
+    let logs = tracing_forest::capture()
+        .build()
+        .on(async {
+            info_span!("outer").in_scope(|| {
+                info!("first");
+                info!("second");
+                info_span!("inner").in_scope(|| {});
+            });
+            info!("after");
+        })
+        .await;
+
+    let outer = logs[0].span()?;
+    let first = outer.nodes()[0].event()?;
+    let second = outer.nodes()[1].event()?;
+    let inner = outer.nodes()[2].span()?;
+    let after = logs[1].event()?;
+
+    assert!(outer.recorded_at() < first.recorded_at());
+    assert!(first.recorded_at() < second.recorded_at());
+    assert!(second.recorded_at() < inner.recorded_at());
+    assert!(inner.recorded_at() < after.recorded_at());
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_message_contains_is_case_sensitive() -> Result<(), Box<dyn std::error::Error>> {
+    let logs = tracing_forest::capture()
+        .build()
+        .on(async {
+            info!("Connection refused");
+            info!(port = 80);
+        })
+        .await;
+
+    let refused = logs[0].event()?;
+    assert!(refused.message_contains("refused"));
+    assert!(refused.message_contains("Connection"));
+    assert!(!refused.message_contains("connection"));
+    assert!(!logs[1].event()?.message_contains(""));
+    Ok(())
+}
+
+#[cfg(feature = "regex")]
+#[tokio::test]
+async fn test_message_matches_anchors() -> Result<(), Box<dyn std::error::Error>> {
+    use regex::Regex;
+
+    let logs = tracing_forest::capture()
+        .build()
+        .on(async {
+            info!("retrying request 3");
+        })
+        .await;
+
+    let event = logs[0].event()?;
+    assert!(event.message_matches(&Regex::new(r"request \d+")?));
+    assert!(event.message_matches(&Regex::new(r"^retrying")?));
+    assert!(!event.message_matches(&Regex::new(r"^request")?));
+    assert!(event.message_matches(&Regex::new(r"\d$")?));
+    assert!(!event.message_matches(&Regex::new(r"^retrying$")?));
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_total_events_and_field_frequency() -> Result<(), Box<dyn std::error::Error>> {
+    let logs = tracing_forest::capture()
+        .build()
+        .on(async {
+            info_span!("request").in_scope(|| {
+                info!(user = "alice", "received");
+                info_span!("query").in_scope(|| {
+                    info!(table = "users", rows = 3, "selected");
+                    warn!(user = "alice", table = "users", "slow");
+                });
+                info!("responded");
+            });
+        })
+        .await;
+
+    let request = logs[0].span()?;
+    assert!(request.total_events() == 4);
+    assert!(request.nodes()[1].span()?.total_events() == 2);
+
+    let frequency = request.field_frequency();
+    assert!(frequency.len() == 3);
+    assert!(frequency["user"] == 2);
+    assert!(frequency["table"] == 2);
+    assert!(frequency["rows"] == 1);
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_structural_hash() -> Result<(), Box<dyn std::error::Error>> {
+    fn handle(id: u32, extra: bool) {
+        info_span!("request", id).in_scope(|| {
+            info!(user = id, "received");
+            info_span!("query").in_scope(|| {
+                std::thread::sleep(std::time::Duration::from_millis(u64::from(id)));
+                warn!(rows = id * 10, "slow");
+            });
+            if extra {
+                info!("retried");
+            }
+        });
+    }
+
+    let logs = tracing_forest::capture()
+        .build()
+        .on(async {
+            handle(1, false);
+            handle(2, false);
+            handle(3, true);
+            info!("standalone");
+        })
+        .await;
+
+    // Different ids, field values, and durations don't change the hash.
+    assert!(logs[0].structural_hash() == logs[1].structural_hash());
+    assert!(logs[0].structural_hash() != logs[2].structural_hash());
+    assert!(logs[0].structural_hash() != logs[3].structural_hash());
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_coalesce_sibling_spans() -> Result<(), Box<dyn std::error::Error>> {
+    let logs = tracing_forest::capture()
+        .build()
+        .on(async {
+            info_span!("batch").in_scope(|| {
+                for i in 0..3 {
+                    info_span!("item").in_scope(|| {
+                        info!("processing {}", i);
+                        info_span!("save").in_scope(|| {});
+                    });
+                }
+                info_span!("flush").in_scope(|| {});
+                info_span!("item").in_scope(|| {
+                    info!("straggler");
+                });
+            });
+        })
+        .await;
+
+    let original = logs[0].span()?;
+    let coalesced = logs[0].coalesce_sibling_spans();
+    let batch = coalesced.span()?;
+
+    // The three adjacent `item` spans merge, but the one after `flush` doesn't.
+    let names: Vec<&str> = batch.child_spans().map(|span| span.name()).collect();
+    assert!(names == ["item", "flush", "item"]);
+
+    let item = batch.nodes()[0].span()?;
+    let items: Vec<_> = original.child_spans().take(3).collect();
+    let total = items.iter().map(|span| span.total_duration()).sum();
+    assert!(item.total_duration() == total);
+    assert!(item.activity().enters == 3);
+    assert!(item.activity().events == 3);
+
+    // Children are concatenated in order, and their own adjacent `save` spans
+    // aren't adjacent, so they stay separate.
+    let children: Vec<String> = item
+        .nodes()
+        .iter()
+        .map(|node| match node.event() {
+            Ok(event) => event.message().unwrap_or_default().to_owned(),
+            Err(_) => node.root_name().unwrap_or_default().to_owned(),
+        })
+        .collect();
+    assert!(
+        children
+            == [
+                "processing 0",
+                "save",
+                "processing 1",
+                "save",
+                "processing 2",
+                "save"
+            ]
+    );
+
+    assert!(batch.nodes()[2].span()?.nodes()[0].event()?.message() == Some("straggler"));
+    assert!(original.nodes().len() == 5);
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_iterate_children() -> Result<(), Box<dyn std::error::Error>> {
+    let logs = tracing_forest::capture()
+        .build()
+        .on(async {
+            info_span!("root").in_scope(|| {
+                info!("first");
+                info_span!("inner").in_scope(|| {
+                    info!("nested");
+                });
+                info!("last");
+            });
+        })
+        .await;
+
+    let span = logs[0].span()?;
+    let mut children = Vec::new();
+    for child in span {
+        children.push(child);
+    }
+    assert!(children.len() == 3);
+    assert!(children[1].span()?.name() == "inner");
+
+    assert!((&logs[0]).into_iter().count() == 3);
+    assert!((&span.nodes()[0]).into_iter().count() == 0);
+    Ok(())
+}
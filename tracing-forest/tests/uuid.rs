@@ -0,0 +1,271 @@
+#![cfg(all(feature = "tokio", feature = "uuid"))]
+use tracing_forest::util::*;
+use uuid::Uuid;
+
+#[tokio::test]
+async fn test_event_forest_id() -> Result<(), Box<dyn std::error::Error>> {
+    let id = Uuid::new_v4();
+    let span_id = Uuid::new_v4();
+
+    let logs = tracing_forest::capture()
+        .build()
+        .on(async {
+            info!(forest.id = %id, "root with id");
+            info!("root without id");
+            info_span!("my_span", uuid = %span_id).in_scope(|| {
+                info!(forest.id = %id, "nested with id");
+                info!("nested without id");
+                info!(forest.id = "not a uuid", "nested with invalid id");
+            });
+        })
+        .await;
+
+    assert!(logs.len() == 3);
+
+    let root_with_id = logs[0].event()?;
+    assert!(root_with_id.uuid() == id);
+    assert!(root_with_id.fields().is_empty());
+
+    assert!(logs[1].event()?.uuid() == Uuid::nil());
+
+    let span = logs[2].span()?;
+    assert!(span.uuid() == span_id);
+
+    let nested_with_id = span.nodes()[0].event()?;
+    assert!(nested_with_id.uuid() == id);
+    assert!(nested_with_id.fields().is_empty());
+
+    assert!(span.nodes()[1].event()?.uuid() == span_id);
+
+    let invalid = span.nodes()[2].event()?;
+    assert!(invalid.uuid() == span_id);
+    assert!(invalid.fields().len() == 1);
+    assert!(invalid.fields()[0].key() == "forest.id");
+    assert!(invalid.fields()[0].value() == "\"not a uuid\"");
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_parent_uuid() -> Result<(), Box<dyn std::error::Error>> {
+    let root_id = Uuid::new_v4();
+    let child_id = Uuid::new_v4();
+
+    let logs = tracing_forest::capture()
+        .build()
+        .on(async {
+            info!("outside");
+            info_span!("root", uuid = %root_id).in_scope(|| {
+                info_span!("child", uuid = %child_id).in_scope(|| {
+                    info!("inside");
+                });
+            });
+        })
+        .await;
+
+    assert!(logs[0].event()?.span_uuid().is_none());
+
+    let root = logs[1].span()?;
+    assert!(root.parent_uuid().is_none());
+
+    let child = root.nodes()[0].span()?;
+    assert!(child.parent_uuid() == Some(root_id));
+    assert!(child.nodes()[0].event()?.span_uuid() == Some(child_id));
+
+    Ok(())
+}
+
+fn colliding_spans(id: Uuid) {
+    info_span!("root").in_scope(|| {
+        info_span!("first", uuid = %id).in_scope(|| {
+            info!("in first");
+        });
+        info_span!("second", uuid = %id).in_scope(|| {
+            info_span!("nested").in_scope(|| {
+                info!("in second");
+            });
+        });
+    });
+}
+
+#[tokio::test]
+async fn test_uuid_collisions_ignored_by_default() -> Result<(), Box<dyn std::error::Error>> {
+    let id = Uuid::new_v4();
+
+    let logs = tracing_forest::capture()
+        .build()
+        .on(async { colliding_spans(id) })
+        .await;
+
+    let root = logs[0].span()?;
+    assert!(root.nodes()[0].span()?.uuid() == id);
+    assert!(root.nodes()[1].span()?.uuid() == id);
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_uuid_collisions_regenerate() -> Result<(), Box<dyn std::error::Error>> {
+    use tracing_forest::layer::UuidCollisionPolicy;
+    use tracing_subscriber::layer::SubscriberExt;
+    use tracing_subscriber::Registry;
+
+    let id = Uuid::new_v4();
+
+    let logs = tracing_forest::capture()
+        .build_with(|layer| {
+            Registry::default().with(layer.uuid_collisions(UuidCollisionPolicy::Regenerate))
+        })
+        .on(async { colliding_spans(id) })
+        .await;
+
+    let root = logs[0].span()?;
+    let first = root.nodes()[0].span()?;
+    let second = root.nodes()[1].span()?;
+    assert!(first.uuid() == id);
+    assert!(second.uuid() != id);
+    assert!(second.uuid() != root.uuid());
+    assert!(second.parent_uuid() == Some(root.uuid()));
+
+    // Nodes that inherited the duplicate uuid follow the regenerated one.
+    let nested = second.nodes()[0].span()?;
+    assert!(nested.uuid() == second.uuid());
+    assert!(nested.parent_uuid() == Some(second.uuid()));
+    let event = nested.nodes()[0].event()?;
+    assert!(event.uuid() == second.uuid());
+    assert!(event.span_uuid() == Some(second.uuid()));
+
+    Ok(())
+}
+
+#[test]
+fn test_uuid_collisions_error() {
+    use std::panic::{self, AssertUnwindSafe};
+    use tracing_forest::layer::UuidCollisionPolicy;
+    use tracing_forest::printer::MakeBuffer;
+    use tracing_forest::{ForestLayer, Printer};
+    use tracing_subscriber::layer::SubscriberExt;
+    use tracing_subscriber::Registry;
+
+    let id = Uuid::new_v4();
+    let buffer = MakeBuffer::new();
+    let layer = ForestLayer::from(Printer::new().writer(buffer.clone()))
+        .uuid_collisions(UuidCollisionPolicy::Error);
+    let subscriber = Registry::default().with(layer);
+
+    let result = panic::catch_unwind(AssertUnwindSafe(|| {
+        tracing::subscriber::with_default(subscriber, || colliding_spans(id));
+    }));
+
+    assert!(result.is_err());
+    assert!(buffer.contents().is_empty());
+}
+
+#[test]
+fn test_uuid_collisions_error_uses_fallback() {
+    use tracing_forest::layer::UuidCollisionPolicy;
+    use tracing_forest::printer::MakeBuffer;
+    use tracing_forest::{ForestLayer, Printer, Processor};
+    use tracing_subscriber::layer::SubscriberExt;
+    use tracing_subscriber::Registry;
+
+    let id = Uuid::new_v4();
+    let primary = MakeBuffer::new();
+    let fallback = MakeBuffer::new();
+    let processor = Printer::new()
+        .writer(primary.clone())
+        .or(Printer::new().writer(fallback.clone()));
+    let layer = ForestLayer::from(processor).uuid_collisions(UuidCollisionPolicy::Error);
+    let subscriber = Registry::default().with(layer);
+
+    tracing::subscriber::with_default(subscriber, || colliding_spans(id));
+
+    assert!(primary.contents().is_empty());
+    assert!(fallback.contents().contains("second"));
+}
+
+#[tokio::test]
+async fn test_subtree_by_uuid() -> Result<(), Box<dyn std::error::Error>> {
+    let child_id = Uuid::new_v4();
+    let event_id = Uuid::new_v4();
+
+    let logs = tracing_forest::capture()
+        .build()
+        .on(async {
+            info_span!("unrelated").in_scope(|| info!("noise"));
+            info_span!("root").in_scope(|| {
+                info!("before");
+                info_span!("child", uuid = %child_id).in_scope(|| {
+                    info!("inside child");
+                    info_span!("grandchild").in_scope(|| {
+                        info!(forest.id = %event_id, "tagged");
+                    });
+                });
+            });
+        })
+        .await;
+
+    let subtree = logs.subtree_by_uuid(child_id).ok_or("child not found")?;
+    let child = subtree.span()?;
+    assert!(child.name() == "child");
+    assert!(child.nodes().len() == 2);
+
+    let rendered = subtree.to_pretty_lines(false);
+    assert!(rendered.len() == 4);
+    assert!(rendered[0].contains("child [ "));
+
+    let event = logs
+        .subtree_by_uuid(event_id)
+        .ok_or("event not found")?
+        .event()?;
+    assert!(event.message() == Some("tagged"));
+
+    assert!(logs.subtree_by_uuid(Uuid::new_v4()).is_none());
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_attribute_follows_from() -> Result<(), Box<dyn std::error::Error>> {
+    use std::time::Duration;
+
+    let origin_id = Uuid::new_v4();
+    let worker_id = Uuid::new_v4();
+
+    let logs = tracing_forest::capture()
+        .build()
+        .on(async {
+            let origin = info_span!("origin", uuid = %origin_id);
+            origin.in_scope(|| info!("handing off"));
+
+            let worker = info_span!("worker", uuid = %worker_id);
+            worker.follows_from(&origin);
+            worker.in_scope(|| std::thread::sleep(Duration::from_millis(10)));
+            drop(worker);
+            drop(origin);
+        })
+        .await;
+
+    let origin = logs
+        .subtree_by_uuid(origin_id)
+        .ok_or("origin not found")?
+        .span()?;
+    let worker = logs
+        .subtree_by_uuid(worker_id)
+        .ok_or("worker not found")?
+        .span()?;
+
+    let attributed = logs.attribute_follows_from();
+    assert!(attributed.len() == 1);
+    assert!(attributed[&origin.id()] == worker.total_duration());
+    assert!(attributed[&origin.id()] >= Duration::from_millis(10));
+    assert!(!attributed.contains_key(&worker.id()));
+
+    #[cfg(feature = "json")]
+    {
+        let replayed = tracing_forest::tree::Forest::from_json(&logs.to_json()?)?;
+        assert!(replayed.attribute_follows_from() == attributed);
+    }
+
+    Ok(())
+}
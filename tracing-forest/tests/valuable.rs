@@ -0,0 +1,126 @@
+#![cfg(feature = "valuable")]
+use tracing_forest::tree::FieldValue;
+use valuable::Valuable;
+
+#[derive(Valuable)]
+struct User {
+    name: &'static str,
+    age: u8,
+    address: Address,
+    roles: Vec<Role>,
+}
+
+#[derive(Valuable)]
+struct Address {
+    city: &'static str,
+}
+
+#[derive(Valuable)]
+enum Role {
+    Admin,
+    Member { team: &'static str },
+}
+
+fn user() -> User {
+    User {
+        name: "alice",
+        age: 42,
+        address: Address { city: "Paris" },
+        roles: vec![Role::Admin, Role::Member { team: "infra" }],
+    }
+}
+
+fn string(value: &str) -> FieldValue {
+    FieldValue::String(value.to_owned())
+}
+
+#[test]
+fn test_field_value_from_valuable() {
+    let value = FieldValue::from(user().as_value());
+
+    let expected = FieldValue::Map(vec![
+        ("name".to_owned(), string("alice")),
+        ("age".to_owned(), FieldValue::U64(42)),
+        (
+            "address".to_owned(),
+            FieldValue::Map(vec![("city".to_owned(), string("Paris"))]),
+        ),
+        (
+            "roles".to_owned(),
+            FieldValue::List(vec![
+                string("Admin"),
+                FieldValue::Map(vec![(
+                    "Member".to_owned(),
+                    FieldValue::Map(vec![("team".to_owned(), string("infra"))]),
+                )]),
+            ]),
+        ),
+    ]);
+
+    assert!(value == expected);
+    assert!(
+        value.to_string()
+            == r#"{"name": "alice", "age": 42, "address": {"city": "Paris"}, "roles": ["Admin", {"Member": {"team": "infra"}}]}"#
+    );
+}
+
+#[cfg(feature = "serde")]
+#[test]
+fn test_structured_field_is_nested_in_json() -> Result<(), Box<dyn std::error::Error>> {
+    use tracing::Level;
+    use tracing_forest::tree::{Event, Field, Shared, Tree};
+
+    let mut event = Event::new(Shared::new(Level::INFO));
+    let value = FieldValue::from(user().as_value());
+    event.push_field(Field::with_structured_value("user", value.clone()));
+    let tree = Tree::Event(event);
+
+    let json = serde_json::to_value(&tree)?;
+    let user = &json["Event"]["fields"]["user"];
+    assert!(user["name"] == "alice");
+    assert!(user["age"] == 42);
+    assert!(user["address"]["city"] == "Paris");
+    assert!(user["roles"][0] == "Admin");
+    assert!(user["roles"][1]["Member"]["team"] == "infra");
+
+    // `serde_json::Value` sorts keys, so round trip through a string instead.
+    let tree: Tree = serde_json::from_str(&serde_json::to_string(&tree)?)?;
+    let field = &tree.event()?.fields()[0];
+    assert!(field.structured_value() == Some(&value));
+    assert!(field.value() == value.to_string());
+
+    Ok(())
+}
+
+/// Recording structured values requires building with
+/// `RUSTFLAGS="--cfg tracing_unstable"`.
+#[cfg(all(tracing_unstable, feature = "serde", feature = "tokio"))]
+#[tokio::test]
+async fn test_valuable_field_is_nested_in_json() -> Result<(), Box<dyn std::error::Error>> {
+    use tracing::info;
+    use tracing_forest::tree::Tree;
+
+    let logs = tracing_forest::capture()
+        .build()
+        .on(async {
+            info!(user = user().as_value(), "logged in");
+        })
+        .await;
+
+    let field = &logs[0].event()?.fields()[0];
+    assert!(field.key() == "user");
+    assert!(field.structured_value() == Some(&FieldValue::from(user().as_value())));
+
+    let json = serde_json::to_value(&logs[0])?;
+    let user = &json["Event"]["fields"]["user"];
+    assert!(user["name"] == "alice");
+    assert!(user["address"]["city"] == "Paris");
+    assert!(user["roles"][1]["Member"]["team"] == "infra");
+
+    // The nested structure survives a round trip, as long as the order of
+    // keys is preserved.
+    let tree: Tree = serde_json::from_str(&serde_json::to_string(&logs[0])?)?;
+    assert!(tree.event()?.fields()[0].structured_value() == field.structured_value());
+
+    Ok(())
+}
@@ -0,0 +1,49 @@
+#![cfg(all(feature = "tokio", feature = "yaml"))]
+use serde::Deserialize;
+use std::error::Error;
+use tracing_forest::printer::{Formatter, Yaml};
+use tracing_forest::tree::DurationFormat;
+use tracing_forest::util::*;
+
+#[tokio::test]
+async fn test_yaml_documents() -> Result<(), Box<dyn Error>> {
+    let logs = tracing_forest::capture()
+        .build()
+        .on(async {
+            info_span!("request").in_scope(|| {
+                warn!(code = 503, "upstream unavailable");
+            });
+            info!("standalone");
+        })
+        .await;
+
+    let yaml = Yaml::new();
+    let mut output = String::new();
+    for tree in &logs {
+        output.push_str(&yaml.fmt(tree)?);
+    }
+    assert!(output.starts_with("---\n"));
+
+    let documents = serde_yaml::Deserializer::from_str(&output)
+        .map(serde_yaml::Value::deserialize)
+        .collect::<Result<Vec<_>, _>>()?;
+    assert!(documents.len() == 2);
+
+    let span = &documents[0]["Span"];
+    assert!(span["name"] == "request");
+    assert!(span["level"] == "INFO");
+    assert!(span["nanos_total"].is_u64());
+    assert!(span["nanos_nested"].is_u64());
+    assert!(span["nodes"][0]["Event"]["message"] == "upstream unavailable");
+    assert!(span["nodes"][0]["Event"]["fields"]["code"] == "503");
+    assert!(documents[1]["Event"]["message"] == "standalone");
+
+    let seconds = Yaml::new()
+        .duration_format(DurationFormat::Seconds)
+        .fmt(&logs[0])?;
+    let document: serde_yaml::Value = serde_yaml::from_str(&seconds)?;
+    assert!(document["Span"]["seconds_total"].is_f64());
+    assert!(document["Span"].get("nanos_total").is_none());
+
+    Ok(())
+}